@@ -1,5 +1,8 @@
 shell /ini/palettes/gruvbox-dark.sh
 read /ini/fonts/zap-light-8x16.psf => /dev/vga/font
+net filter load
 read /ini/banner.txt
+# Uncomment to synchronize the clock with the server defined in /ini/ntp
+# ntp --set
 user login
 shell
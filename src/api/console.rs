@@ -1,6 +1,7 @@
 use crate::sys;
 
 use alloc::string::ToString;
+use alloc::vec::Vec;
 use core::fmt;
 
 pub use crate::sys::console::{EOT_KEY, ETX_KEY};
@@ -102,21 +103,57 @@ fn color_to_bg(name: &str) -> Option<usize> {
 pub fn is_printable(c: char) -> bool {
     if cfg!(feature = "video") {
         // Check if the char can be converted to ASCII or Extended ASCII before
-        // asking the VGA driver if it's printable.
-        ((c as u32) < 0xFF) && sys::vga::is_printable(c as u8)
+        // asking the VGA driver if it's printable. The other chars are
+        // displayed with the glyphs of the unicode table of the font.
+        if (c as u32) < 0xFF {
+            sys::vga::is_printable(c as u8)
+        } else {
+            !c.is_control()
+        }
     } else {
         true // TODO
     }
 }
 
-// The size of the screen in VGA Text Mode is 80x25
+// The size of the screen in VGA Text Mode is 80x25, and the size of the
+// serial console is given by the remote terminal
 
 pub fn cols() -> usize {
-    let n = 80; // chars
+    let n = if cfg!(feature = "video") { 80 } else { sys::serial::cols() };
     sys::process::env("COLS").unwrap_or(n.to_string()).parse().unwrap_or(n)
 }
 
 pub fn rows() -> usize {
-    let n = 25; // lines
+    let n = if cfg!(feature = "video") { 25 } else { sys::serial::rows() };
     sys::process::env("ROWS").unwrap_or(n.to_string()).parse().unwrap_or(n)
 }
+
+// Ask the remote terminal of the serial console for its current size
+pub fn update_size() {
+    if !cfg!(feature = "video") {
+        sys::serial::query_size();
+    }
+}
+
+// Parse the parameters of a mouse event reported in the SGR format like
+// `\x1b[<0;10;5M` into a button, a column and a row starting from 0
+pub fn parse_mouse_event(params: &str) -> Option<(usize, usize, usize)> {
+    let params = params.strip_prefix('<')?;
+    let values: Option<Vec<usize>> = params.split(';').map(|s|
+        s.parse().ok()
+    ).collect();
+    match values?[..] {
+        [b, x, y] if x > 0 && y > 0 => Some((b, x - 1, y - 1)),
+        _ => None,
+    }
+}
+
+#[test_case]
+fn test_parse_mouse_event() {
+    assert_eq!(parse_mouse_event("<0;10;5"), Some((0, 9, 4)));
+    assert_eq!(parse_mouse_event("<2;1;1"), Some((2, 0, 0)));
+    assert_eq!(parse_mouse_event("<0;0;5"), None);
+    assert_eq!(parse_mouse_event("0;10;5"), None);
+    assert_eq!(parse_mouse_event("<0;10"), None);
+    assert_eq!(parse_mouse_event("<0;a;10;5"), None);
+}
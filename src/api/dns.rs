@@ -0,0 +1,477 @@
+use crate::api::clock;
+use crate::api::fs;
+use crate::api::rng;
+use crate::api::syscall;
+use crate::sys::fs::OpenFlag;
+
+use alloc::collections::btree_map::BTreeMap;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use bit_field::BitField;
+use core::convert::TryInto;
+use core::str::FromStr;
+use smoltcp::wire::{IpAddress, Ipv4Address, Ipv6Address};
+use spin::Mutex;
+
+// See RFC 1035 for implementation details
+
+const DNS_FILE: &str = "/ini/dns";
+const HOSTS_FILE: &str = "/ini/hosts";
+const DEFAULT_SERVER: IpAddress = IpAddress::v4(8, 8, 8, 8);
+const MAX_CACHE_SIZE: usize = 64;
+const MAX_CNAME_CHAIN: usize = 8;
+
+// Addresses and their expiration time since boot, indexed by name and type
+static CACHE: Mutex<BTreeMap<(String, u16), (IpAddress, f64)>> =
+    Mutex::new(BTreeMap::new());
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy)]
+#[repr(u16)]
+enum QueryType {
+    A = 1,
+    // NS = 2,
+    // MD = 3,
+    // MF = 4,
+    CNAME = 5,
+    // SOA = 6,
+    // MX = 15,
+    // TXT = 16,
+    AAAA = 28,
+}
+
+#[repr(u16)]
+enum QueryClass {
+    IN = 1,
+}
+
+#[derive(Debug)]
+#[repr(u16)]
+pub enum ResponseCode {
+    NoError = 0,
+    FormatError = 1,
+    ServerFailure = 2,
+    NameError = 3,
+    NotImplemented = 4,
+    Refused = 5,
+
+    UnknownError,
+    NetworkError,
+}
+
+struct Record {
+    pub name: String,
+    pub kind: u16,
+    pub ttl: u32,
+    pub offset: usize, // Offset of the data in the datagram
+    pub len: usize,
+}
+
+struct Message {
+    pub datagram: Vec<u8>,
+}
+
+const FLAG_RD: u16 = 0x0100; // Recursion desired
+
+impl Message {
+    pub fn from(datagram: &[u8]) -> Self {
+        Self {
+            datagram: Vec::from(datagram),
+        }
+    }
+
+    pub fn query(qname: &str, qtype: QueryType, qclass: QueryClass) -> Self {
+        let mut datagram = Vec::new();
+
+        let id = rng::get_u16();
+        for b in id.to_be_bytes().iter() {
+            datagram.push(*b); // Transaction ID
+        }
+        for b in FLAG_RD.to_be_bytes().iter() {
+            datagram.push(*b); // Flags
+        }
+        for b in (1 as u16).to_be_bytes().iter() {
+            datagram.push(*b); // Questions
+        }
+        for _ in 0..6 {
+            datagram.push(0); // Answer + Authority + Additional
+        }
+        for label in qname.split('.') {
+            datagram.push(label.len() as u8); // QNAME label length
+            for b in label.bytes() {
+                datagram.push(b); // QNAME label bytes
+            }
+        }
+        datagram.push(0); // Root null label
+        for b in (qtype as u16).to_be_bytes().iter() {
+            datagram.push(*b); // QTYPE
+        }
+        for b in (qclass as u16).to_be_bytes().iter() {
+            datagram.push(*b); // QCLASS
+        }
+
+        Self { datagram }
+    }
+
+    pub fn id(&self) -> u16 {
+        u16::from_be_bytes(self.datagram[0..2].try_into().unwrap())
+    }
+
+    pub fn header(&self) -> u16 {
+        u16::from_be_bytes(self.datagram[2..4].try_into().unwrap())
+    }
+
+    pub fn is_response(&self) -> bool {
+        self.header().get_bit(15)
+    }
+
+    fn count(&self, i: usize) -> u16 {
+        u16::from_be_bytes(self.datagram[i..(i + 2)].try_into().unwrap())
+    }
+
+    // Return the offset of the first byte after the name at `i`
+    fn skip_name(&self, mut i: usize) -> Option<usize> {
+        loop {
+            let n = *self.datagram.get(i)? as usize;
+            if n == 0 {
+                return Some(i + 1);
+            } else if n & 0xC0 == 0xC0 {
+                return Some(i + 2); // Compression pointer
+            } else {
+                i += n + 1;
+            }
+        }
+    }
+
+    // Return the name at `i` following the compression pointers
+    pub fn read_name(&self, mut i: usize) -> Option<String> {
+        let mut name = String::new();
+        let mut jumps = 0;
+        loop {
+            let n = *self.datagram.get(i)? as usize;
+            if n == 0 {
+                return Some(name);
+            } else if n & 0xC0 == 0xC0 {
+                jumps += 1;
+                if jumps > 16 {
+                    return None; // Compression loop
+                }
+                let lo = *self.datagram.get(i + 1)? as usize;
+                i = ((n & 0x3F) << 8) | lo;
+            } else {
+                let label = self.datagram.get((i + 1)..(i + 1 + n))?;
+                if !name.is_empty() {
+                    name.push('.');
+                }
+                name.push_str(&String::from_utf8_lossy(label));
+                i += n + 1;
+            }
+        }
+    }
+
+    // Return each resource record in the answer section
+    pub fn answers(&self) -> Vec<Record> {
+        let mut res = Vec::new();
+        let n = self.datagram.len();
+        if n < 12 {
+            return res;
+        }
+        let mut i = 12;
+        for _ in 0..self.count(4) {
+            match self.skip_name(i) {
+                Some(j) => i = j + 4, // QTYPE + QCLASS
+                None => return res,
+            }
+        }
+        for _ in 0..self.count(6) {
+            let name = match self.read_name(i) {
+                Some(name) => name,
+                None => break,
+            };
+            i = match self.skip_name(i) {
+                Some(j) if j + 10 <= n => j,
+                _ => break,
+            };
+            let kind = self.count(i);
+            let ttl = u32::from_be_bytes(
+                self.datagram[(i + 4)..(i + 8)].try_into().unwrap()
+            );
+            let len = self.count(i + 8) as usize;
+            i += 10; // TYPE + CLASS + TTL + RDLENGTH
+            if i + len > n {
+                break;
+            }
+            res.push(Record { name, kind, ttl, offset: i, len });
+            i += len;
+        }
+        res
+    }
+
+    pub fn code(&self) -> ResponseCode {
+        match self.header().get_bits(11..15) {
+            0 => ResponseCode::NoError,
+            1 => ResponseCode::FormatError,
+            2 => ResponseCode::ServerFailure,
+            3 => ResponseCode::NameError,
+            4 => ResponseCode::NotImplemented,
+            5 => ResponseCode::Refused,
+            _ => ResponseCode::UnknownError,
+        }
+    }
+}
+
+// Return the DNS servers defined in `/ini/dns` separated by commas
+pub fn servers() -> Vec<IpAddress> {
+    let mut res = Vec::new();
+    if let Ok(servers) = fs::read_to_string(DNS_FILE) {
+        for server in servers.split(',') {
+            if let Ok(addr) = IpAddress::from_str(server.trim()) {
+                res.push(addr);
+            }
+        }
+    }
+    if res.is_empty() {
+        res.push(DEFAULT_SERVER);
+    }
+    res
+}
+
+// Return the address of a name defined in `/ini/hosts` with one address
+// followed by its names on each line, preferring IPv4 addresses.
+fn hosts_lookup(name: &str) -> Option<IpAddress> {
+    let mut res = None;
+    let contents = fs::read_to_string(HOSTS_FILE).ok()?;
+    for line in contents.lines() {
+        let line = line.split('#').next().unwrap_or("");
+        let mut fields = line.split_whitespace();
+        if let Some(Ok(addr)) = fields.next().map(IpAddress::from_str) {
+            if fields.any(|field| field.eq_ignore_ascii_case(name)) {
+                match addr {
+                    IpAddress::Ipv4(_) => return Some(addr),
+                    IpAddress::Ipv6(_) => res = res.or(Some(addr)),
+                }
+            }
+        }
+    }
+    res
+}
+
+fn cache_get(name: &str, qtype: QueryType) -> Option<IpAddress> {
+    let key = (name.to_lowercase(), qtype as u16);
+    let mut cache = CACHE.lock();
+    match cache.get(&key) {
+        Some((addr, expiration)) if *expiration > clock::boot_time() => {
+            Some(*addr)
+        }
+        Some(_) => {
+            cache.remove(&key);
+            None
+        }
+        None => None,
+    }
+}
+
+fn cache_set(name: &str, qtype: QueryType, addr: IpAddress, ttl: u32) {
+    if ttl == 0 {
+        return;
+    }
+    let now = clock::boot_time();
+    let mut cache = CACHE.lock();
+    cache.retain(|_, (_, expiration)| *expiration > now);
+    if cache.len() >= MAX_CACHE_SIZE {
+        cache.pop_first();
+    }
+    let key = (name.to_lowercase(), qtype as u16);
+    cache.insert(key, (addr, now + ttl as f64));
+}
+
+// Resolve a name to an IPv4 address or to an IPv6 address when the name has
+// no IPv4 address, using the static hosts file, the cache, and then the DNS
+// servers of the network configuration.
+pub fn resolve(name: &str) -> Result<IpAddress, ResponseCode> {
+    if let Some(addr) = hosts_lookup(name) {
+        return Ok(addr);
+    }
+    resolve_with(name, &servers(), true)
+}
+
+// Resolve a name with the given DNS servers, trying the next one when a
+// server cannot be reached or fails to answer. The cache is neither read
+// nor updated when `use_cache` is false, so that every answer comes from
+// the servers.
+pub fn resolve_with(
+    name: &str,
+    servers: &[IpAddress],
+    use_cache: bool
+) -> Result<IpAddress, ResponseCode> {
+    match lookup(name, QueryType::A, servers, use_cache) {
+        Err(ResponseCode::NetworkError) => Err(ResponseCode::NetworkError),
+        Err(_) => lookup(name, QueryType::AAAA, servers, use_cache),
+        res => res,
+    }
+}
+
+fn lookup(
+    name: &str,
+    qtype: QueryType,
+    servers: &[IpAddress],
+    use_cache: bool
+) -> Result<IpAddress, ResponseCode> {
+    if use_cache {
+        if let Some(addr) = cache_get(name, qtype) {
+            return Ok(addr);
+        }
+    }
+    let mut res = Err(ResponseCode::NetworkError);
+    for server in servers {
+        res = query(name, qtype, *server, use_cache);
+        match res {
+            Err(ResponseCode::NetworkError) => continue,
+            Err(ResponseCode::ServerFailure) => continue,
+            Err(ResponseCode::Refused) => continue,
+            _ => break,
+        }
+    }
+    res
+}
+
+fn query(
+    qname: &str,
+    qtype: QueryType,
+    server: IpAddress,
+    use_cache: bool
+) -> Result<IpAddress, ResponseCode> {
+    let mut name = String::from(qname);
+    let mut ttl = u32::MAX;
+    for _ in 0..MAX_CNAME_CHAIN {
+        let message = exchange(&name, qtype, server)?;
+        let answers = message.answers();
+
+        // Follow the chain of aliases found in the answer section
+        let mut target = name.clone();
+        for _ in 0..MAX_CNAME_CHAIN {
+            let alias = answers.iter().find(|r|
+                r.kind == QueryType::CNAME as u16
+                && r.name.eq_ignore_ascii_case(&target)
+            );
+            if let Some(r) = alias {
+                if let Some(canonical_name) = message.read_name(r.offset) {
+                    ttl = ttl.min(r.ttl);
+                    target = canonical_name;
+                    continue;
+                }
+            }
+            break;
+        }
+
+        for r in &answers {
+            if r.kind == qtype as u16 && r.name.eq_ignore_ascii_case(&target) {
+                let data = &message.datagram[r.offset..(r.offset + r.len)];
+                if let Some(addr) = parse_addr(data) {
+                    if use_cache {
+                        cache_set(qname, qtype, addr, ttl.min(r.ttl));
+                    }
+                    return Ok(addr);
+                }
+            }
+        }
+
+        if target == name {
+            return Err(ResponseCode::NameError);
+        }
+        name = target; // Ask for the address of the canonical name
+    }
+    Err(ResponseCode::NameError)
+}
+
+fn exchange(
+    name: &str,
+    qtype: QueryType,
+    server: IpAddress
+) -> Result<Message, ResponseCode> {
+    let port = 53;
+    let query = Message::query(name, qtype, QueryClass::IN);
+
+    let socket_path = "/dev/net/udp";
+    let buf_len = if let Some(info) = syscall::info(socket_path) {
+        info.size() as usize
+    } else {
+        return Err(ResponseCode::NetworkError);
+    };
+
+    let flags = OpenFlag::Device as u8;
+    if let Some(handle) = syscall::open(socket_path, flags) {
+        if syscall::connect(handle, server, port).is_err() {
+            syscall::close(handle);
+            return Err(ResponseCode::NetworkError);
+        }
+        if syscall::write(handle, &query.datagram).is_none() {
+            syscall::close(handle);
+            return Err(ResponseCode::NetworkError);
+        }
+        loop {
+            let mut data = vec![0; buf_len];
+            if let Some(bytes) = syscall::read(handle, &mut data) {
+                if bytes < 28 {
+                    break;
+                }
+                data.resize(bytes, 0);
+
+                let message = Message::from(&data);
+                if message.id() == query.id() && message.is_response() {
+                    syscall::close(handle);
+                    return match message.code() {
+                        ResponseCode::NoError => Ok(message),
+                        code => Err(code),
+                    };
+                }
+            } else {
+                break;
+            }
+        }
+        syscall::close(handle);
+    }
+    Err(ResponseCode::NetworkError)
+}
+
+fn parse_addr(data: &[u8]) -> Option<IpAddress> {
+    match data.len() {
+        4 => {
+            let ipv4 = Ipv4Address::from_octets(data.try_into().ok()?);
+            Some(IpAddress::from(ipv4))
+        }
+        16 => {
+            let ipv6 = Ipv6Address::from_octets(data.try_into().ok()?);
+            Some(IpAddress::from(ipv6))
+        }
+        _ => None,
+    }.filter(|addr| !addr.is_unspecified())
+}
+
+#[test_case]
+fn test_message_answers() {
+    let datagram = [
+        0x12, 0x34, 0x81, 0x80, 0x00, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00,
+        0x03, b'f', b'o', b'o', 0x03, b'c', b'o', b'm', 0x00, 0x00, 0x1C, 0x00,
+        0x01, 0xC0, 0x0C, 0x00, 0x05, 0x00, 0x01, 0x00, 0x00, 0x00, 0x3C, 0x00,
+        0x06, 0x03, b'b', b'a', b'r', 0xC0, 0x10, 0xC0, 0x25, 0x00, 0x1C, 0x00,
+        0x01, 0x00, 0x00, 0x00, 0x1E, 0x00, 0x10, 0x20, 0x01, 0x0D, 0xB8, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    ];
+    let message = Message::from(&datagram);
+    let answers = message.answers();
+    assert_eq!(answers.len(), 2);
+    assert_eq!(answers[0].name, "foo.com");
+    assert_eq!(answers[0].kind, QueryType::CNAME as u16);
+    assert_eq!(answers[0].ttl, 60);
+    assert_eq!(message.read_name(answers[0].offset).unwrap(), "bar.com");
+    assert_eq!(answers[1].name, "bar.com");
+    assert_eq!(answers[1].kind, QueryType::AAAA as u16);
+    assert_eq!(answers[1].ttl, 30);
+    let data = &message.datagram[answers[1].offset..][..answers[1].len];
+    assert_eq!(
+        parse_addr(data),
+        Some(IpAddress::from_str("2001:db8::1").unwrap())
+    );
+}
@@ -1,3 +1,4 @@
+use alloc::collections::BTreeMap;
 use alloc::vec::Vec;
 use core::convert::TryFrom;
 
@@ -6,35 +7,187 @@ pub struct Font {
     pub height: u8,
     pub size: u16,
     pub data: Vec<u8>,
+    pub unicode: BTreeMap<char, u16>,
+}
+
+impl Font {
+    // Return the index of the glyph of a char, or of a similar char when the
+    // font doesn't have it
+    pub fn glyph(&self, c: char) -> Option<u16> {
+        self.lookup(c).or_else(|| fallback(c).and_then(|c| self.lookup(c)))
+    }
+
+    // Fonts without a unicode table are indexed by code point
+    fn lookup(&self, c: char) -> Option<u16> {
+        if self.unicode.is_empty() {
+            let i = c as u32;
+            if i < self.size as u32 {
+                Some(i as u16)
+            } else {
+                None
+            }
+        } else {
+            self.unicode.get(&c).copied()
+        }
+    }
 }
 
 impl TryFrom<&[u8]> for Font {
     type Error = ();
 
     fn try_from(buf: &[u8]) -> Result<Self, Self::Error> {
-        // See: http://www.fifi.org/doc/console-tools-dev/file-formats/psf
-        // Header
-        if buf.len() < 4 || buf[0] != 0x36 || buf[1] != 0x04 {
-            return Err(());
+        if buf.starts_with(&[0x36, 0x04]) {
+            parse_psf1(buf)
+        } else if buf.starts_with(&[0x72, 0xB5, 0x4A, 0x86]) {
+            parse_psf2(buf)
+        } else {
+            Err(())
         }
-        let mode = buf[2];
-        let height = buf[3];
-        let size = match mode {
-            0 | 2 => 256,
-            1 | 3 => 512,
-            _ => return Err(()),
-        };
-
-        // Data
-        let n = (4 + size * height as u16) as usize;
-        if buf.len() < n {
-            return Err(());
+    }
+}
+
+// See: http://www.fifi.org/doc/console-tools-dev/file-formats/psf
+fn parse_psf1(buf: &[u8]) -> Result<Font, ()> {
+    // Header
+    if buf.len() < 4 {
+        return Err(());
+    }
+    let mode = buf[2];
+    let height = buf[3];
+    let size = match mode {
+        0..=7 if mode & 1 == 0 => 256,
+        0..=7 => 512,
+        _ => return Err(()),
+    };
+
+    // Data
+    let n = 4 + (size as usize) * (height as usize);
+    if buf.len() < n {
+        return Err(());
+    }
+    let data = buf[4..n].to_vec();
+
+    // Unicode Table
+    //
+    // Each glyph has a list of 16 bits code points terminated by 0xFFFF,
+    // with sequences of combining chars starting with 0xFFFE.
+    let mut unicode = BTreeMap::new();
+    if mode & 6 != 0 {
+        let mut glyph = 0;
+        let mut is_seq = false;
+        for bytes in buf[n..].chunks_exact(2) {
+            match u16::from_le_bytes([bytes[0], bytes[1]]) {
+                0xFFFF => {
+                    glyph += 1;
+                    is_seq = false;
+                }
+                0xFFFE => is_seq = true,
+                _ if is_seq => {}
+                code => {
+                    if let Some(c) = char::from_u32(code as u32) {
+                        unicode.entry(c).or_insert(glyph);
+                    }
+                }
+            }
+            if glyph == size {
+                break;
+            }
         }
-        let data = buf[4..n].to_vec();
+    }
 
-        // TODO: Unicode Table
+    Ok(Font { height, size, data, unicode })
+}
 
-        Ok(Font { height, size, data })
+// See: https://www.win.tue.nl/~aeb/linux/kbd/font-formats-1.html
+fn parse_psf2(buf: &[u8]) -> Result<Font, ()> {
+    // Header
+    if buf.len() < 32 {
+        return Err(());
+    }
+    let field = |i: usize| {
+        u32::from_le_bytes([buf[i], buf[i + 1], buf[i + 2], buf[i + 3]])
+    };
+    let offset = field(8) as usize;
+    let flags = field(12);
+    let size = field(16) as usize;
+    let glyph_size = field(20) as usize;
+    let height = field(24) as usize;
+    let width = field(28) as usize;
+
+    // Only the glyphs of 8 pixels wide or less can be displayed
+    if width == 0 || width > 8 || height > 0xFF || glyph_size != height {
+        return Err(());
+    }
+    if size == 0 || size > 0xFFFF {
+        return Err(());
+    }
+
+    // Data
+    let n = offset + size * glyph_size;
+    if offset < 32 || buf.len() < n {
+        return Err(());
+    }
+    let data = buf[offset..n].to_vec();
+
+    // Unicode Table
+    //
+    // Each glyph has a list of UTF-8 chars terminated by 0xFF, with
+    // sequences of combining chars starting with 0xFE.
+    let mut unicode = BTreeMap::new();
+    if flags & 1 == 1 {
+        let entries = buf[n..].split(|&byte| byte == 0xFF);
+        for (glyph, entry) in entries.take(size).enumerate() {
+            let chars = entry.split(|&byte| byte == 0xFE).next().unwrap();
+            if let Ok(s) = core::str::from_utf8(chars) {
+                for c in s.chars() {
+                    unicode.entry(c).or_insert(glyph as u16);
+                }
+            }
+        }
+    }
+
+    let height = height as u8;
+    let size = size as u16;
+    Ok(Font { height, size, data, unicode })
+}
+
+// Letters with diacritics are replaced by the same letters without them
+const LETTERS: [(&str, char); 38] = [
+    ("ÀÁÂÃÄÅĀĂĄ", 'A'), ("àáâãäåāăą", 'a'),
+    ("ÇĆĈĊČ", 'C'), ("çćĉċč", 'c'),
+    ("ĎĐ", 'D'), ("ďđ", 'd'),
+    ("ÈÉÊËĒĔĖĘĚ", 'E'), ("èéêëēĕėęě", 'e'),
+    ("ĜĞĠĢ", 'G'), ("ĝğġģ", 'g'),
+    ("ĤĦ", 'H'), ("ĥħ", 'h'),
+    ("ÌÍÎÏĨĪĬĮİ", 'I'), ("ìíîïĩīĭįı", 'i'),
+    ("Ĵ", 'J'), ("ĵ", 'j'),
+    ("Ķ", 'K'), ("ķ", 'k'),
+    ("ĹĻĽĿŁ", 'L'), ("ĺļľŀł", 'l'),
+    ("ÑŃŅŇ", 'N'), ("ñńņň", 'n'),
+    ("ÒÓÔÕÖØŌŎŐ", 'O'), ("òóôõöøōŏő", 'o'),
+    ("ŔŖŘ", 'R'), ("ŕŗř", 'r'),
+    ("ŚŜŞŠ", 'S'), ("śŝşš", 's'),
+    ("ŢŤŦ", 'T'), ("ţťŧ", 't'),
+    ("ÙÚÛÜŨŪŬŮŰŲ", 'U'), ("ùúûüũūŭůűų", 'u'),
+    ("ŴẀẂẄ", 'W'), ("ŵẁẃẅ", 'w'),
+    ("ÝŶŸ", 'Y'), ("ýÿŷ", 'y'),
+    ("ŹŻŽ", 'Z'), ("źżž", 'z'),
+];
+
+// Return an ASCII char that looks like the given char
+pub fn fallback(c: char) -> Option<char> {
+    match c {
+        '\u{A0}' | '\u{2000}'..='\u{200A}' => Some(' '),
+        '‘' | '’' | '‚' | '‛' | '′' => Some('\''),
+        '“' | '”' | '„' | '‟' | '″' | '«' | '»' => Some('"'),
+        '‐' | '‑' | '‒' | '–' | '—' | '―' | '−' => Some('-'),
+        '•' | '·' | '∙' => Some('*'),
+        '…' => Some('.'),
+        '─' | '━' | '┄' | '┅' | '┈' | '┉' | '╌' | '╍' | '═' => Some('-'),
+        '│' | '┃' | '┆' | '┇' | '┊' | '┋' | '╎' | '╏' | '║' => Some('|'),
+        '\u{2500}'..='\u{257F}' => Some('+'), // Box Drawing
+        '\u{2580}'..='\u{259F}' => Some('#'), // Block Elements
+        _ => LETTERS.iter().find(|(s, _)| s.contains(c)).map(|(_, c)| *c),
     }
 }
 
@@ -48,4 +201,44 @@ fn parse_psf_font() {
     assert_eq!(font.height, 16);
     assert_eq!(font.size, 256);
     assert_eq!(font.data.len(), 256 * 16);
+    assert_eq!(font.glyph('A'), Some(0x41));
+    assert_eq!(font.glyph('é'), Some(0xE9));
+    assert_eq!(font.glyph('└'), Some(0xC4));
+    assert_eq!(font.glyph('ő'), Some(0x6F)); // Fallback to 'o'
+    assert_eq!(font.glyph('Ł'), Some(0x4C)); // Fallback to 'L'
+}
+
+#[test_case]
+fn parse_psf2_font() {
+    let mut buf = Vec::new();
+    for field in [0, 32, 1, 2, 2, 2, 8] { // Header without magic bytes
+        buf.extend_from_slice(&u32::to_le_bytes(field));
+    }
+    buf.splice(0..0, [0x72, 0xB5, 0x4A, 0x86]);
+    buf.extend_from_slice(&[0x18, 0x3C, 0x66, 0x7E]); // Glyphs
+    buf.extend_from_slice("Aa".as_bytes());
+    buf.push(0xFF);
+    buf.extend_from_slice("é".as_bytes());
+    buf.push(0xFE);
+    buf.extend_from_slice("e\u{301}".as_bytes());
+    buf.push(0xFF);
+
+    let font = Font::try_from(&buf[..]).unwrap();
+    assert_eq!(font.height, 2);
+    assert_eq!(font.size, 2);
+    assert_eq!(font.data, [0x18, 0x3C, 0x66, 0x7E]);
+    assert_eq!(font.glyph('A'), Some(0));
+    assert_eq!(font.glyph('a'), Some(0));
+    assert_eq!(font.glyph('é'), Some(1));
+    assert_eq!(font.glyph('\u{301}'), None);
+    assert_eq!(font.glyph('ä'), Some(0)); // Fallback to 'a'
+
+    buf[28] = 9; // Width
+    assert!(Font::try_from(&buf[..]).is_err());
+
+    assert_eq!(fallback('─'), Some('-'));
+    assert_eq!(fallback('┼'), Some('+'));
+    assert_eq!(fallback('ñ'), Some('n'));
+    assert_eq!(fallback('é'), Some('e'));
+    assert_eq!(fallback('€'), None);
 }
@@ -139,7 +139,13 @@ pub fn create_device(path: &str, name: &str) -> Option<usize> {
 }
 
 fn device_buffer(name: &str) -> Result<Vec<u8>, ()> {
-    let arg = if name.starts_with("ata-") { "ata" } else { name };
+    let arg = if name.starts_with("ata-") {
+        "ata"
+    } else if name.starts_with("vt-") {
+        "console"
+    } else {
+        name
+    };
     let dev = device_type(arg)?;
     let mut buf = dev.buf();
     if name.starts_with("ata-") {
@@ -151,6 +157,15 @@ fn device_buffer(name: &str) -> Result<Vec<u8>, ()> {
             _ => return Err(()),
         }
     }
+    if name.starts_with("vt-") {
+        match name {
+            "vt-1" => buf[1] = 1,
+            "vt-2" => buf[1] = 2,
+            "vt-3" => buf[1] = 3,
+            "vt-4" => buf[1] = 4,
+            _ => return Err(()),
+        }
+    }
     Ok(buf)
 }
 
@@ -175,6 +190,7 @@ fn device_type(name: &str) -> Result<DeviceType, ()> {
         "vga-mode"    => Ok(DeviceType::VgaMode),
         "vga-palette" => Ok(DeviceType::VgaPalette),
         "speaker"     => Ok(DeviceType::Speaker),
+        "mouse"       => Ok(DeviceType::Mouse),
         "ata"         => Ok(DeviceType::Drive),
         _             => Err(()),
     }
@@ -86,6 +86,7 @@ pub mod allocator;
 pub mod base64;
 pub mod clock;
 pub mod console;
+pub mod dns;
 pub mod font;
 pub mod fs;
 pub mod io;
@@ -96,6 +97,7 @@ pub mod rng;
 pub mod regex;
 pub mod syscall;
 pub mod time;
+pub mod tls;
 pub mod unit;
 pub mod vga;
 // TODO: add mod wildcard
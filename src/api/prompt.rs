@@ -1,9 +1,12 @@
 use crate::api::{console, fs, io};
 use alloc::boxed::Box;
+use alloc::format;
 use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 use vte::{Params, Parser, Perform};
 
+const KILL_RING_SIZE: usize = 16;
+
 pub struct Prompt {
     pub completion: Completion,
     pub history: History,
@@ -11,6 +14,16 @@ pub struct Prompt {
     offset: usize, // Offset line by the length of the prompt string
     cursor: usize,
     line: Vec<char>, // UTF-32
+    kill_ring: Vec<String>,
+    yank: Option<(usize, usize)>, // Index in the kill ring and length
+    search: Option<Search>,
+}
+
+// State of a reverse incremental search in the history
+struct Search {
+    query: String,
+    pos: Option<usize>,
+    line: Vec<char>, // Line restored when the search is canceled
 }
 
 impl Prompt {
@@ -22,6 +35,9 @@ impl Prompt {
             offset: 0,
             cursor: 0,
             line: Vec::with_capacity(console::cols()),
+            kill_ring: Vec::new(),
+            yank: None,
+            search: None,
         }
     }
 
@@ -34,8 +50,12 @@ impl Prompt {
         self.offset = offset_from_prompt(prompt);
         self.cursor = self.offset;
         self.line = Vec::with_capacity(self.max());
+        self.search = None;
         let mut parser = Parser::new();
         while let Some(c) = io::stdin().read_char() {
+            if self.handle_search_key(c) {
+                continue;
+            }
             match c {
                 console::ETX_KEY => { // End of Text (^C)
                     self.update_completion();
@@ -260,19 +280,211 @@ impl Prompt {
             self.cursor += 1;
         }
     }
+
+    // Print the given text in place of the line and move the cursor to the
+    // char at index `i`
+    fn redraw(&mut self, s: &str, i: usize) {
+        let n = self.cursor - self.offset;
+        if n > 0 {
+            print!("\x1b[{}D", n);
+        }
+        print!("{}\x1b[K", s);
+        let n = s.chars().count();
+        if n > i {
+            print!("\x1b[{}D", n - i);
+        }
+        self.cursor = self.offset + i;
+    }
+
+    fn redraw_line(&mut self, i: usize) {
+        let s: String = self.line.iter().collect();
+        self.redraw(&s, i);
+    }
+
+    // Return the index of the start of the word before the cursor
+    fn prev_word(&self, is_word: fn(char) -> bool) -> usize {
+        let mut i = self.cursor - self.offset;
+        while i > 0 && !is_word(self.line[i - 1]) {
+            i -= 1;
+        }
+        while i > 0 && is_word(self.line[i - 1]) {
+            i -= 1;
+        }
+        i
+    }
+
+    // Return the index of the end of the word after the cursor
+    fn next_word(&self, is_word: fn(char) -> bool) -> usize {
+        let n = self.line.len();
+        let mut i = self.cursor - self.offset;
+        while i < n && !is_word(self.line[i]) {
+            i += 1;
+        }
+        while i < n && is_word(self.line[i]) {
+            i += 1;
+        }
+        i
+    }
+
+    fn handle_word_backward_key(&mut self) {
+        self.update_completion();
+        self.update_history();
+        let i = self.prev_word(char::is_alphanumeric);
+        self.redraw_line(i);
+    }
+
+    fn handle_word_forward_key(&mut self) {
+        self.update_completion();
+        self.update_history();
+        let i = self.next_word(char::is_alphanumeric);
+        self.redraw_line(i);
+    }
+
+    // Remove the chars between `start` and `end` from the line and add them
+    // to the kill ring
+    fn kill(&mut self, start: usize, end: usize) {
+        if start == end {
+            return;
+        }
+        let killed: String = self.line.drain(start..end).collect();
+        self.kill_ring.push(killed);
+        if self.kill_ring.len() > KILL_RING_SIZE {
+            self.kill_ring.remove(0);
+        }
+        self.redraw_line(start);
+    }
+
+    fn handle_kill_word_key(&mut self) { // Ctrl-W
+        self.update_completion();
+        self.update_history();
+        let i = self.prev_word(|c| !c.is_whitespace());
+        self.kill(i, self.cursor - self.offset);
+    }
+
+    fn handle_kill_start_key(&mut self) { // Ctrl-U
+        self.update_completion();
+        self.update_history();
+        self.kill(0, self.cursor - self.offset);
+    }
+
+    fn handle_kill_end_key(&mut self) { // Ctrl-K
+        self.update_completion();
+        self.update_history();
+        self.kill(self.cursor - self.offset, self.line.len());
+    }
+
+    // Insert an entry of the kill ring at the cursor
+    fn yank(&mut self, pos: usize) {
+        let s: Vec<char> = self.kill_ring[pos].chars().collect();
+        let n = s.len();
+        if self.line.len() + n > self.max() {
+            return;
+        }
+        let i = self.cursor - self.offset;
+        self.line.splice(i..i, s);
+        self.redraw_line(i + n);
+        self.yank = Some((pos, n));
+    }
+
+    fn handle_yank_key(&mut self) { // Ctrl-Y
+        self.update_completion();
+        self.update_history();
+        if !self.kill_ring.is_empty() {
+            self.yank(self.kill_ring.len() - 1);
+        }
+    }
+
+    // Replace the text that has just been yanked with the previous entry of
+    // the kill ring
+    fn handle_yank_pop_key(&mut self, yank: Option<(usize, usize)>) { // Alt-Y
+        if let Some((pos, n)) = yank {
+            let i = self.cursor - self.offset;
+            self.line.drain((i - n)..i);
+            self.redraw_line(i - n);
+            let pos = if pos > 0 { pos } else { self.kill_ring.len() };
+            self.yank(pos - 1);
+        }
+    }
+
+    fn handle_search_start_key(&mut self) { // Ctrl-R
+        self.update_completion();
+        self.update_history();
+        let line = self.line.clone();
+        self.search = Some(Search { query: String::new(), pos: None, line });
+        self.print_search();
+    }
+
+    fn print_search(&mut self) {
+        if let Some(search) = &self.search {
+            let entry = match search.pos {
+                Some(i) => self.history.entries[i].as_str(),
+                None => "",
+            };
+            let s = format!("(reverse-i-search)`{}': {}", search.query, entry);
+            self.redraw(&s, s.chars().count());
+        }
+    }
+
+    // Return true if the key has been used by the search
+    fn handle_search_key(&mut self, c: char) -> bool {
+        let mut search = match self.search.take() {
+            Some(search) => search,
+            None => return false,
+        };
+        let n = self.history.entries.len();
+        match c {
+            '\x12' => { // Ctrl-R -> Find older entry
+                let i = search.pos.unwrap_or(n);
+                if let Some(i) = self.history.find(&search.query, i) {
+                    search.pos = Some(i);
+                }
+            }
+            '\x07' => { // Ctrl-G -> Cancel search
+                self.line = search.line;
+                self.redraw_line(self.line.len());
+                return true;
+            }
+            '\x08' => { // Backspace
+                search.query.pop();
+                search.pos = self.history.find(&search.query, n);
+            }
+            c if !c.is_control() => {
+                search.query.push(c);
+                let i = search.pos.map_or(n, |i| i + 1);
+                search.pos = self.history.find(&search.query, i);
+            }
+            _ => { // Accept the entry found and handle the key as usual
+                if let Some(i) = search.pos {
+                    self.line = self.history.entries[i].chars().collect();
+                }
+                self.redraw_line(self.line.len());
+                return false;
+            }
+        }
+        self.search = Some(search);
+        self.print_search();
+        true
+    }
 }
 
 impl Perform for Prompt {
     fn execute(&mut self, b: u8) {
+        self.yank = None;
         let c = b as char;
         match c {
             '\x08' => self.handle_backspace_key(),
             '\t' => self.handle_tab_key(),
+            '\x0B' => self.handle_kill_end_key(), // Ctrl-K
+            '\x12' => self.handle_search_start_key(), // Ctrl-R
+            '\x15' => self.handle_kill_start_key(), // Ctrl-U
+            '\x17' => self.handle_kill_word_key(), // Ctrl-W
+            '\x19' => self.handle_yank_key(), // Ctrl-Y
             _ => {}
         }
     }
 
     fn print(&mut self, c: char) {
+        self.yank = None;
         match c {
             '\x7f' => self.handle_delete_key(),
             c => self.handle_printable_key(c),
@@ -280,9 +492,15 @@ impl Perform for Prompt {
     }
 
     fn csi_dispatch(&mut self, params: &Params, _: &[u8], _: bool, c: char) {
+        self.yank = None;
+        // Arrow keys with the Alt (3) or Ctrl (5) modifier move by words
+        let modifier = params.iter().nth(1).map(|param| param[0]);
+        let is_word = matches!(modifier, Some(3) | Some(5));
         match c {
             'A' => self.handle_up_key(),
             'B' => self.handle_down_key(),
+            'C' if is_word => self.handle_word_forward_key(),
+            'D' if is_word => self.handle_word_backward_key(),
             'C' => self.handle_forward_key(),
             'D' => self.handle_backward_key(),
             'Z' => self.handle_backtab_key(),
@@ -299,6 +517,16 @@ impl Perform for Prompt {
             _ => {}
         }
     }
+
+    fn esc_dispatch(&mut self, _: &[u8], _: bool, b: u8) {
+        let yank = self.yank.take();
+        match b {
+            b'b' => self.handle_word_backward_key(), // Alt-B
+            b'f' => self.handle_word_forward_key(), // Alt-F
+            b'y' => self.handle_yank_pop_key(yank), // Alt-Y
+            _ => {}
+        }
+    }
 }
 
 pub struct Completion {
@@ -349,6 +577,14 @@ impl History {
         fs::write(path, self.entries.join("\n").as_bytes()).ok();
     }
 
+    // Return the index of the last entry before `i` containing the query
+    fn find(&self, query: &str, i: usize) -> Option<usize> {
+        if query.is_empty() {
+            return None;
+        }
+        self.entries[..i].iter().rposition(|entry| entry.contains(query))
+    }
+
     pub fn add(&mut self, entry: &str) {
         // Remove duplicated entries
         let mut i = 0;
@@ -386,3 +622,18 @@ fn offset_from_prompt(s: &str) -> usize {
     }
     offset.0
 }
+
+#[test_case]
+fn test_history_find() {
+    let mut history = History::new();
+    history.add("print hello");
+    history.add("list /tmp");
+    history.add("print world");
+    let n = history.entries.len();
+    assert_eq!(history.find("print", n), Some(2));
+    assert_eq!(history.find("print", 2), Some(0));
+    assert_eq!(history.find("print", 0), None);
+    assert_eq!(history.find("list", n), Some(1));
+    assert_eq!(history.find("edit", n), None);
+    assert_eq!(history.find("", n), None);
+}
@@ -22,3 +22,15 @@ pub fn get_u16() -> u16 {
     }
     0
 }
+
+// Fill the buffer with random bytes and return false if it could not be
+// completely filled
+pub fn fill_bytes(buf: &mut [u8]) -> bool {
+    if let Some(handle) = fs::open_device("/dev/random") {
+        let res = syscall::read(handle, buf);
+        syscall::close(handle);
+        res == Some(buf.len())
+    } else {
+        false
+    }
+}
@@ -7,7 +7,7 @@ use crate::syscall;
 use core::convert::TryInto;
 use core::convert::TryFrom;
 use core::sync::atomic::{fence, Ordering};
-use smoltcp::wire::{IpAddress, Ipv4Address};
+use smoltcp::wire::{IpAddress, Ipv4Address, Ipv6Address};
 
 pub fn exit(code: ExitCode) {
     unsafe { syscall!(EXIT, code as usize) };
@@ -128,9 +128,10 @@ pub fn poll(list: &[(usize, IO)]) -> Option<(usize, IO)> {
 }
 
 pub fn connect(handle: usize, addr: IpAddress, port: u16) -> Result<(), ()> {
-    let IpAddress::Ipv4(addr) = addr; // Only IPv4 is supported
-    let tmp = addr.octets();
-    let buf = tmp.as_slice();
+    let buf = match addr {
+        IpAddress::Ipv4(addr) => addr.octets().to_vec(),
+        IpAddress::Ipv6(addr) => addr.octets().to_vec(),
+    };
     let ptr = buf.as_ptr() as usize;
     let len = buf.len();
     let res = unsafe { syscall!(CONNECT, handle, ptr, len, port) } as isize;
@@ -151,20 +152,33 @@ pub fn listen(handle: usize, port: u16) -> Result<(), ()> {
 }
 
 pub fn accept(handle: usize) -> Result<IpAddress, ()> {
-    let addr = Ipv4Address::new(0, 0, 0, 0);
-    let tmp = addr.octets();
-    let buf = tmp.as_slice();
+    let buf = [0; 16]; // Large enough for an IPv6 address
     let ptr = buf.as_ptr() as usize;
     let len = buf.len();
     let res = unsafe { syscall!(ACCEPT, handle, ptr, len) } as isize;
-    if res >= 0 {
-        if let Ok(buf) = buf.try_into() {
-            return Ok(IpAddress::from(Ipv4Address::from_octets(buf)));
+    match res {
+        4 => {
+            if let Ok(buf) = buf[0..4].try_into() {
+                return Ok(IpAddress::from(Ipv4Address::from_octets(buf)));
+            }
+        }
+        16 => {
+            return Ok(IpAddress::from(Ipv6Address::from_octets(buf)));
         }
+        _ => {}
     }
     Err(())
 }
 
+pub fn ioctl(handle: usize, request: usize, value: usize) -> Option<usize> {
+    let res = unsafe { syscall!(IOCTL, handle, request, value) } as isize;
+    if res >= 0 {
+        Some(res as usize)
+    } else {
+        None
+    }
+}
+
 pub fn alloc(size: usize, align: usize) -> *mut u8 {
     unsafe { syscall!(ALLOC, size, align) as *mut u8 }
 }
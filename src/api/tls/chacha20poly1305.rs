@@ -0,0 +1,237 @@
+use alloc::vec::Vec;
+use core::convert::TryInto;
+
+// See RFC 8439 for implementation details
+
+pub const KEY_LEN: usize = 32;
+pub const NONCE_LEN: usize = 12;
+pub const TAG_LEN: usize = 16;
+
+const CONSTANTS: [u32; 4] = [0x61707865, 0x3320646E, 0x79622D32, 0x6B206574];
+
+fn le32(buf: &[u8]) -> u32 {
+    u32::from_le_bytes(buf[0..4].try_into().unwrap())
+}
+
+fn quarter_round(s: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    s[a] = s[a].wrapping_add(s[b]);
+    s[d] = (s[d] ^ s[a]).rotate_left(16);
+    s[c] = s[c].wrapping_add(s[d]);
+    s[b] = (s[b] ^ s[c]).rotate_left(12);
+    s[a] = s[a].wrapping_add(s[b]);
+    s[d] = (s[d] ^ s[a]).rotate_left(8);
+    s[c] = s[c].wrapping_add(s[d]);
+    s[b] = (s[b] ^ s[c]).rotate_left(7);
+}
+
+fn chacha20_block(key: &[u8], counter: u32, nonce: &[u8]) -> [u8; 64] {
+    let mut state = [0; 16];
+    state[0..4].copy_from_slice(&CONSTANTS);
+    for i in 0..8 {
+        state[4 + i] = le32(&key[(i * 4)..]);
+    }
+    state[12] = counter;
+    for i in 0..3 {
+        state[13 + i] = le32(&nonce[(i * 4)..]);
+    }
+    let mut s = state;
+    for _ in 0..10 {
+        quarter_round(&mut s, 0, 4, 8, 12);
+        quarter_round(&mut s, 1, 5, 9, 13);
+        quarter_round(&mut s, 2, 6, 10, 14);
+        quarter_round(&mut s, 3, 7, 11, 15);
+        quarter_round(&mut s, 0, 5, 10, 15);
+        quarter_round(&mut s, 1, 6, 11, 12);
+        quarter_round(&mut s, 2, 7, 8, 13);
+        quarter_round(&mut s, 3, 4, 9, 14);
+    }
+    let mut block = [0; 64];
+    for i in 0..16 {
+        let word = s[i].wrapping_add(state[i]);
+        block[(i * 4)..(i * 4 + 4)].copy_from_slice(&word.to_le_bytes());
+    }
+    block
+}
+
+pub fn chacha20(key: &[u8], counter: u32, nonce: &[u8], buf: &mut [u8]) {
+    for (i, chunk) in buf.chunks_mut(64).enumerate() {
+        let block = chacha20_block(key, counter.wrapping_add(i as u32), nonce);
+        for (a, b) in chunk.iter_mut().zip(block.iter()) {
+            *a ^= b;
+        }
+    }
+}
+
+// Poly1305 with 26 bits limbs
+pub fn poly1305(key: &[u8], msg: &[u8]) -> [u8; 16] {
+    let mask = 0x3FFFFFF;
+    let r = [
+        (le32(&key[0..]) & 0x3FFFFFF) as u64,
+        ((le32(&key[3..]) >> 2) & 0x3FFFF03) as u64,
+        ((le32(&key[6..]) >> 4) & 0x3FFC0FF) as u64,
+        ((le32(&key[9..]) >> 6) & 0x3F03FFF) as u64,
+        ((le32(&key[12..]) >> 8) & 0x00FFFFF) as u64,
+    ];
+    let s = [0, r[1] * 5, r[2] * 5, r[3] * 5, r[4] * 5];
+    let mut h = [0u32; 5];
+
+    for chunk in msg.chunks(16) {
+        let mut block = [0; 16];
+        block[0..chunk.len()].copy_from_slice(chunk);
+        let hibit = if chunk.len() == 16 {
+            1 << 24
+        } else {
+            block[chunk.len()] = 1;
+            0
+        };
+        h[0] += le32(&block[0..]) & mask;
+        h[1] += (le32(&block[3..]) >> 2) & mask;
+        h[2] += (le32(&block[6..]) >> 4) & mask;
+        h[3] += (le32(&block[9..]) >> 6) & mask;
+        h[4] += (le32(&block[12..]) >> 8) | hibit;
+
+        // Multiply h by r modulo 2^130 - 5
+        let mut d = [0u64; 5];
+        for i in 0..5 {
+            for j in 0..5 {
+                // The limbs above 2^130 wrap around multiplied by 5
+                let rj = if j <= i { r[i - j] } else { s[5 + i - j] };
+                d[i] += (h[j] as u64) * rj;
+            }
+        }
+        for i in 0..4 {
+            d[i + 1] += d[i] >> 26;
+            h[i] = (d[i] as u32) & mask;
+        }
+        h[4] = (d[4] as u32) & mask;
+        h[0] += ((d[4] >> 26) as u32) * 5;
+        h[1] += h[0] >> 26;
+        h[0] &= mask;
+    }
+
+    // Fully carry h
+    let mut c = 0;
+    for x in h.iter_mut().skip(1) {
+        *x += c;
+        c = *x >> 26;
+        *x &= mask;
+    }
+    h[0] += c * 5;
+    h[1] += h[0] >> 26;
+    h[0] &= mask;
+
+    // Compute g = h + 5 - 2^130 and select it if h >= 2^130 - 5
+    let mut g = [0u32; 5];
+    let mut c = 5;
+    for i in 0..4 {
+        g[i] = h[i] + c;
+        c = g[i] >> 26;
+        g[i] &= mask;
+    }
+    g[4] = (h[4] + c).wrapping_sub(1 << 26);
+    let m = (g[4] >> 31).wrapping_sub(1); // All ones if g is positive
+    for (a, b) in h.iter_mut().zip(g) {
+        *a = (*a & !m) | (b & m);
+    }
+
+    // Compute (h + s) % 2^128
+    let words = [
+        h[0] | (h[1] << 26),
+        (h[1] >> 6) | (h[2] << 20),
+        (h[2] >> 12) | (h[3] << 14),
+        (h[3] >> 18) | (h[4] << 8),
+    ];
+    let mut tag = [0; 16];
+    let mut f = 0u64;
+    for (i, word) in words.iter().enumerate() {
+        let pad = le32(&key[(16 + i * 4)..]) as u64;
+        f = (*word as u64) + pad + (f >> 32);
+        tag[(i * 4)..(i * 4 + 4)].copy_from_slice(&(f as u32).to_le_bytes());
+    }
+    tag
+}
+
+fn compute_tag(
+    key: &[u8],
+    nonce: &[u8],
+    aad: &[u8],
+    ciphertext: &[u8]
+) -> [u8; 16] {
+    let otk = chacha20_block(key, 0, nonce);
+    let mut data = Vec::with_capacity(aad.len() + ciphertext.len() + 48);
+    data.extend_from_slice(aad);
+    data.resize(data.len().div_ceil(16) * 16, 0);
+    data.extend_from_slice(ciphertext);
+    data.resize(data.len().div_ceil(16) * 16, 0);
+    data.extend_from_slice(&(aad.len() as u64).to_le_bytes());
+    data.extend_from_slice(&(ciphertext.len() as u64).to_le_bytes());
+    poly1305(&otk[0..32], &data)
+}
+
+// Encrypt the plaintext and return it followed by the authentication tag
+pub fn seal(
+    key: &[u8],
+    nonce: &[u8],
+    aad: &[u8],
+    plaintext: &[u8]
+) -> Vec<u8> {
+    let mut buf = plaintext.to_vec();
+    chacha20(key, 1, nonce, &mut buf);
+    let tag = compute_tag(key, nonce, aad, &buf);
+    buf.extend_from_slice(&tag);
+    buf
+}
+
+// Authenticate and decrypt the ciphertext followed by its tag
+pub fn open(
+    key: &[u8],
+    nonce: &[u8],
+    aad: &[u8],
+    buf: &[u8]
+) -> Option<Vec<u8>> {
+    if buf.len() < TAG_LEN {
+        return None;
+    }
+    let (ciphertext, tag) = buf.split_at(buf.len() - TAG_LEN);
+    let expected = compute_tag(key, nonce, aad, ciphertext);
+
+    // Compare the tags in constant time
+    let diff = expected.iter().zip(tag).fold(0, |acc, (a, b)| acc | (a ^ b));
+    if diff != 0 {
+        return None;
+    }
+    let mut plaintext = ciphertext.to_vec();
+    chacha20(key, 1, nonce, &mut plaintext);
+    Some(plaintext)
+}
+
+#[test_case]
+fn test_chacha20poly1305() {
+    let hex = |s: &str| -> Vec<u8> {
+        (0..s.len()).step_by(2).map(|i|
+            u8::from_str_radix(&s[i..(i + 2)], 16).unwrap()
+        ).collect()
+    };
+
+    // See RFC 8439 section 2.8.2
+    let key: Vec<u8> = (0x80..0xA0).collect();
+    let nonce = hex("070000004041424344454647");
+    let aad = hex("50515253C0C1C2C3C4C5C6C7");
+    let plaintext = b"Ladies and Gentlemen of the class of '99: If I could \
+        offer you only one tip for the future, sunscreen would be it.";
+    let ciphertext = hex(
+        "D31A8D34648E60DB7B86AFBC53EF7EC2A4ADED51296E08FEA9E2B5A736EE62D6\
+         3DBEA45E8CA9671282FAFB69DA92728B1A71DE0A9E060B2905D6A5B67ECD3B36\
+         92DDBD7F2D778B8C9803AEE328091B58FAB324E4FAD675945585808B4831D7BC\
+         3FF4DEF08E4B7A9DE576D26586CEC64B6116\
+         1AE10B594F09E26A7E902ECBD0600691"
+    );
+    assert_eq!(seal(&key, &nonce, &aad, plaintext), ciphertext);
+    let decrypted = open(&key, &nonce, &aad, &ciphertext);
+    assert_eq!(decrypted, Some(plaintext.to_vec()));
+
+    let mut altered = ciphertext.clone();
+    altered[0] ^= 1;
+    assert_eq!(open(&key, &nonce, &aad, &altered), None);
+    assert_eq!(open(&key, &nonce, &[], &ciphertext), None);
+}
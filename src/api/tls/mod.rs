@@ -0,0 +1,768 @@
+pub mod chacha20poly1305;
+pub mod x25519;
+pub mod x509;
+
+use crate::api::fs;
+use crate::api::rng;
+use crate::api::syscall;
+use crate::api::time;
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use x509::{Certificate, Hash};
+
+// TLS 1.3 client with a single cipher suite and key exchange group
+// See RFC 8446 for implementation details
+
+pub const TRUST_STORE: &str = "/ini/certs.pem";
+
+const CHANGE_CIPHER_SPEC: u8 = 20;
+const ALERT: u8 = 21;
+const HANDSHAKE: u8 = 22;
+const APPLICATION_DATA: u8 = 23;
+
+const CLIENT_HELLO: u8 = 1;
+const SERVER_HELLO: u8 = 2;
+const NEW_SESSION_TICKET: u8 = 4;
+const ENCRYPTED_EXTENSIONS: u8 = 8;
+const CERTIFICATE: u8 = 11;
+const CERTIFICATE_REQUEST: u8 = 13;
+const CERTIFICATE_VERIFY: u8 = 15;
+const FINISHED: u8 = 20;
+const KEY_UPDATE: u8 = 24;
+
+const EXT_SERVER_NAME: u16 = 0;
+const EXT_SUPPORTED_GROUPS: u16 = 10;
+const EXT_SIGNATURE_ALGORITHMS: u16 = 13;
+const EXT_SUPPORTED_VERSIONS: u16 = 43;
+const EXT_KEY_SHARE: u16 = 51;
+
+const TLS_CHACHA20_POLY1305_SHA256: u16 = 0x1303;
+const X25519: u16 = 0x001D;
+const TLS_1_2: u16 = 0x0303;
+const TLS_1_3: u16 = 0x0304;
+
+const RSA_PKCS1_SHA256: u16 = 0x0401;
+const RSA_PKCS1_SHA384: u16 = 0x0501;
+const RSA_PKCS1_SHA512: u16 = 0x0601;
+const RSA_PSS_RSAE_SHA256: u16 = 0x0804;
+const RSA_PSS_RSAE_SHA384: u16 = 0x0805;
+const RSA_PSS_RSAE_SHA512: u16 = 0x0806;
+
+const ALERT_CLOSE_NOTIFY: u8 = 0;
+
+const MAX_RECORD_LEN: usize = 1 << 14;
+
+// SHA-256 of "HelloRetryRequest"
+const HELLO_RETRY_REQUEST: [u8; 32] = [
+    0xCF, 0x21, 0xAD, 0x74, 0xE5, 0x9A, 0x61, 0x11, 0xBE, 0x1D, 0x8C, 0x02,
+    0x1E, 0x65, 0xB8, 0x91, 0xC2, 0xA2, 0x11, 0x16, 0x7A, 0xBB, 0x8C, 0x5E,
+    0x07, 0x9E, 0x09, 0xE2, 0xC8, 0xA8, 0x33, 0x9C,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Error {
+    NetworkError,
+    ConnectionClosed,
+    DecodeError,
+    DecryptError,
+    HandshakeFailure,
+    UnexpectedMessage,
+    UnsupportedCertificate,
+    UntrustedCertificate,
+    InvalidSignature,
+    Alert(u8),
+}
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).unwrap();
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+// See RFC 5869 for the HMAC-based key derivation function
+fn hkdf_extract(salt: &[u8], ikm: &[u8]) -> Vec<u8> {
+    hmac(salt, ikm)
+}
+
+fn hkdf_expand(prk: &[u8], info: &[u8], len: usize) -> Vec<u8> {
+    let mut okm = Vec::new();
+    let mut t = Vec::new();
+    let mut i = 1;
+    while okm.len() < len {
+        t.extend_from_slice(info);
+        t.push(i);
+        t = hmac(prk, &t);
+        okm.extend_from_slice(&t);
+        i += 1;
+    }
+    okm.truncate(len);
+    okm
+}
+
+fn expand_label(
+    secret: &[u8],
+    label: &str,
+    ctx: &[u8],
+    len: usize
+) -> Vec<u8> {
+    let label = [b"tls13 ", label.as_bytes()].concat();
+    let mut info = Vec::new();
+    info.extend_from_slice(&(len as u16).to_be_bytes());
+    push_vec8(&mut info, &label);
+    push_vec8(&mut info, ctx);
+    hkdf_expand(secret, &info, len)
+}
+
+fn derive_secret(secret: &[u8], label: &str, transcript: &[u8]) -> Vec<u8> {
+    expand_label(secret, label, &Sha256::digest(transcript), 32)
+}
+
+fn push_u16(buf: &mut Vec<u8>, n: u16) {
+    buf.extend_from_slice(&n.to_be_bytes());
+}
+
+fn push_vec8(buf: &mut Vec<u8>, data: &[u8]) {
+    buf.push(data.len() as u8);
+    buf.extend_from_slice(data);
+}
+
+fn push_vec16(buf: &mut Vec<u8>, data: &[u8]) {
+    push_u16(buf, data.len() as u16);
+    buf.extend_from_slice(data);
+}
+
+fn push_vec24(buf: &mut Vec<u8>, data: &[u8]) {
+    buf.extend_from_slice(&(data.len() as u32).to_be_bytes()[1..4]);
+    buf.extend_from_slice(data);
+}
+
+fn handshake_message(kind: u8, body: &[u8]) -> Vec<u8> {
+    let mut msg = vec![kind];
+    push_vec24(&mut msg, body);
+    msg
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    pub fn bytes(&mut self, n: usize) -> Result<&'a [u8], Error> {
+        if n > self.buf.len() {
+            return Err(Error::DecodeError);
+        }
+        let (bytes, rest) = self.buf.split_at(n);
+        self.buf = rest;
+        Ok(bytes)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16, Error> {
+        Ok(u16::from_be_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    pub fn u24(&mut self) -> Result<usize, Error> {
+        let buf = self.bytes(3)?;
+        Ok(u32::from_be_bytes([0, buf[0], buf[1], buf[2]]) as usize)
+    }
+
+    pub fn vec8(&mut self) -> Result<&'a [u8], Error> {
+        let n = self.u8()? as usize;
+        self.bytes(n)
+    }
+
+    pub fn vec16(&mut self) -> Result<&'a [u8], Error> {
+        let n = self.u16()? as usize;
+        self.bytes(n)
+    }
+
+    pub fn vec24(&mut self) -> Result<&'a [u8], Error> {
+        let n = self.u24()?;
+        self.bytes(n)
+    }
+}
+
+struct TrafficKeys {
+    secret: Vec<u8>,
+    key: Vec<u8>,
+    iv: Vec<u8>,
+    seq: u64,
+}
+
+impl TrafficKeys {
+    pub fn new(secret: &[u8]) -> Self {
+        Self {
+            secret: secret.to_vec(),
+            key: expand_label(secret, "key", &[], chacha20poly1305::KEY_LEN),
+            iv: expand_label(secret, "iv", &[], chacha20poly1305::NONCE_LEN),
+            seq: 0,
+        }
+    }
+
+    pub fn update(&self) -> Self {
+        Self::new(&expand_label(&self.secret, "traffic upd", &[], 32))
+    }
+
+    pub fn nonce(&mut self) -> Vec<u8> {
+        let mut nonce = self.iv.clone();
+        for (i, b) in self.seq.to_be_bytes().iter().enumerate() {
+            nonce[4 + i] ^= b;
+        }
+        self.seq += 1;
+        nonce
+    }
+}
+
+fn client_hello(host: &str, random: &[u8], public_key: &[u8]) -> Vec<u8> {
+    let mut body = Vec::new();
+    push_u16(&mut body, TLS_1_2);
+    body.extend_from_slice(&random[0..32]);
+    push_vec8(&mut body, &random[32..64]); // Legacy session ID
+    push_vec16(&mut body, &TLS_CHACHA20_POLY1305_SHA256.to_be_bytes());
+    push_vec8(&mut body, &[0]); // Legacy compression methods
+
+    let mut exts = Vec::new();
+    let mut ext = Vec::new();
+    if !host.is_empty() && !host.contains(':') && !is_ipv4(host) {
+        let mut name = vec![0]; // Host name type
+        push_vec16(&mut name, host.as_bytes());
+        push_vec16(&mut ext, &name);
+        push_u16(&mut exts, EXT_SERVER_NAME);
+        push_vec16(&mut exts, &ext);
+    }
+
+    ext.clear();
+    push_vec16(&mut ext, &X25519.to_be_bytes());
+    push_u16(&mut exts, EXT_SUPPORTED_GROUPS);
+    push_vec16(&mut exts, &ext);
+
+    ext.clear();
+    let mut algs = Vec::new();
+    for alg in [
+        RSA_PSS_RSAE_SHA256, RSA_PSS_RSAE_SHA384, RSA_PSS_RSAE_SHA512,
+        RSA_PKCS1_SHA256, RSA_PKCS1_SHA384, RSA_PKCS1_SHA512,
+    ] {
+        push_u16(&mut algs, alg);
+    }
+    push_vec16(&mut ext, &algs);
+    push_u16(&mut exts, EXT_SIGNATURE_ALGORITHMS);
+    push_vec16(&mut exts, &ext);
+
+    ext.clear();
+    push_vec8(&mut ext, &TLS_1_3.to_be_bytes());
+    push_u16(&mut exts, EXT_SUPPORTED_VERSIONS);
+    push_vec16(&mut exts, &ext);
+
+    ext.clear();
+    let mut share = Vec::new();
+    push_u16(&mut share, X25519);
+    push_vec16(&mut share, public_key);
+    push_vec16(&mut ext, &share);
+    push_u16(&mut exts, EXT_KEY_SHARE);
+    push_vec16(&mut exts, &ext);
+
+    push_vec16(&mut body, &exts);
+    handshake_message(CLIENT_HELLO, &body)
+}
+
+fn is_ipv4(host: &str) -> bool {
+    let parts: Vec<&str> = host.split('.').collect();
+    parts.len() == 4 && parts.iter().all(|s| s.parse::<u8>().is_ok())
+}
+
+// Return the public key of the server from its hello message
+fn parse_server_hello(body: &[u8]) -> Result<[u8; 32], Error> {
+    let mut r = Reader::new(body);
+    r.u16()?; // Legacy version
+    if r.bytes(32)? == HELLO_RETRY_REQUEST {
+        return Err(Error::HandshakeFailure);
+    }
+    r.vec8()?; // Legacy session ID
+    let cipher_suite = r.u16()?;
+    r.u8()?; // Legacy compression method
+    let mut exts = Reader::new(r.vec16()?);
+    let mut version = TLS_1_2;
+    let mut public_key = None;
+    while !exts.is_empty() {
+        let kind = exts.u16()?;
+        let mut ext = Reader::new(exts.vec16()?);
+        match kind {
+            EXT_SUPPORTED_VERSIONS => {
+                version = ext.u16()?;
+            }
+            EXT_KEY_SHARE => {
+                if ext.u16()? != X25519 {
+                    return Err(Error::HandshakeFailure);
+                }
+                let key = ext.vec16()?;
+                public_key = key.try_into().ok();
+            }
+            _ => {}
+        }
+    }
+    if version != TLS_1_3 || cipher_suite != TLS_CHACHA20_POLY1305_SHA256 {
+        return Err(Error::HandshakeFailure);
+    }
+    public_key.ok_or(Error::HandshakeFailure)
+}
+
+fn parse_certificates(body: &[u8]) -> Result<Vec<Certificate>, Error> {
+    let mut r = Reader::new(body);
+    r.vec8()?; // Certificate request context
+    let mut list = Reader::new(r.vec24()?);
+    let mut certs = Vec::new();
+    while !list.is_empty() {
+        let data = list.vec24()?;
+        list.vec16()?; // Extensions
+        match Certificate::parse(data) {
+            Some(cert) => certs.push(cert),
+            None => return Err(Error::UnsupportedCertificate),
+        }
+    }
+    Ok(certs)
+}
+
+fn verify_signature(
+    cert: &Certificate,
+    transcript: &[u8],
+    body: &[u8]
+) -> Result<(), Error> {
+    let mut r = Reader::new(body);
+    let hash = match r.u16()? {
+        RSA_PSS_RSAE_SHA256 => Hash::Sha256,
+        RSA_PSS_RSAE_SHA384 => Hash::Sha384,
+        RSA_PSS_RSAE_SHA512 => Hash::Sha512,
+        _ => return Err(Error::UnsupportedCertificate),
+    };
+    let sig = r.vec16()?;
+    let mut msg = vec![0x20; 64];
+    msg.extend_from_slice(b"TLS 1.3, server CertificateVerify\0");
+    msg.extend_from_slice(&Sha256::digest(transcript));
+    match &cert.public_key {
+        Some(key) if key.verify_pss(hash, &msg, sig) => Ok(()),
+        Some(_) => Err(Error::InvalidSignature),
+        None => Err(Error::UnsupportedCertificate),
+    }
+}
+
+fn verify_certificates(
+    host: &str,
+    certs: &[Certificate]
+) -> Result<(), Error> {
+    let pem = fs::read_to_string(TRUST_STORE).unwrap_or_default();
+    let trusted = Certificate::parse_pem(&pem);
+    let now = time::now_utc().format("%Y%m%d%H%M%S");
+    if x509::verify_chain(certs, &trusted, host, &now) {
+        Ok(())
+    } else {
+        Err(Error::UntrustedCertificate)
+    }
+}
+
+pub struct Session {
+    handle: usize,
+    records: Vec<u8>, // Data received but not yet decoded
+    messages: Vec<u8>, // Handshake messages not yet processed
+    data: Vec<u8>, // Application data not yet read
+    client_keys: Option<TrafficKeys>,
+    server_keys: Option<TrafficKeys>,
+    is_closed: bool,
+}
+
+impl Session {
+    // Establish a session over a connected TCP socket, optionally skipping
+    // the verification of the certificates of the server.
+    pub fn connect(
+        handle: usize,
+        host: &str,
+        verify: bool
+    ) -> Result<Self, Error> {
+        let mut session = Self {
+            handle,
+            records: Vec::new(),
+            messages: Vec::new(),
+            data: Vec::new(),
+            client_keys: None,
+            server_keys: None,
+            is_closed: false,
+        };
+        session.handshake(host, verify)?;
+        Ok(session)
+    }
+
+    fn handshake(&mut self, host: &str, verify: bool) -> Result<(), Error> {
+        let mut random = [0; 96];
+        if !rng::fill_bytes(&mut random) {
+            return Err(Error::HandshakeFailure);
+        }
+        let private_key: [u8; 32] = random[64..96].try_into().unwrap();
+        let public_key = x25519::public_key(&private_key);
+
+        let hello = client_hello(host, &random, &public_key);
+        self.write_record(HANDSHAKE, &hello)?;
+        let mut transcript = hello;
+
+        let msg = self.read_handshake_message()?;
+        if msg[0] != SERVER_HELLO {
+            return Err(Error::UnexpectedMessage);
+        }
+        let server_key = parse_server_hello(&msg[4..])?;
+        transcript.extend_from_slice(&msg);
+
+        let shared_secret = x25519::x25519(&private_key, &server_key);
+        if shared_secret.iter().all(|&b| b == 0) {
+            return Err(Error::HandshakeFailure);
+        }
+
+        // Key schedule
+        let zeros = [0; 32];
+        let early_secret = hkdf_extract(&[], &zeros);
+        let salt = derive_secret(&early_secret, "derived", &[]);
+        let secret = hkdf_extract(&salt, &shared_secret);
+        let c_hs_secret = derive_secret(&secret, "c hs traffic", &transcript);
+        let s_hs_secret = derive_secret(&secret, "s hs traffic", &transcript);
+        let salt = derive_secret(&secret, "derived", &[]);
+        let master = hkdf_extract(&salt, &zeros);
+        self.server_keys = Some(TrafficKeys::new(&s_hs_secret));
+
+        let mut certs = Vec::new();
+        let mut cert_request = None;
+        let mut is_verified = false;
+        loop {
+            let msg = self.read_handshake_message()?;
+            let body = &msg[4..];
+            match msg[0] {
+                ENCRYPTED_EXTENSIONS => {}
+                CERTIFICATE_REQUEST => {
+                    cert_request = Some(Reader::new(body).vec8()?.to_vec());
+                }
+                CERTIFICATE if certs.is_empty() => {
+                    certs = parse_certificates(body)?;
+                    if certs.is_empty() {
+                        return Err(Error::HandshakeFailure);
+                    }
+                    if verify {
+                        verify_certificates(host, &certs)?;
+                    }
+                }
+                CERTIFICATE_VERIFY if !certs.is_empty() => {
+                    if verify {
+                        verify_signature(&certs[0], &transcript, body)?;
+                    }
+                    is_verified = true;
+                }
+                FINISHED if is_verified => {
+                    let key = expand_label(&s_hs_secret, "finished", &[], 32);
+                    if hmac(&key, &Sha256::digest(&transcript)) != body {
+                        return Err(Error::DecryptError);
+                    }
+                    transcript.extend_from_slice(&msg);
+                    break;
+                }
+                _ => {
+                    return Err(Error::UnexpectedMessage);
+                }
+            }
+            transcript.extend_from_slice(&msg);
+        }
+
+        let c_ap_secret = derive_secret(&master, "c ap traffic", &transcript);
+        let s_ap_secret = derive_secret(&master, "s ap traffic", &transcript);
+
+        self.client_keys = Some(TrafficKeys::new(&c_hs_secret));
+        if let Some(ctx) = cert_request {
+            // We don't have any certificate to send
+            let mut body = Vec::new();
+            push_vec8(&mut body, &ctx);
+            push_vec24(&mut body, &[]);
+            let msg = handshake_message(CERTIFICATE, &body);
+            self.write_record(HANDSHAKE, &msg)?;
+            transcript.extend_from_slice(&msg);
+        }
+        let key = expand_label(&c_hs_secret, "finished", &[], 32);
+        let verify_data = hmac(&key, &Sha256::digest(&transcript));
+        let msg = handshake_message(FINISHED, &verify_data);
+        self.write_record(HANDSHAKE, &msg)?;
+
+        self.client_keys = Some(TrafficKeys::new(&c_ap_secret));
+        self.server_keys = Some(TrafficKeys::new(&s_ap_secret));
+        Ok(())
+    }
+
+    fn send(&mut self, buf: &[u8]) -> Result<(), Error> {
+        let mut i = 0;
+        while i < buf.len() {
+            match syscall::write(self.handle, &buf[i..]) {
+                Some(n) => i += n,
+                None => return Err(Error::NetworkError),
+            }
+        }
+        Ok(())
+    }
+
+    fn write_record(&mut self, kind: u8, data: &[u8]) -> Result<(), Error> {
+        let mut record = Vec::new();
+        if let Some(keys) = &mut self.client_keys {
+            let plaintext = [data, &[kind]].concat();
+            let len = plaintext.len() + chacha20poly1305::TAG_LEN;
+            record.push(APPLICATION_DATA);
+            push_u16(&mut record, TLS_1_2);
+            push_u16(&mut record, len as u16);
+            let nonce = keys.nonce();
+            let ciphertext = chacha20poly1305::seal(
+                &keys.key, &nonce, &record, &plaintext
+            );
+            record.extend_from_slice(&ciphertext);
+        } else {
+            record.push(kind);
+            push_u16(&mut record, TLS_1_2);
+            push_vec16(&mut record, data);
+        }
+        self.send(&record)
+    }
+
+    // Read the next record and return its content type and its decrypted
+    // content.
+    fn read_record(&mut self) -> Result<(u8, Vec<u8>), Error> {
+        loop {
+            if self.records.len() >= 5 {
+                let buf = &self.records;
+                let len = u16::from_be_bytes([buf[3], buf[4]]) as usize;
+                if len > MAX_RECORD_LEN + 256 {
+                    return Err(Error::DecodeError);
+                }
+                if self.records.len() >= 5 + len {
+                    let n = 5 + len;
+                    let record: Vec<u8> = self.records.drain(0..n).collect();
+                    let (header, content) = record.split_at(5);
+                    let kind = header[0];
+                    if kind == CHANGE_CIPHER_SPEC {
+                        continue; // Ignored for middlebox compatibility
+                    }
+                    let keys = match &mut self.server_keys {
+                        Some(keys) => keys,
+                        None => return Ok((kind, content.to_vec())),
+                    };
+                    if kind != APPLICATION_DATA {
+                        return Err(Error::UnexpectedMessage);
+                    }
+                    let nonce = keys.nonce();
+                    let mut plaintext = chacha20poly1305::open(
+                        &keys.key, &nonce, header, content
+                    ).ok_or(Error::DecryptError)?;
+
+                    // Remove the padding to find the real content type
+                    while plaintext.last() == Some(&0) {
+                        plaintext.pop();
+                    }
+                    match plaintext.pop() {
+                        Some(kind) => return Ok((kind, plaintext)),
+                        None => return Err(Error::UnexpectedMessage),
+                    }
+                }
+            }
+            let mut buf = vec![0; 2048];
+            match syscall::read(self.handle, &mut buf) {
+                Some(0) => {
+                    return Err(Error::ConnectionClosed);
+                }
+                Some(n) => {
+                    self.records.extend_from_slice(&buf[0..n]);
+                }
+                None => {
+                    return Err(Error::NetworkError);
+                }
+            }
+        }
+    }
+
+    fn read_alert(&mut self, content: &[u8]) -> Result<(), Error> {
+        match content.get(1) {
+            Some(&ALERT_CLOSE_NOTIFY) => {
+                self.is_closed = true;
+                Ok(())
+            }
+            Some(&code) => {
+                self.is_closed = true;
+                Err(Error::Alert(code))
+            }
+            None => {
+                Err(Error::DecodeError)
+            }
+        }
+    }
+
+    // Return the next handshake message with its header if it has been
+    // completely received.
+    fn pop_handshake_message(&mut self) -> Option<Vec<u8>> {
+        let buf = self.messages.get(0..4)?;
+        let len = u32::from_be_bytes([0, buf[1], buf[2], buf[3]]) as usize;
+        if self.messages.len() < 4 + len {
+            return None;
+        }
+        Some(self.messages.drain(0..(4 + len)).collect())
+    }
+
+    fn read_handshake_message(&mut self) -> Result<Vec<u8>, Error> {
+        loop {
+            if let Some(msg) = self.pop_handshake_message() {
+                return Ok(msg);
+            }
+            let (kind, content) = self.read_record()?;
+            match kind {
+                HANDSHAKE => {
+                    self.messages.extend_from_slice(&content);
+                }
+                ALERT => {
+                    self.read_alert(&content)?;
+                    return Err(Error::UnexpectedMessage);
+                }
+                _ => {
+                    return Err(Error::UnexpectedMessage);
+                }
+            }
+        }
+    }
+
+    fn process_message(&mut self, msg: &[u8]) -> Result<(), Error> {
+        match msg[0] {
+            NEW_SESSION_TICKET => {} // Session resumption is not supported
+            KEY_UPDATE => {
+                let keys = self.server_keys.as_ref().unwrap().update();
+                self.server_keys = Some(keys);
+                if msg.get(4) == Some(&1) { // Update requested
+                    let msg = handshake_message(KEY_UPDATE, &[0]);
+                    self.write_record(HANDSHAKE, &msg)?;
+                    let keys = self.client_keys.as_ref().unwrap().update();
+                    self.client_keys = Some(keys);
+                }
+            }
+            _ => {
+                return Err(Error::UnexpectedMessage);
+            }
+        }
+        Ok(())
+    }
+
+    // Read decrypted application data and return 0 bytes when the server
+    // has closed the session.
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        while self.data.is_empty() && !self.is_closed {
+            let (kind, content) = match self.read_record() {
+                Ok(record) => record,
+                Err(Error::ConnectionClosed) => {
+                    // The server closed the connection without sending
+                    // a close notify alert
+                    self.is_closed = true;
+                    break;
+                }
+                Err(e) => return Err(e),
+            };
+            match kind {
+                APPLICATION_DATA => {
+                    self.data = content;
+                }
+                HANDSHAKE => {
+                    self.messages.extend_from_slice(&content);
+                    while let Some(msg) = self.pop_handshake_message() {
+                        self.process_message(&msg)?;
+                    }
+                }
+                ALERT => {
+                    self.read_alert(&content)?;
+                }
+                _ => {
+                    return Err(Error::UnexpectedMessage);
+                }
+            }
+        }
+        let n = buf.len().min(self.data.len());
+        buf[0..n].copy_from_slice(&self.data[0..n]);
+        self.data.drain(0..n);
+        Ok(n)
+    }
+
+    pub fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        for chunk in buf.chunks(MAX_RECORD_LEN) {
+            self.write_record(APPLICATION_DATA, chunk)?;
+        }
+        Ok(buf.len())
+    }
+
+    pub fn close(&mut self) {
+        if !self.is_closed {
+            self.write_record(ALERT, &[1, ALERT_CLOSE_NOTIFY]).ok();
+            self.is_closed = true;
+        }
+    }
+}
+
+#[test_case]
+fn test_key_schedule() {
+    // See RFC 8448 section 3
+    let early_secret = hkdf_extract(&[], &[0; 32]);
+    assert_eq!(early_secret, [
+        0x33, 0xAD, 0x0A, 0x1C, 0x60, 0x7E, 0xC0, 0x3B, 0x09, 0xE6, 0xCD,
+        0x98, 0x93, 0x68, 0x0C, 0xE2, 0x10, 0xAD, 0xF3, 0x00, 0xAA, 0x1F,
+        0x26, 0x60, 0xE1, 0xB2, 0x2E, 0x10, 0xF1, 0x70, 0xF9, 0x2A,
+    ]);
+    assert_eq!(derive_secret(&early_secret, "derived", &[]), [
+        0x6F, 0x26, 0x15, 0xA1, 0x08, 0xC7, 0x02, 0xC5, 0x67, 0x8F, 0x54,
+        0xFC, 0x9D, 0xBA, 0xB6, 0x97, 0x16, 0xC0, 0x76, 0x18, 0x9C, 0x48,
+        0x25, 0x0C, 0xEB, 0xEA, 0xC3, 0x57, 0x6C, 0x36, 0x11, 0xBA,
+    ]);
+}
+
+#[test_case]
+fn test_client_hello() {
+    let random = [0; 96];
+    let public_key = [1; 32];
+    let msg = client_hello("moros.cc", &random, &public_key);
+    assert_eq!(msg[0], CLIENT_HELLO);
+
+    let mut r = Reader::new(&msg[1..]);
+    let mut body = Reader::new(r.vec24().unwrap());
+    assert!(r.is_empty());
+    assert_eq!(body.u16(), Ok(TLS_1_2));
+    assert_eq!(body.bytes(32), Ok(&random[0..32]));
+    assert_eq!(body.vec8(), Ok(&random[32..64]));
+    assert_eq!(body.vec16(), Ok(&[0x13, 0x03][..]));
+    assert_eq!(body.vec8(), Ok(&[0][..]));
+
+    let mut exts = Reader::new(body.vec16().unwrap());
+    assert!(body.is_empty());
+    let mut kinds = Vec::new();
+    while !exts.is_empty() {
+        kinds.push(exts.u16().unwrap());
+        exts.vec16().unwrap();
+    }
+    assert_eq!(kinds, [
+        EXT_SERVER_NAME,
+        EXT_SUPPORTED_GROUPS,
+        EXT_SIGNATURE_ALGORITHMS,
+        EXT_SUPPORTED_VERSIONS,
+        EXT_KEY_SHARE,
+    ]);
+
+    // The server name is only sent for hostnames
+    let msg = client_hello("10.0.2.2", &random, &public_key);
+    let exts = &msg[(4 + 2 + 32 + 33 + 4 + 2 + 2)..];
+    assert_eq!(u16::from_be_bytes([exts[0], exts[1]]), EXT_SUPPORTED_GROUPS);
+}
@@ -0,0 +1,195 @@
+// See RFC 7748 for implementation details
+
+// Field element of GF(2^255 - 19) stored in 16 limbs of 16 bits
+type Fe = [i64; 16];
+
+const A24: Fe = [0xDB41, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+pub const BASE_POINT: [u8; 32] = [
+    9, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+];
+
+fn carry(o: &mut Fe) {
+    for i in 0..16 {
+        o[i] += 1 << 16;
+        let c = o[i] >> 16;
+        if i < 15 {
+            o[i + 1] += c - 1;
+        } else {
+            o[0] += 38 * (c - 1);
+        }
+        o[i] -= c << 16;
+    }
+}
+
+// Swap `p` and `q` in constant time if `b` is 1
+fn swap(p: &mut Fe, q: &mut Fe, b: i64) {
+    let c = !(b - 1);
+    for i in 0..16 {
+        let t = c & (p[i] ^ q[i]);
+        p[i] ^= t;
+        q[i] ^= t;
+    }
+}
+
+fn pack(n: &Fe) -> [u8; 32] {
+    let mut t = *n;
+    carry(&mut t);
+    carry(&mut t);
+    carry(&mut t);
+    for _ in 0..2 {
+        let mut m = [0; 16];
+        m[0] = t[0] - 0xFFED;
+        for i in 1..15 {
+            m[i] = t[i] - 0xFFFF - ((m[i - 1] >> 16) & 1);
+            m[i - 1] &= 0xFFFF;
+        }
+        m[15] = t[15] - 0x7FFF - ((m[14] >> 16) & 1);
+        let b = (m[15] >> 16) & 1;
+        m[14] &= 0xFFFF;
+        swap(&mut t, &mut m, 1 - b);
+    }
+    let mut o = [0; 32];
+    for i in 0..16 {
+        o[2 * i] = t[i] as u8;
+        o[2 * i + 1] = (t[i] >> 8) as u8;
+    }
+    o
+}
+
+fn unpack(n: &[u8; 32]) -> Fe {
+    let mut o = [0; 16];
+    for i in 0..16 {
+        o[i] = (n[2 * i] as i64) + ((n[2 * i + 1] as i64) << 8);
+    }
+    o[15] &= 0x7FFF;
+    o
+}
+
+fn add(a: &Fe, b: &Fe) -> Fe {
+    let mut o = [0; 16];
+    for i in 0..16 {
+        o[i] = a[i] + b[i];
+    }
+    o
+}
+
+fn sub(a: &Fe, b: &Fe) -> Fe {
+    let mut o = [0; 16];
+    for i in 0..16 {
+        o[i] = a[i] - b[i];
+    }
+    o
+}
+
+fn mul(a: &Fe, b: &Fe) -> Fe {
+    let mut t = [0; 31];
+    for i in 0..16 {
+        for j in 0..16 {
+            t[i + j] += a[i] * b[j];
+        }
+    }
+    for i in 0..15 {
+        t[i] += 38 * t[i + 16];
+    }
+    let mut o = [0; 16];
+    o.copy_from_slice(&t[0..16]);
+    carry(&mut o);
+    carry(&mut o);
+    o
+}
+
+fn square(a: &Fe) -> Fe {
+    mul(a, a)
+}
+
+// Compute the inverse with Fermat's little theorem: a^(p - 2)
+fn invert(a: &Fe) -> Fe {
+    let mut c = *a;
+    for i in (0..254).rev() {
+        c = square(&c);
+        if i != 2 && i != 4 {
+            c = mul(&c, a);
+        }
+    }
+    c
+}
+
+// Multiply the point `u` by the `scalar` on Curve25519
+pub fn x25519(scalar: &[u8; 32], u: &[u8; 32]) -> [u8; 32] {
+    let mut z = *scalar;
+    z[31] = (z[31] & 127) | 64;
+    z[0] &= 248;
+    let x = unpack(u);
+    let mut a = [0; 16];
+    let mut b = x;
+    let mut c = [0; 16];
+    let mut d = [0; 16];
+    a[0] = 1;
+    d[0] = 1;
+    for i in (0..255).rev() {
+        let r = ((z[i >> 3] >> (i & 7)) & 1) as i64;
+        swap(&mut a, &mut b, r);
+        swap(&mut c, &mut d, r);
+        let e = add(&a, &c);
+        a = sub(&a, &c);
+        c = add(&b, &d);
+        b = sub(&b, &d);
+        d = square(&e);
+        let f = square(&a);
+        a = mul(&c, &a);
+        c = mul(&b, &e);
+        let e = add(&a, &c);
+        a = sub(&a, &c);
+        b = square(&a);
+        c = sub(&d, &f);
+        a = mul(&c, &A24);
+        a = add(&a, &d);
+        c = mul(&c, &a);
+        a = mul(&d, &f);
+        d = mul(&b, &x);
+        b = square(&e);
+        swap(&mut a, &mut b, r);
+        swap(&mut c, &mut d, r);
+    }
+    pack(&mul(&a, &invert(&c)))
+}
+
+pub fn public_key(private_key: &[u8; 32]) -> [u8; 32] {
+    x25519(private_key, &BASE_POINT)
+}
+
+#[test_case]
+fn test_x25519() {
+    // See RFC 7748 section 6.1
+    let alice_private_key = [
+        0x77, 0x07, 0x6D, 0x0A, 0x73, 0x18, 0xA5, 0x7D, 0x3C, 0x16, 0xC1,
+        0x72, 0x51, 0xB2, 0x66, 0x45, 0xDF, 0x4C, 0x2F, 0x87, 0xEB, 0xC0,
+        0x99, 0x2A, 0xB1, 0x77, 0xFB, 0xA5, 0x1D, 0xB9, 0x2C, 0x2A,
+    ];
+    let alice_public_key = [
+        0x85, 0x20, 0xF0, 0x09, 0x89, 0x30, 0xA7, 0x54, 0x74, 0x8B, 0x7D,
+        0xDC, 0xB4, 0x3E, 0xF7, 0x5A, 0x0D, 0xBF, 0x3A, 0x0D, 0x26, 0x38,
+        0x1A, 0xF4, 0xEB, 0xA4, 0xA9, 0x8E, 0xAA, 0x9B, 0x4E, 0x6A,
+    ];
+    let bob_private_key = [
+        0x5D, 0xAB, 0x08, 0x7E, 0x62, 0x4A, 0x8A, 0x4B, 0x79, 0xE1, 0x7F,
+        0x8B, 0x83, 0x80, 0x0E, 0xE6, 0x6F, 0x3B, 0xB1, 0x29, 0x26, 0x18,
+        0xB6, 0xFD, 0x1C, 0x2F, 0x8B, 0x27, 0xFF, 0x88, 0xE0, 0xEB,
+    ];
+    let bob_public_key = [
+        0xDE, 0x9E, 0xDB, 0x7D, 0x7B, 0x7D, 0xC1, 0xB4, 0xD3, 0x5B, 0x61,
+        0xC2, 0xEC, 0xE4, 0x35, 0x37, 0x3F, 0x83, 0x43, 0xC8, 0x5B, 0x78,
+        0x67, 0x4D, 0xAD, 0xFC, 0x7E, 0x14, 0x6F, 0x88, 0x2B, 0x4F,
+    ];
+    let shared_secret = [
+        0x4A, 0x5D, 0x9D, 0x5B, 0xA4, 0xCE, 0x2D, 0xE1, 0x72, 0x8E, 0x3B,
+        0xF4, 0x80, 0x35, 0x0F, 0x25, 0xE0, 0x7E, 0x21, 0xC9, 0x47, 0xD1,
+        0x9E, 0x33, 0x76, 0xF0, 0x9B, 0x3C, 0x1E, 0x16, 0x17, 0x42,
+    ];
+    assert_eq!(public_key(&alice_private_key), alice_public_key);
+    assert_eq!(public_key(&bob_private_key), bob_public_key);
+    assert_eq!(x25519(&alice_private_key, &bob_public_key), shared_secret);
+    assert_eq!(x25519(&bob_private_key, &alice_public_key), shared_secret);
+}
@@ -0,0 +1,565 @@
+use crate::api::base64::Base64;
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+// See RFC 5280 for implementation details
+
+const TAG_BOOLEAN: u8 = 0x01;
+const TAG_INTEGER: u8 = 0x02;
+const TAG_BIT_STRING: u8 = 0x03;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_OID: u8 = 0x06;
+const TAG_UTC_TIME: u8 = 0x17;
+const TAG_GENERALIZED_TIME: u8 = 0x18;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_SET: u8 = 0x31;
+const TAG_DNS_NAME: u8 = 0x82;
+const TAG_IP_ADDRESS: u8 = 0x87;
+const TAG_VERSION: u8 = 0xA0;
+const TAG_EXTENSIONS: u8 = 0xA3;
+
+const OID_RSA_ENCRYPTION: &[u8] = &[
+    0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x01, 0x01
+];
+const OID_SHA256_WITH_RSA: &[u8] = &[
+    0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x01, 0x0B
+];
+const OID_SHA384_WITH_RSA: &[u8] = &[
+    0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x01, 0x0C
+];
+const OID_SHA512_WITH_RSA: &[u8] = &[
+    0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x01, 0x0D
+];
+const OID_COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+const OID_SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1D, 0x11];
+const OID_BASIC_CONSTRAINTS: &[u8] = &[0x55, 0x1D, 0x13];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Hash {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl Hash {
+    pub fn digest(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Hash::Sha256 => Sha256::digest(data).to_vec(),
+            Hash::Sha384 => Sha384::digest(data).to_vec(),
+            Hash::Sha512 => Sha512::digest(data).to_vec(),
+        }
+    }
+
+    // Prefix of the DER encoded DigestInfo of PKCS #1 v1.5 signatures
+    fn digest_info(&self) -> &'static [u8] {
+        match self {
+            Hash::Sha256 => &[
+                0x30, 0x31, 0x30, 0x0D, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01,
+                0x65, 0x03, 0x04, 0x02, 0x01, 0x05, 0x00, 0x04, 0x20
+            ],
+            Hash::Sha384 => &[
+                0x30, 0x41, 0x30, 0x0D, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01,
+                0x65, 0x03, 0x04, 0x02, 0x02, 0x05, 0x00, 0x04, 0x30
+            ],
+            Hash::Sha512 => &[
+                0x30, 0x51, 0x30, 0x0D, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01,
+                0x65, 0x03, 0x04, 0x02, 0x03, 0x05, 0x00, 0x04, 0x40
+            ],
+        }
+    }
+}
+
+// Parse a DER encoded value and return its tag, its content, and the rest
+// of the buffer.
+fn parse_der(buf: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let tag = *buf.first()?;
+    let first = *buf.get(1)? as usize;
+    let (len, i) = if first < 0x80 {
+        (first, 2)
+    } else {
+        let n = first & 0x7F;
+        if n == 0 || n > 4 {
+            return None;
+        }
+        let mut len = 0;
+        for b in buf.get(2..(2 + n))? {
+            len = (len << 8) | (*b as usize);
+        }
+        (len, 2 + n)
+    };
+    let end = i.checked_add(len)?;
+    Some((tag, buf.get(i..end)?, &buf[end..]))
+}
+
+// Iterate over a sequence of DER encoded values
+struct DerIter<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Iterator for DerIter<'a> {
+    type Item = (u8, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (tag, content, rest) = parse_der(self.buf)?;
+        self.buf = rest;
+        Some((tag, content))
+    }
+}
+
+fn der_iter(buf: &[u8]) -> DerIter {
+    DerIter { buf }
+}
+
+fn expect(buf: &[u8], tag: u8) -> Option<(&[u8], &[u8])> {
+    match parse_der(buf)? {
+        (t, content, rest) if t == tag => Some((content, rest)),
+        _ => None,
+    }
+}
+
+// Return the content of a bit string without its unused bits count
+fn bit_string(buf: &[u8]) -> Option<&[u8]> {
+    match buf.split_first()? {
+        (0, content) => Some(content),
+        _ => None,
+    }
+}
+
+// Return a time as a "YYYYMMDDHHMMSS" string that can be compared with
+// other times.
+fn parse_time(tag: u8, buf: &[u8]) -> Option<String> {
+    let s = core::str::from_utf8(buf).ok()?.trim_end_matches('Z');
+    if !s.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    match (tag, s.len()) {
+        (TAG_UTC_TIME, 12) => {
+            let century = if &s[0..2] < "50" { "20" } else { "19" };
+            Some(format!("{}{}", century, s))
+        }
+        (TAG_GENERALIZED_TIME, 14) => {
+            Some(s.to_string())
+        }
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RsaPublicKey {
+    n: BigUint,
+    e: BigUint,
+}
+
+impl RsaPublicKey {
+    fn parse(buf: &[u8]) -> Option<Self> {
+        let (seq, _) = expect(buf, TAG_SEQUENCE)?;
+        let (n, rest) = expect(seq, TAG_INTEGER)?;
+        let (e, _) = expect(rest, TAG_INTEGER)?;
+        Some(Self {
+            n: BigUint::from_bytes_be(n),
+            e: BigUint::from_bytes_be(e),
+        })
+    }
+
+    fn bits(&self) -> usize {
+        self.n.bits() as usize
+    }
+
+    // Return the encoded message of the signature
+    fn encrypt(&self, signature: &[u8], len: usize) -> Option<Vec<u8>> {
+        let s = BigUint::from_bytes_be(signature);
+        if s >= self.n {
+            return None;
+        }
+        let m = s.modpow(&self.e, &self.n).to_bytes_be();
+        if m.len() > len {
+            return None;
+        }
+        let mut em = vec![0; len - m.len()];
+        em.extend_from_slice(&m);
+        Some(em)
+    }
+
+    // See RFC 8017 section 8.2.2
+    pub fn verify_pkcs1(&self, hash: Hash, msg: &[u8], sig: &[u8]) -> bool {
+        let k = self.bits().div_ceil(8);
+        let info = hash.digest_info();
+        let digest = hash.digest(msg);
+        let t_len = info.len() + digest.len();
+        if sig.len() != k || k < t_len + 11 {
+            return false;
+        }
+        let mut expected = vec![0x00, 0x01];
+        expected.resize(k - t_len - 1, 0xFF);
+        expected.push(0x00);
+        expected.extend_from_slice(info);
+        expected.extend_from_slice(&digest);
+        self.encrypt(sig, k) == Some(expected)
+    }
+
+    // See RFC 8017 section 8.1.2 with a salt as long as the hash
+    pub fn verify_pss(&self, hash: Hash, msg: &[u8], sig: &[u8]) -> bool {
+        let k = self.bits().div_ceil(8);
+        if sig.len() != k {
+            return false;
+        }
+        let em_bits = self.bits() - 1;
+        let em_len = em_bits.div_ceil(8);
+        let em = match self.encrypt(sig, k) {
+            Some(em) => em,
+            None => return false,
+        };
+        if em[0..(k - em_len)].iter().any(|&b| b != 0) {
+            return false;
+        }
+        let em = &em[(k - em_len)..];
+        let h_len = hash.digest(&[]).len();
+        let s_len = h_len;
+        if em_len < h_len + s_len + 2 || em[em_len - 1] != 0xBC {
+            return false;
+        }
+        let (masked_db, h) = em[0..(em_len - 1)].split_at(em_len - h_len - 1);
+        let zero_bits = 8 * em_len - em_bits;
+        let top_mask = 0xFFu8.checked_shr(zero_bits as u32).unwrap_or(0);
+        if masked_db[0] & !top_mask != 0 {
+            return false;
+        }
+
+        // Mask generation function MGF1
+        let mut db = Vec::with_capacity(masked_db.len());
+        let mut counter = 0u32;
+        while db.len() < masked_db.len() {
+            let mut seed = h.to_vec();
+            seed.extend_from_slice(&counter.to_be_bytes());
+            db.extend_from_slice(&hash.digest(&seed));
+            counter += 1;
+        }
+        db.truncate(masked_db.len());
+        for (a, b) in db.iter_mut().zip(masked_db) {
+            *a ^= b;
+        }
+        db[0] &= top_mask;
+
+        let ps_len = em_len - h_len - s_len - 2;
+        if db[0..ps_len].iter().any(|&b| b != 0) || db[ps_len] != 0x01 {
+            return false;
+        }
+        let salt = &db[(ps_len + 1)..];
+        let mut m = vec![0; 8];
+        m.extend_from_slice(&hash.digest(msg));
+        m.extend_from_slice(salt);
+        hash.digest(&m) == h
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Certificate {
+    pub der: Vec<u8>,
+    pub issuer: Vec<u8>,
+    pub subject: Vec<u8>,
+    pub not_before: String,
+    pub not_after: String,
+    pub names: Vec<String>,
+    pub is_ca: bool,
+    pub public_key: Option<RsaPublicKey>,
+    tbs: Vec<u8>,
+    signature_algorithm: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+impl Certificate {
+    pub fn parse(der: &[u8]) -> Option<Self> {
+        let (cert, _) = expect(der, TAG_SEQUENCE)?;
+        let (_, tbs_content, rest) = parse_der(cert)?;
+        let tbs = &cert[0..(cert.len() - rest.len())];
+        let (alg, rest) = expect(rest, TAG_SEQUENCE)?;
+        let (sig, _) = expect(rest, TAG_BIT_STRING)?;
+        let (oid, _) = expect(alg, TAG_OID)?;
+
+        let mut fields = der_iter(tbs_content).peekable();
+        if fields.peek()?.0 == TAG_VERSION {
+            fields.next();
+        }
+        fields.next()?; // Serial number
+        fields.next()?; // Signature algorithm
+        let (_, issuer) = fields.next()?;
+        let (_, validity) = fields.next()?;
+        let (_, subject) = fields.next()?;
+        let (_, spki) = fields.next()?;
+
+        let mut times = der_iter(validity);
+        let (tag, buf) = times.next()?;
+        let not_before = parse_time(tag, buf)?;
+        let (tag, buf) = times.next()?;
+        let not_after = parse_time(tag, buf)?;
+
+        let (key_alg, rest) = expect(spki, TAG_SEQUENCE)?;
+        let (key, _) = expect(rest, TAG_BIT_STRING)?;
+        let (key_oid, _) = expect(key_alg, TAG_OID)?;
+        let public_key = if key_oid == OID_RSA_ENCRYPTION {
+            Some(RsaPublicKey::parse(bit_string(key)?)?)
+        } else {
+            None
+        };
+
+        let mut names = Vec::new();
+        let mut is_ca = false;
+        for (tag, buf) in fields {
+            if tag != TAG_EXTENSIONS {
+                continue;
+            }
+            let (exts, _) = expect(buf, TAG_SEQUENCE)?;
+            for (_, ext) in der_iter(exts) {
+                let mut items = der_iter(ext);
+                let (_, ext_oid) = items.next()?;
+                let mut item = items.next()?;
+                if item.0 == TAG_BOOLEAN {
+                    item = items.next()?; // Critical flag
+                }
+                if item.0 != TAG_OCTET_STRING {
+                    return None;
+                }
+                let value = match expect(item.1, TAG_SEQUENCE) {
+                    Some((value, _)) => value,
+                    None => continue,
+                };
+                if ext_oid == OID_SUBJECT_ALT_NAME {
+                    for (tag, name) in der_iter(value) {
+                        match tag {
+                            TAG_DNS_NAME => {
+                                let name = String::from_utf8_lossy(name);
+                                names.push(name.to_lowercase());
+                            }
+                            TAG_IP_ADDRESS => {
+                                names.push(ip_address_name(name));
+                            }
+                            _ => {}
+                        }
+                    }
+                } else if ext_oid == OID_BASIC_CONSTRAINTS {
+                    if let Some((TAG_BOOLEAN, buf, _)) = parse_der(value) {
+                        is_ca = buf.first() == Some(&0xFF);
+                    }
+                }
+            }
+        }
+        if names.is_empty() {
+            if let Some(name) = common_name(subject) {
+                names.push(name.to_lowercase());
+            }
+        }
+
+        Some(Self {
+            der: der.to_vec(),
+            issuer: issuer.to_vec(),
+            subject: subject.to_vec(),
+            not_before,
+            not_after,
+            names,
+            is_ca,
+            public_key,
+            tbs: tbs.to_vec(),
+            signature_algorithm: oid.to_vec(),
+            signature: bit_string(sig)?.to_vec(),
+        })
+    }
+
+    // Parse all the certificates of a PEM file
+    pub fn parse_pem(pem: &str) -> Vec<Self> {
+        let mut certs = Vec::new();
+        let mut data = String::new();
+        let mut is_cert = false;
+        for line in pem.lines() {
+            let line = line.trim();
+            if line == "-----BEGIN CERTIFICATE-----" {
+                data.clear();
+                is_cert = true;
+            } else if line == "-----END CERTIFICATE-----" {
+                let data = data.trim_end_matches('=');
+                if let Ok(der) = Base64::decode(data.as_bytes()) {
+                    if let Some(cert) = Self::parse(&der) {
+                        certs.push(cert);
+                    }
+                }
+                is_cert = false;
+            } else if is_cert {
+                data.push_str(line);
+            }
+        }
+        certs
+    }
+
+    // Check that the certificate was signed by the issuer
+    pub fn is_signed_by(&self, issuer: &Certificate) -> bool {
+        if self.issuer != issuer.subject {
+            return false;
+        }
+        let hash = match self.signature_algorithm.as_slice() {
+            OID_SHA256_WITH_RSA => Hash::Sha256,
+            OID_SHA384_WITH_RSA => Hash::Sha384,
+            OID_SHA512_WITH_RSA => Hash::Sha512,
+            _ => return false,
+        };
+        match &issuer.public_key {
+            Some(key) => key.verify_pkcs1(hash, &self.tbs, &self.signature),
+            None => false,
+        }
+    }
+
+    pub fn is_valid_at(&self, time: &str) -> bool {
+        self.not_before.as_str() <= time && time <= self.not_after.as_str()
+    }
+
+    pub fn matches_host(&self, host: &str) -> bool {
+        let host = host.to_lowercase();
+        self.names.iter().any(|name| {
+            if let Some(suffix) = name.strip_prefix("*.") {
+                // The wildcard can only match the leftmost label
+                match host.split_once('.') {
+                    Some((label, rest)) => !label.is_empty() && rest == suffix,
+                    None => false,
+                }
+            } else {
+                *name == host
+            }
+        })
+    }
+}
+
+fn common_name(name: &[u8]) -> Option<String> {
+    for (tag, set) in der_iter(name) {
+        if tag != TAG_SET {
+            continue;
+        }
+        for (_, attr) in der_iter(set) {
+            let mut items = der_iter(attr);
+            if let (Some((TAG_OID, oid)), Some((_, value))) =
+                (items.next(), items.next())
+            {
+                if oid == OID_COMMON_NAME {
+                    return Some(String::from_utf8_lossy(value).to_string());
+                }
+            }
+        }
+    }
+    None
+}
+
+fn ip_address_name(buf: &[u8]) -> String {
+    match buf.len() {
+        4 => {
+            let mut octets = [0; 4];
+            octets.copy_from_slice(buf);
+            core::net::Ipv4Addr::from(octets).to_string()
+        }
+        16 => {
+            let mut octets = [0; 16];
+            octets.copy_from_slice(buf);
+            core::net::Ipv6Addr::from(octets).to_string()
+        }
+        _ => String::new(),
+    }
+}
+
+// Check that the chain of certificates sent by a server for the given host
+// leads to one of the trusted certificates.
+pub fn verify_chain(
+    chain: &[Certificate],
+    trusted: &[Certificate],
+    host: &str,
+    time: &str
+) -> bool {
+    match chain.first() {
+        Some(leaf) if leaf.matches_host(host) => {}
+        _ => return false,
+    }
+    for (i, cert) in chain.iter().enumerate() {
+        if !cert.is_valid_at(time) {
+            return false;
+        }
+        if trusted.iter().any(|root| root.der == cert.der) {
+            return true;
+        }
+        let is_trusted = trusted.iter().any(|root| {
+            root.is_ca && root.is_valid_at(time) && cert.is_signed_by(root)
+        });
+        if is_trusted {
+            return true;
+        }
+        match chain.get(i + 1) {
+            Some(next) if next.is_ca && cert.is_signed_by(next) => {}
+            _ => return false,
+        }
+    }
+    false
+}
+
+#[test_case]
+fn test_certificates() {
+    let ca = "\
+        -----BEGIN CERTIFICATE-----\n\
+        MIICDjCCAXegAwIBAgIUK1wkIOND51D6m6t2MHLCfQX2DDMwDQYJKoZIhvcNAQEL\n\
+        BQAwGDEWMBQGA1UEAwwNTU9ST1MgVGVzdCBDQTAgFw0yNjEwMTgyMjM5NTBaGA8y\n\
+        MTI2MDkyNDIyMzk1MFowGDEWMBQGA1UEAwwNTU9ST1MgVGVzdCBDQTCBnzANBgkq\n\
+        hkiG9w0BAQEFAAOBjQAwgYkCgYEArad1a8kXLqFQcKDj1XVJnwU+I6jaP1iXmEXx\n\
+        xRr2ZrYuAT4b/8Rr3/RduYcWE/lKk+8XwEP1V4Vn6GGQhnCs8YMs1do69sP3tN9i\n\
+        HJfvGpBFCwjjBZJDxkXZyb3inWNdYxEjSQMzJu5yGlv4zs2BsL1EwbpVwI8atZ6B\n\
+        Sn96G6MCAwEAAaNTMFEwHQYDVR0OBBYEFFwz7Ns5Lb3YYZG1hUIA8oMJ7qp4MB8G\n\
+        A1UdIwQYMBaAFFwz7Ns5Lb3YYZG1hUIA8oMJ7qp4MA8GA1UdEwEB/wQFMAMBAf8w\n\
+        DQYJKoZIhvcNAQELBQADgYEAlxHu/M/sbNc9ZDoYLZifbN1F8noYkgEklts1kL0u\n\
+        EPPvMLW63ckiBVHI05GyIIzDDHNXX/q41mi03/Vvv4sFgeNi3K/1lW3HVoIiy4ht\n\
+        MCoqjK6BG9ILcZkEdkQgkPCNDVkHsyVegxX0ULtBYgfvxh7Peso1Uz1LJG5aL5H8\n\
+        4Vs=\n\
+        -----END CERTIFICATE-----\n\
+    ";
+    let leaf = "\
+        -----BEGIN CERTIFICATE-----\n\
+        MIICKjCCAZOgAwIBAgIUUIml68gb9kGY/thAYI71KjxpsMQwDQYJKoZIhvcNAQEL\n\
+        BQAwGDEWMBQGA1UEAwwNTU9ST1MgVGVzdCBDQTAgFw0yNjEwMTgyMjM5NTBaGA8y\n\
+        MTI2MDkyNDIyMzk1MFowFTETMBEGA1UEAwwKbW9yb3MudGVzdDCBnzANBgkqhkiG\n\
+        9w0BAQEFAAOBjQAwgYkCgYEA79eUV9ciqIEY2l9Y6zdUC/yNqY5//AnyifuAF7Tj\n\
+        ejf/yrjWB4T8kKuDXYmQ2mGiI0qgN8JXTJXtgHNNZrElP1zV6w++Dgsd1br+YWgS\n\
+        nRWmrFye8bn20MqyzXzMTzcwwBo3qvP6o8k2iPRrkk5iN+6GlXQdxeolWQH2g/gi\n\
+        RzMCAwEAAaNyMHAwIwYDVR0RBBwwGoIKbW9yb3MudGVzdIIMKi5tb3Jvcy50ZXN0\n\
+        MAkGA1UdEwQCMAAwHQYDVR0OBBYEFIsQji2FgtF14OhbmKVEx6LZmco5MB8GA1Ud\n\
+        IwQYMBaAFFwz7Ns5Lb3YYZG1hUIA8oMJ7qp4MA0GCSqGSIb3DQEBCwUAA4GBAFaR\n\
+        bBZASpWGSxA2uRUOlL480Myn84ySRwu/f32LT4KMILTMa5Kv3M5nrXNEPIrurGxS\n\
+        BloCp3QkcgDZxjDBQwrURRkOi4kuTl0mS9leQ0IhomKcuMwphyePhk1XT/ey3R3M\n\
+        zcTUMQoHJg4hyVlSmTwy4SvA/pu0z3Fg0TGN/241\n\
+        -----END CERTIFICATE-----\n\
+    ";
+
+    let pem = [ca, leaf].concat();
+    let certs = Certificate::parse_pem(&pem);
+    assert_eq!(certs.len(), 2);
+    let (ca, leaf) = (&certs[0], &certs[1]);
+
+    assert!(ca.is_ca);
+    assert_eq!(ca.names, ["moros test ca"]);
+    assert_eq!(ca.not_before, "20261018223950");
+    assert_eq!(ca.not_after, "21260924223950");
+    assert!(ca.is_signed_by(ca));
+
+    assert!(!leaf.is_ca);
+    assert_eq!(leaf.names, ["moros.test", "*.moros.test"]);
+    assert!(leaf.is_signed_by(ca));
+    assert!(!ca.is_signed_by(leaf));
+    assert!(leaf.matches_host("moros.test"));
+    assert!(leaf.matches_host("www.moros.test"));
+    assert!(!leaf.matches_host("a.www.moros.test"));
+    assert!(!leaf.matches_host("moros.cc"));
+
+    let now = "20300101000000";
+    let chain = [leaf.clone()];
+    let trusted = [ca.clone()];
+    assert!(verify_chain(&chain, &trusted, "moros.test", now));
+    assert!(verify_chain(&chain, &chain, "moros.test", now));
+    assert!(!verify_chain(&chain, &[], "moros.test", now));
+    assert!(!verify_chain(&chain, &trusted, "moros.cc", now));
+    assert!(!verify_chain(&chain, &trusted, "moros.test", "20200101000000"));
+}
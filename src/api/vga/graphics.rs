@@ -0,0 +1,242 @@
+use crate::api::font::Font;
+use crate::api::fs;
+use crate::sys;
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+pub const WIDTH: usize = 320;
+pub const HEIGHT: usize = 200;
+
+// A canvas is an offscreen buffer of pixels that is drawn with primitives
+// and copied to the screen all at once to avoid flickering.
+#[derive(Debug, Clone)]
+pub struct Canvas {
+    pub width: usize,
+    pub height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    pub fn new(width: usize, height: usize) -> Self {
+        let pixels = vec![0; width * height];
+        Self { width, height, pixels }
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn clear(&mut self, color: u8) {
+        self.pixels.fill(color);
+    }
+
+    pub fn pixel(&self, x: i64, y: i64) -> Option<u8> {
+        self.index(x, y).map(|i| self.pixels[i])
+    }
+
+    // Pixels outside of the canvas are ignored
+    pub fn set_pixel(&mut self, x: i64, y: i64, color: u8) {
+        if let Some(i) = self.index(x, y) {
+            self.pixels[i] = color;
+        }
+    }
+
+    // Source: https://en.wikipedia.org/wiki/Bresenham%27s_line_algorithm
+    pub fn line(&mut self, x0: i64, y0: i64, x1: i64, y1: i64, color: u8) {
+        let dx = (x1 - x0).abs();
+        let dy = -(y1 - y0).abs();
+        let sx = if x0 < x1 { 1 } else { -1 };
+        let sy = if y0 < y1 { 1 } else { -1 };
+        let mut err = dx + dy;
+        let (mut x, mut y) = (x0, y0);
+        loop {
+            self.set_pixel(x, y, color);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    pub fn rect(&mut self, x: i64, y: i64, w: i64, h: i64, color: u8) {
+        if w <= 0 || h <= 0 {
+            return;
+        }
+        let (x1, y1) = (x + w - 1, y + h - 1);
+        self.line(x, y, x1, y, color);
+        self.line(x, y1, x1, y1, color);
+        self.line(x, y, x, y1, color);
+        self.line(x1, y, x1, y1, color);
+    }
+
+    pub fn fill_rect(&mut self, x: i64, y: i64, w: i64, h: i64, color: u8) {
+        let x0 = x.max(0);
+        let x1 = (x + w).min(self.width as i64);
+        if x0 >= x1 {
+            return;
+        }
+        for y in y.max(0)..(y + h).min(self.height as i64) {
+            let i = self.width * y as usize;
+            self.pixels[i + x0 as usize..i + x1 as usize].fill(color);
+        }
+    }
+
+    // Source: https://en.wikipedia.org/wiki/Midpoint_circle_algorithm
+    pub fn circle(&mut self, cx: i64, cy: i64, r: i64, color: u8) {
+        let (mut x, mut y) = (r, 0);
+        let mut err = 1 - r;
+        while x >= y {
+            for (dx, dy) in [(x, y), (y, x)] {
+                self.set_pixel(cx + dx, cy + dy, color);
+                self.set_pixel(cx - dx, cy + dy, color);
+                self.set_pixel(cx + dx, cy - dy, color);
+                self.set_pixel(cx - dx, cy - dy, color);
+            }
+            y += 1;
+            if err < 0 {
+                err += 2 * y + 1;
+            } else {
+                x -= 1;
+                err += 2 * (y - x) + 1;
+            }
+        }
+    }
+
+    pub fn fill_circle(&mut self, cx: i64, cy: i64, r: i64, color: u8) {
+        for dy in -r..=r {
+            let dx = libm::sqrt((r * r - dy * dy) as f64) as i64;
+            self.fill_rect(cx - dx, cy + dy, 2 * dx + 1, 1, color);
+        }
+    }
+
+    // Copy an image of `w` pixels wide at the given position
+    pub fn blit(&mut self, x: i64, y: i64, w: usize, image: &[u8]) {
+        if w == 0 {
+            return;
+        }
+        for (dy, row) in image.chunks(w).enumerate() {
+            for (dx, color) in row.iter().enumerate() {
+                self.set_pixel(x + dx as i64, y + dy as i64, *color);
+            }
+        }
+    }
+
+    // Draw the text with the glyphs of a PSF font that are 8 pixels wide
+    pub fn text(&mut self, x: i64, y: i64, s: &str, font: &Font, color: u8) {
+        let h = font.height as usize;
+        for (i, c) in s.chars().enumerate() {
+            let code = font.glyph(c).unwrap_or(0) as usize;
+            let glyph = &font.data[code * h..(code + 1) * h];
+            let gx = x + 8 * i as i64;
+            for (dy, bits) in glyph.iter().enumerate() {
+                for dx in 0..8 {
+                    if bits & (0x80 >> dx) != 0 {
+                        self.set_pixel(gx + dx, y + dy as i64, color);
+                    }
+                }
+            }
+        }
+    }
+
+    // Copy the canvas to the top left corner of the screen in graphic mode.
+    //
+    // The color indexes of the pixels are written as they are in the 320x200
+    // mode, and converted to colors with the current palette in the high
+    // resolution modes of VBE. The 640x480 mode with 16 colors is not
+    // supported because its memory is organized in planes.
+    pub fn present(&self) -> bool {
+        let dev = "/dev/vga/buffer";
+        if !fs::is_device(dev) {
+            return false;
+        }
+        let (width, height) = match screen_size() {
+            Some(size) => size,
+            None => return false,
+        };
+        if !sys::vga::is_vbe_enabled() {
+            if (width, height) != (320, 200) {
+                return false; // Text mode or planar 640x480 mode
+            }
+            return fs::write(dev, &self.pixels).is_ok();
+        }
+        let palette = match fs::read_to_bytes("/dev/vga/palette") {
+            Ok(palette) if palette.len() == 256 * 3 => palette,
+            _ => return false,
+        };
+        let rows = self.height.min(height);
+        let cols = self.width.min(width);
+        let mut buf = vec![0; width * rows * 4]; // BGRX
+        for y in 0..rows {
+            for x in 0..cols {
+                let i = self.pixels[y * self.width + x] as usize * 3;
+                let j = (y * width + x) * 4;
+                buf[j] = palette[i + 2];
+                buf[j + 1] = palette[i + 1];
+                buf[j + 2] = palette[i];
+            }
+        }
+        fs::write(dev, &buf).is_ok()
+    }
+
+    fn index(&self, x: i64, y: i64) -> Option<usize> {
+        let (w, h) = (self.width as i64, self.height as i64);
+        if 0 <= x && x < w && 0 <= y && y < h {
+            Some((y * w + x) as usize)
+        } else {
+            None
+        }
+    }
+}
+
+// Return the size of the screen in pixels from the current VGA mode
+fn screen_size() -> Option<(usize, usize)> {
+    let buf = fs::read_to_bytes("/dev/vga/mode").ok()?;
+    let mode = core::str::from_utf8(&buf).ok()?;
+    let (width, height) = mode.trim().split_once('x')?;
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
+
+#[test_case]
+fn test_canvas() {
+    use core::convert::TryFrom;
+
+    let mut canvas = Canvas::new(16, 16);
+    canvas.set_pixel(-1, 20, 1); // Ignored
+    assert_eq!(canvas.pixel(-1, 20), None);
+
+    canvas.line(0, 0, 15, 15, 1);
+    assert_eq!(canvas.pixel(7, 7), Some(1));
+    assert_eq!(canvas.pixels().iter().filter(|&&c| c == 1).count(), 16);
+
+    canvas.clear(0);
+    canvas.rect(2, 2, 4, 3, 2);
+    assert_eq!(canvas.pixels().iter().filter(|&&c| c == 2).count(), 10);
+    canvas.fill_rect(-4, -4, 8, 8, 3);
+    assert_eq!(canvas.pixels().iter().filter(|&&c| c == 3).count(), 16);
+
+    canvas.clear(0);
+    canvas.circle(8, 8, 4, 4);
+    assert_eq!(canvas.pixel(12, 8), Some(4));
+    assert_eq!(canvas.pixel(8, 4), Some(4));
+    assert_eq!(canvas.pixel(8, 8), Some(0));
+    canvas.fill_circle(8, 8, 4, 5);
+    assert_eq!(canvas.pixel(8, 8), Some(5));
+
+    canvas.blit(14, 14, 3, &[6, 6, 6, 6, 6, 6]);
+    assert_eq!(canvas.pixels().iter().filter(|&&c| c == 6).count(), 4);
+
+    let buf = include_bytes!("../../../dsk/ini/fonts/zap-light-8x16.psf");
+    let font = Font::try_from(&buf[..]).unwrap();
+    canvas.clear(0);
+    canvas.text(0, 0, "|", &font, 7);
+    assert!(canvas.pixels().contains(&7));
+}
@@ -1,3 +1,5 @@
+pub mod graphics;
+
 use crate::api::fs;
 
 pub fn graphic_mode() {
@@ -30,6 +30,7 @@ pub fn init(boot_info: &'static BootInfo) {
     sys::pic::init(); // Enable interrupts
     sys::serial::init();
     sys::keyboard::init();
+    sys::mouse::init();
     sys::clk::init();
 
     let v = option_env!("MOROS_VERSION").unwrap_or(env!("CARGO_PKG_VERSION"));
@@ -40,6 +41,7 @@ pub fn init(boot_info: &'static BootInfo) {
     sys::acpi::init(); // Require MEM
     sys::rng::init();
     sys::pci::init(); // Require MEM
+    sys::vga::init_vbe(); // Require PCI
     sys::net::init(); // Require PCI
     sys::ata::init();
     sys::fs::init(); // Require ATA
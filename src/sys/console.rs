@@ -1,15 +1,28 @@
 use crate::api::fs::{FileIO, IO};
 use crate::sys;
+use crate::sys::fs::{Device, Resource};
+use alloc::format;
 use alloc::string::String;
 use alloc::string::ToString;
 use core::fmt;
-use core::sync::atomic::{AtomicBool, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use spin::Mutex;
 use x86_64::instructions::interrupts;
 
-pub static STDIN: Mutex<String> = Mutex::new(String::new());
-pub static ECHO: AtomicBool = AtomicBool::new(true);
-pub static RAW: AtomicBool = AtomicBool::new(false);
+// Number of virtual consoles switched with Alt+F1 to Alt+F4
+pub const MAX_CONSOLES: usize = 4;
+
+pub static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+pub static STDIN: [Mutex<String>; MAX_CONSOLES] =
+    [const { Mutex::new(String::new()) }; MAX_CONSOLES];
+pub static ECHO: [AtomicBool; MAX_CONSOLES] =
+    [const { AtomicBool::new(true) }; MAX_CONSOLES];
+pub static RAW: [AtomicBool; MAX_CONSOLES] =
+    [const { AtomicBool::new(false) }; MAX_CONSOLES];
+pub static MOUSE: [AtomicBool; MAX_CONSOLES] =
+    [const { AtomicBool::new(false) }; MAX_CONSOLES];
+pub static MOUSE_SGR: [AtomicBool; MAX_CONSOLES] =
+    [const { AtomicBool::new(false) }; MAX_CONSOLES];
 
 pub const BS_KEY: char = '\x08'; // Backspace
 pub const EOT_KEY: char = '\x04'; // End of Transmission
@@ -17,11 +30,24 @@ pub const ESC_KEY: char = '\x1B'; // Escape
 pub const ETX_KEY: char = '\x03'; // End of Text
 
 #[derive(Debug, Clone)]
-pub struct Console;
+pub struct Console {
+    vt: usize,
+}
 
 impl Console {
-    pub fn new() -> Self {
-        Self {}
+    pub fn new(vt: usize) -> Self {
+        debug_assert!(vt < MAX_CONSOLES);
+        Self { vt }
+    }
+
+    // Open the virtual console of the current process when no number is
+    // given, like with `/dev/console`, or the given one like `/dev/vt/2`.
+    pub fn open(n: u8) -> Option<Self> {
+        match n as usize {
+            0 => Some(Self::new(current())),
+            n if n <= MAX_CONSOLES => Some(Self::new(n - 1)),
+            _ => None,
+        }
     }
 
     pub fn size() -> usize {
@@ -32,9 +58,9 @@ impl Console {
 impl FileIO for Console {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize, ()> {
         let mut s = if buf.len() == 4 {
-            read_char().to_string()
+            read_char(self.vt).to_string()
         } else {
-            read_line()
+            read_line(self.vt)
         };
         s.truncate(buf.len());
         let n = s.len();
@@ -45,7 +71,7 @@ impl FileIO for Console {
     fn write(&mut self, buf: &[u8]) -> Result<usize, ()> {
         let s = String::from_utf8_lossy(buf);
         let n = s.len();
-        print_fmt(format_args!("{}", s));
+        write_fmt(self.vt, format_args!("{}", s));
         Ok(n)
     }
 
@@ -53,58 +79,145 @@ impl FileIO for Console {
 
     fn poll(&mut self, event: IO) -> bool {
         match event {
-            IO::Read => STDIN.lock().contains('\n'),
+            IO::Read => STDIN[self.vt].lock().contains('\n'),
             IO::Write => true,
         }
     }
 }
 
+// Return the virtual console displayed on the screen
+pub fn active() -> usize {
+    ACTIVE.load(Ordering::SeqCst)
+}
+
+// Return the virtual console of the standard input of the current process
+pub fn current() -> usize {
+    match sys::process::handle(0).map(|file| *file) {
+        Some(Resource::Device(Device::Console(console))) => console.vt,
+        _ => active(),
+    }
+}
+
+// Scroll the active console into its history until the next output
+pub fn scroll_up() {
+    if cfg!(feature = "video") {
+        sys::vga::scroll_up(active());
+    }
+}
+
+pub fn scroll_down() {
+    if cfg!(feature = "video") {
+        sys::vga::scroll_down(active());
+    }
+}
+
+pub fn switch(vt: usize) {
+    if vt < MAX_CONSOLES && vt != active() {
+        ACTIVE.store(vt, Ordering::SeqCst);
+        if cfg!(feature = "video") {
+            sys::vga::switch(vt);
+        }
+    }
+}
+
 pub fn has_cursor() -> bool {
     cfg!(feature = "video")
 }
 
-pub fn disable_echo() {
-    ECHO.store(false, Ordering::SeqCst);
+pub fn disable_echo(vt: usize) {
+    ECHO[vt].store(false, Ordering::SeqCst);
+}
+
+pub fn enable_echo(vt: usize) {
+    ECHO[vt].store(true, Ordering::SeqCst);
 }
 
-pub fn enable_echo() {
-    ECHO.store(true, Ordering::SeqCst);
+pub fn is_echo_enabled(vt: usize) -> bool {
+    ECHO[vt].load(Ordering::SeqCst)
 }
 
-pub fn is_echo_enabled() -> bool {
-    ECHO.load(Ordering::SeqCst)
+pub fn disable_raw(vt: usize) {
+    RAW[vt].store(false, Ordering::SeqCst);
 }
 
-pub fn disable_raw() {
-    RAW.store(false, Ordering::SeqCst);
+pub fn enable_raw(vt: usize) {
+    RAW[vt].store(true, Ordering::SeqCst);
 }
 
-pub fn enable_raw() {
-    RAW.store(true, Ordering::SeqCst);
+pub fn is_raw_enabled(vt: usize) -> bool {
+    RAW[vt].load(Ordering::SeqCst)
 }
 
-pub fn is_raw_enabled() -> bool {
-    RAW.load(Ordering::SeqCst)
+pub fn disable_mouse(vt: usize) {
+    MOUSE[vt].store(false, Ordering::SeqCst);
 }
 
+pub fn enable_mouse(vt: usize) {
+    MOUSE[vt].store(true, Ordering::SeqCst);
+}
+
+pub fn is_mouse_enabled(vt: usize) -> bool {
+    MOUSE[vt].load(Ordering::SeqCst)
+}
+
+pub fn disable_mouse_sgr(vt: usize) {
+    MOUSE_SGR[vt].store(false, Ordering::SeqCst);
+}
+
+pub fn enable_mouse_sgr(vt: usize) {
+    MOUSE_SGR[vt].store(true, Ordering::SeqCst);
+}
+
+pub fn is_mouse_sgr_enabled(vt: usize) -> bool {
+    MOUSE_SGR[vt].load(Ordering::SeqCst)
+}
+
+// Draw the pointer of the mouse on the active console
+pub fn move_pointer(x: usize, y: usize) {
+    if cfg!(feature = "video") {
+        sys::vga::set_pointer(active(), x, y);
+    }
+}
+
+// Add the xterm escape sequence of a mouse button press or release to the
+// input queue of the active console when mouse reporting is enabled
+pub fn mouse_handle(button: u8, x: usize, y: usize, is_pressed: bool) {
+    let vt = active();
+    if !is_mouse_enabled(vt) {
+        return;
+    }
+    let seq = mouse_event(button, x, y, is_pressed, is_mouse_sgr_enabled(vt));
+    STDIN[vt].lock().push_str(&seq);
+}
+
+fn mouse_event(b: u8, x: usize, y: usize, pressed: bool, sgr: bool) -> String {
+    if sgr {
+        let c = if pressed { 'M' } else { 'm' };
+        format!("\x1b[<{};{};{}{}", b, x + 1, y + 1, c)
+    } else {
+        let b = if pressed { b } else { 3 }; // Release
+        let b = (32 + b) as char;
+        let x = (33 + x as u8) as char;
+        let y = (33 + y as u8) as char;
+        format!("\x1b[M{}{}{}", b, x, y)
+    }
+}
+
+// Add a key to the input queue of the active console
 pub fn key_handle(key: char) {
-    let mut stdin = STDIN.lock();
+    let vt = active();
+    let mut stdin = STDIN[vt].lock();
 
-    if key == BS_KEY && !is_raw_enabled() {
+    if key == BS_KEY && !is_raw_enabled(vt) {
         // Avoid printing more backspaces than chars inserted into STDIN
         if let Some(c) = stdin.pop() {
-            if is_echo_enabled() {
+            if is_echo_enabled(vt) {
                 let n = match c {
                     ETX_KEY | EOT_KEY | ESC_KEY => 2,
-                    _ => {
-                        if (c as u32) < 0xFF {
-                            1
-                        } else {
-                            c.len_utf8()
-                        }
-                    }
+                    _ => 1, // Each char is displayed with a single glyph
                 };
-                print_fmt(format_args!("{}", BS_KEY.to_string().repeat(n)));
+                let bs = BS_KEY.to_string().repeat(n);
+                write_fmt(vt, format_args!("{}", bs));
             }
         }
     } else {
@@ -114,36 +227,52 @@ pub fn key_handle(key: char) {
             key
         };
         stdin.push(key);
-        if is_echo_enabled() {
+        if is_echo_enabled(vt) {
             match key {
-                ETX_KEY => print_fmt(format_args!("^C")),
-                EOT_KEY => print_fmt(format_args!("^D")),
-                ESC_KEY => print_fmt(format_args!("^[")),
-                _ => print_fmt(format_args!("{}", key)),
+                ETX_KEY => write_fmt(vt, format_args!("^C")),
+                EOT_KEY => write_fmt(vt, format_args!("^D")),
+                ESC_KEY => write_fmt(vt, format_args!("^[")),
+                _ => write_fmt(vt, format_args!("{}", key)),
             };
         }
     }
 }
 
 pub fn end_of_text() -> bool {
-    interrupts::without_interrupts(|| STDIN.lock().contains(ETX_KEY))
+    let vt = current();
+    interrupts::without_interrupts(|| STDIN[vt].lock().contains(ETX_KEY))
 }
 
 pub fn end_of_transmission() -> bool {
-    interrupts::without_interrupts(|| STDIN.lock().contains(EOT_KEY))
+    let vt = current();
+    interrupts::without_interrupts(|| STDIN[vt].lock().contains(EOT_KEY))
 }
 
 pub fn drain() {
-    interrupts::without_interrupts(|| STDIN.lock().clear())
+    let vt = current();
+    interrupts::without_interrupts(|| STDIN[vt].lock().clear())
 }
 
-pub fn read_char() -> char {
-    sys::console::disable_echo();
-    sys::console::enable_raw();
+// Return the next char of the input of the current process without waiting
+pub fn try_read_char() -> Option<char> {
+    let vt = current();
+    interrupts::without_interrupts(|| {
+        let mut stdin = STDIN[vt].lock();
+        if !stdin.is_empty() {
+            Some(stdin.remove(0))
+        } else {
+            None
+        }
+    })
+}
+
+fn read_char(vt: usize) -> char {
+    disable_echo(vt);
+    enable_raw(vt);
     loop {
         sys::clk::halt();
         let res = interrupts::without_interrupts(|| {
-            let mut stdin = STDIN.lock();
+            let mut stdin = STDIN[vt].lock();
             if !stdin.is_empty() {
                 Some(stdin.remove(0))
             } else {
@@ -151,18 +280,18 @@ pub fn read_char() -> char {
             }
         });
         if let Some(c) = res {
-            sys::console::enable_echo();
-            sys::console::disable_raw();
+            enable_echo(vt);
+            disable_raw(vt);
             return c;
         }
     }
 }
 
-pub fn read_line() -> String {
+fn read_line(vt: usize) -> String {
     loop {
         sys::clk::halt();
         let res = interrupts::without_interrupts(|| {
-            let mut stdin = STDIN.lock();
+            let mut stdin = STDIN[vt].lock();
             match stdin.chars().next_back() {
                 Some('\n') => {
                     let line = stdin.clone();
@@ -178,11 +307,24 @@ pub fn read_line() -> String {
     }
 }
 
+// Print the messages of the kernel on the active console
 #[doc(hidden)]
 pub fn print_fmt(args: fmt::Arguments) {
+    write_fmt(active(), args);
+}
+
+pub fn write_fmt(vt: usize, args: fmt::Arguments) {
     if cfg!(feature = "video") {
-        sys::vga::print_fmt(args);
+        sys::vga::print_fmt(vt, args);
     } else {
         sys::serial::print_fmt(args);
     }
 }
+
+#[test_case]
+fn test_mouse_event() {
+    assert_eq!(mouse_event(0, 9, 4, true, false), "\x1b[M *%");
+    assert_eq!(mouse_event(0, 9, 4, false, false), "\x1b[M#*%");
+    assert_eq!(mouse_event(2, 9, 4, true, true), "\x1b[<2;10;5M");
+    assert_eq!(mouse_event(2, 9, 4, false, true), "\x1b[<2;10;5m");
+}
@@ -6,11 +6,13 @@ use super::{dirname, filename, realpath, FileIO, IO};
 use crate::sys::ata::Drive;
 use crate::sys::clk::{RTC, EpochTime, BootTime};
 use crate::sys::console::Console;
+use crate::sys::mouse::Mouse;
 use crate::sys::net::gw::NetGw;
 use crate::sys::net::ip::NetIp;
 use crate::sys::net::mac::NetMac;
 use crate::sys::net::usage::NetUsage;
 use crate::sys::net::socket::tcp::TcpSocket;
+use crate::sys::net::socket::telnet::TelnetSocket;
 use crate::sys::net::socket::udp::UdpSocket;
 use crate::sys::rng::Random;
 use crate::sys::speaker::Speaker;
@@ -43,6 +45,7 @@ pub enum DeviceType {
     NetIp      = 16,
     NetMac     = 17,
     NetUsage   = 18,
+    Mouse      = 19,
 }
 
 impl TryFrom<&[u8]> for DeviceType {
@@ -69,6 +72,7 @@ impl TryFrom<&[u8]> for DeviceType {
             16 => Ok(DeviceType::NetIp),
             17 => Ok(DeviceType::NetMac),
             18 => Ok(DeviceType::NetUsage),
+            19 => Ok(DeviceType::Mouse),
              _ => Err(()),
         }
     }
@@ -94,6 +98,7 @@ impl DeviceType {
             DeviceType::NetIp      => NetIp::size(),
             DeviceType::NetMac     => NetMac::size(),
             DeviceType::NetUsage   => NetUsage::size(),
+            DeviceType::Mouse      => Mouse::size(),
             _                      => 1,
         };
         let mut res = vec![0; len];
@@ -123,6 +128,8 @@ pub enum Device {
     NetIp(NetIp),
     NetMac(NetMac),
     NetUsage(NetUsage),
+    Mouse(Mouse),
+    TelnetSocket(TelnetSocket),
 }
 
 impl TryFrom<&[u8]> for Device {
@@ -132,7 +139,10 @@ impl TryFrom<&[u8]> for Device {
         match buf.try_into()? {
             DeviceType::Null       => Ok(Device::Null),
             DeviceType::File       => Ok(Device::File(File::new())),
-            DeviceType::Console    => Ok(Device::Console(Console::new())),
+            DeviceType::Console    => {
+                let n = buf.get(1).copied().unwrap_or(0);
+                Console::open(n).map(Device::Console).ok_or(())
+            }
             DeviceType::Random     => Ok(Device::Random(Random::new())),
             DeviceType::BootTime   => Ok(Device::BootTime(BootTime::new())),
             DeviceType::EpochTime  => Ok(Device::EpochTime(EpochTime::new())),
@@ -148,6 +158,7 @@ impl TryFrom<&[u8]> for Device {
             DeviceType::NetIp      => Ok(Device::NetIp(NetIp::new())),
             DeviceType::NetMac     => Ok(Device::NetMac(NetMac::new())),
             DeviceType::NetUsage   => Ok(Device::NetUsage(NetUsage::new())),
+            DeviceType::Mouse      => Ok(Device::Mouse(Mouse::new())),
             DeviceType::Drive if buf.len() > 2 => {
                 let bus = buf[1];
                 let dsk = buf[2];
@@ -216,6 +227,8 @@ impl FileIO for Device {
             Device::NetIp(io)      => io.read(buf),
             Device::NetMac(io)     => io.read(buf),
             Device::NetUsage(io)   => io.read(buf),
+            Device::Mouse(io)      => io.read(buf),
+            Device::TelnetSocket(io) => io.read(buf),
         }
     }
 
@@ -240,6 +253,8 @@ impl FileIO for Device {
             Device::NetIp(io)      => io.write(buf),
             Device::NetMac(io)     => io.write(buf),
             Device::NetUsage(io)   => io.write(buf),
+            Device::Mouse(io)      => io.write(buf),
+            Device::TelnetSocket(io) => io.write(buf),
         }
     }
 
@@ -264,6 +279,8 @@ impl FileIO for Device {
             Device::NetIp(io)      => io.close(),
             Device::NetMac(io)     => io.close(),
             Device::NetUsage(io)   => io.close(),
+            Device::Mouse(io)      => io.close(),
+            Device::TelnetSocket(io) => io.close(),
         }
     }
 
@@ -288,6 +305,8 @@ impl FileIO for Device {
             Device::NetIp(io)      => io.poll(event),
             Device::NetMac(io)     => io.poll(event),
             Device::NetUsage(io)   => io.poll(event),
+            Device::Mouse(io)      => io.poll(event),
+            Device::TelnetSocket(io) => io.poll(event),
         }
     }
 }
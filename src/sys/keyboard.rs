@@ -1,20 +1,34 @@
 use crate::api;
 use crate::sys;
 
+use alloc::format;
+use alloc::string::String;
+use core::str::FromStr;
 use core::sync::atomic::{AtomicBool, Ordering};
 use pc_keyboard::{
     layouts, DecodedKey, Error, HandleControl, KeyCode, KeyEvent, KeyState,
     Keyboard, ScancodeSet1,
 };
 use spin::Mutex;
+use x86_64::instructions::interrupts;
 use x86_64::instructions::port::Port;
 
 pub static KEYBOARD: Mutex<Option<KeyboardLayout>> = Mutex::new(None);
+pub static KEYMAP: Mutex<Option<Keymap>> = Mutex::new(None);
 
 pub static ALT: AtomicBool = AtomicBool::new(false);
+pub static ALT_GR: AtomicBool = AtomicBool::new(false);
+pub static CAPS_LOCK: AtomicBool = AtomicBool::new(false);
 pub static CTRL: AtomicBool = AtomicBool::new(false);
 pub static SHIFT: AtomicBool = AtomicBool::new(false);
 
+// Set when the previous scancode was the prefix of an extended key
+static EXTENDED: AtomicBool = AtomicBool::new(false);
+
+const NORMAL: usize = 0;
+const SHIFTED: usize = 1;
+const ALTGR: usize = 2;
+
 pub enum KeyboardLayout {
     Azerty(Keyboard<layouts::Azerty, ScancodeSet1>),
     Dvorak(Keyboard<layouts::Dvorak104Key, ScancodeSet1>),
@@ -54,15 +68,113 @@ impl KeyboardLayout {
     }
 }
 
+// A keymap gives the chars of the keys of scancode set 1 for the normal,
+// shift and altgr layers, and the other keys are decoded with the qwerty
+// layout. It is parsed from a file like the following:
+//
+//     # scancode normal shift altgr
+//     0x03 2 " ²
+//     0x15 z Z
+//     0x2C y Y
+//
+// The keys without a shift or an altgr char will use the normal char.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keymap {
+    keys: [[Option<char>; 3]; 0x80],
+}
+
+impl Keymap {
+    fn map(&self, scancode: u8, layer: usize) -> Option<char> {
+        let key = self.keys.get(scancode as usize)?;
+        key[layer].or(key[NORMAL])
+    }
+
+    fn is_letter(&self, scancode: u8) -> bool {
+        self.map(scancode, NORMAL).is_some_and(|c| c.is_alphabetic())
+    }
+}
+
+impl FromStr for Keymap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut keys = [[None; 3]; 0x80];
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            let code = words.next().unwrap_or("");
+            let scancode = parse_scancode(code).ok_or(
+                format!("Could not parse scancode on line {}", i + 1)
+            )?;
+            let key = &mut keys[scancode];
+            for (layer, word) in words.enumerate() {
+                let mut chars = word.chars();
+                match (layer, chars.next(), chars.next()) {
+                    (0..=2, Some(c), None) => key[layer] = Some(c),
+                    _ => return Err(
+                        format!("Could not parse key on line {}", i + 1)
+                    ),
+                }
+            }
+        }
+        Ok(Self { keys })
+    }
+}
+
+fn parse_scancode(s: &str) -> Option<usize> {
+    let code = match s.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16).ok()?,
+        None => s.parse().ok()?,
+    };
+    if code < 0x80 {
+        Some(code)
+    } else {
+        None
+    }
+}
+
 pub fn set_keyboard(layout: &str) -> bool {
     if let Some(keyboard) = KeyboardLayout::from(layout) {
-        *KEYBOARD.lock() = Some(keyboard);
+        interrupts::without_interrupts(|| {
+            *KEYBOARD.lock() = Some(keyboard);
+            *KEYMAP.lock() = None;
+        });
         true
     } else {
         false
     }
 }
 
+pub fn set_keymap(keymap: Keymap) {
+    let keyboard = KeyboardLayout::from("qwerty");
+    interrupts::without_interrupts(|| {
+        *KEYBOARD.lock() = keyboard;
+        *KEYMAP.lock() = Some(keymap);
+    });
+}
+
+// Return the char given by the keymap for the key of a scancode
+fn keymap_char(scancode: u8) -> Option<char> {
+    let ord = Ordering::Relaxed;
+    let keymap = KEYMAP.lock();
+    let keymap = keymap.as_ref()?;
+    let mut is_shift = SHIFT.load(ord);
+    if CAPS_LOCK.load(ord) && keymap.is_letter(scancode) {
+        is_shift = !is_shift;
+    }
+    let layer = if ALT_GR.load(ord) {
+        ALTGR
+    } else if is_shift {
+        SHIFTED
+    } else {
+        NORMAL
+    };
+    keymap.map(scancode, layer)
+}
+
 pub fn init() {
     set_keyboard(option_env!("MOROS_KEYBOARD").unwrap_or("qwerty"));
     sys::idt::set_irq_handler(1, interrupt_handler);
@@ -88,30 +200,77 @@ fn send_csi(code: &str) {
 fn interrupt_handler() {
     if let Some(ref mut keyboard) = *KEYBOARD.lock() {
         let scancode = read_scancode();
+        let ord = Ordering::Relaxed;
+        let is_extended = EXTENDED.swap(scancode == 0xE0, ord);
         if let Ok(Some(event)) = keyboard.add_byte(scancode) {
-            let ord = Ordering::Relaxed;
+            let is_down = event.state == KeyState::Down;
             match event.code {
-                KeyCode::AltLeft | KeyCode::AltRight => {
-                    ALT.store(event.state == KeyState::Down, ord)
+                KeyCode::AltLeft => {
+                    ALT.store(is_down, ord)
+                }
+                KeyCode::AltRight => {
+                    ALT.store(is_down, ord);
+                    ALT_GR.store(is_down, ord)
                 }
                 KeyCode::ShiftLeft | KeyCode::ShiftRight => {
-                    SHIFT.store(event.state == KeyState::Down, ord)
+                    SHIFT.store(is_down, ord)
                 }
                 KeyCode::ControlLeft | KeyCode::ControlRight => {
-                    CTRL.store(event.state == KeyState::Down, ord)
+                    CTRL.store(is_down, ord)
+                }
+                KeyCode::CapsLock if is_down => {
+                    CAPS_LOCK.fetch_xor(true, ord);
                 }
                 _ => {}
             }
             let is_alt = ALT.load(ord);
+            let is_alt_gr = ALT_GR.load(ord);
             let is_ctrl = CTRL.load(ord);
             let is_shift = SHIFT.load(ord);
-            if let Some(key) = keyboard.process_keyevent(event) {
+            // The keys of the keymap replace the keys of the layout
+            let key = keyboard.process_keyevent(event);
+            let key = if is_down && !is_extended && !is_ctrl {
+                keymap_char(scancode).map(DecodedKey::Unicode).or(key)
+            } else {
+                key
+            };
+            if let Some(key) = key {
                 match key {
                     // Ctrl-Alt-Del
                     DecodedKey::Unicode('\u{7f}') if is_alt && is_ctrl => {
                         api::power::reboot()
                     }
 
+                    // Alt-F1 to Alt-F4 switch between virtual consoles
+                    DecodedKey::RawKey(KeyCode::F1) if is_alt => {
+                        sys::console::switch(0)
+                    }
+                    DecodedKey::RawKey(KeyCode::F2) if is_alt => {
+                        sys::console::switch(1)
+                    }
+                    DecodedKey::RawKey(KeyCode::F3) if is_alt => {
+                        sys::console::switch(2)
+                    }
+                    DecodedKey::RawKey(KeyCode::F4) if is_alt => {
+                        sys::console::switch(3)
+                    }
+
+                    // Shift-PageUp and Shift-PageDown scroll the history
+                    DecodedKey::RawKey(KeyCode::PageUp) if is_shift => {
+                        sys::console::scroll_up()
+                    }
+                    DecodedKey::RawKey(KeyCode::PageDown) if is_shift => {
+                        sys::console::scroll_down()
+                    }
+
+                    // Ctrl-Left and Ctrl-Right move by words
+                    DecodedKey::RawKey(KeyCode::ArrowRight) if is_ctrl => {
+                        send_csi("1;5C")
+                    }
+                    DecodedKey::RawKey(KeyCode::ArrowLeft) if is_ctrl => {
+                        send_csi("1;5D")
+                    }
+
                     DecodedKey::RawKey(KeyCode::PageUp) => send_csi("5~"),
                     DecodedKey::RawKey(KeyCode::PageDown) => send_csi("6~"),
                     DecodedKey::RawKey(KeyCode::ArrowUp) => send_csi("A"),
@@ -122,6 +281,12 @@ fn interrupt_handler() {
                     // Convert Shift-Tab into Backtab
                     DecodedKey::Unicode('\t') if is_shift => send_csi("Z"),
 
+                    // Alt prefixes the key with ESC like in xterm
+                    DecodedKey::Unicode(c) if is_alt && !is_alt_gr => {
+                        send_key('\x1B');
+                        send_key(c)
+                    }
+
                     DecodedKey::Unicode(c) => send_key(c),
 
                     _ => {}
@@ -130,3 +295,21 @@ fn interrupt_handler() {
         }
     }
 }
+
+#[test_case]
+fn test_keymap() {
+    let s = "# German\n0x03 2 \" ²\n0x15 z Z\n\n0x56 <";
+    let keymap: Keymap = s.parse().unwrap();
+    assert_eq!(keymap.map(0x03, NORMAL), Some('2'));
+    assert_eq!(keymap.map(0x03, SHIFTED), Some('"'));
+    assert_eq!(keymap.map(0x03, ALTGR), Some('²'));
+    assert_eq!(keymap.map(0x15, ALTGR), Some('z'));
+    assert_eq!(keymap.map(0x56, SHIFTED), Some('<'));
+    assert_eq!(keymap.map(0x10, NORMAL), None);
+    assert!(keymap.is_letter(0x15));
+    assert!(!keymap.is_letter(0x03));
+
+    assert!("0x80 a".parse::<Keymap>().is_err());
+    assert!("0x10 ab".parse::<Keymap>().is_err());
+    assert!("0x10 a b c d".parse::<Keymap>().is_err());
+}
@@ -48,6 +48,7 @@ pub mod idt;
 pub mod keyboard;
 pub mod log;
 pub mod mem;
+pub mod mouse;
 pub mod net;
 pub mod pci;
 pub mod pic;
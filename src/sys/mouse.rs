@@ -0,0 +1,261 @@
+use crate::api::fs::{FileIO, IO};
+use crate::sys;
+
+use alloc::format;
+use bit_field::BitField;
+use spin::Mutex;
+use x86_64::instructions::interrupts;
+use x86_64::instructions::port::Port;
+
+// See: https://wiki.osdev.org/PS/2_Mouse
+
+const DATA_PORT: u16 = 0x60;
+const STATUS_PORT: u16 = 0x64;
+const COMMAND_PORT: u16 = 0x64;
+
+const ENABLE_AUX: u8 = 0xA8;
+const READ_CONFIG: u8 = 0x20;
+const WRITE_CONFIG: u8 = 0x60;
+const WRITE_AUX: u8 = 0xD4;
+
+const SET_DEFAULTS: u8 = 0xF6;
+const ENABLE_REPORTING: u8 = 0xF4;
+
+// The pointer moves on a virtual screen of 8x16 pixels per char of the
+// text console
+const CHAR_WIDTH: i64 = 8;
+const CHAR_HEIGHT: i64 = 16;
+const COLS: i64 = 80;
+const ROWS: i64 = 25;
+
+// The relative motion is clamped to fit in the size of the device file
+const MAX_MOTION: i64 = i16::MAX as i64;
+
+static MOUSE: Mutex<MouseState> = Mutex::new(MouseState::new());
+
+struct MouseState {
+    packet: [u8; 3],
+    index: usize,
+    buttons: u8,
+    dx: i64, // Relative motion since the last read of the device
+    dy: i64,
+    x: i64, // Position of the pointer on the screen
+    y: i64,
+}
+
+impl MouseState {
+    const fn new() -> Self {
+        Self { packet: [0; 3], index: 0, buttons: 0, dx: 0, dy: 0, x: 0, y: 0 }
+    }
+
+    // Return a complete packet after receiving its last byte
+    fn add_byte(&mut self, byte: u8) -> Option<[u8; 3]> {
+        // The first byte of a packet always has its bit 3 set
+        if self.index == 0 && !byte.get_bit(3) {
+            return None;
+        }
+        self.packet[self.index] = byte;
+        self.index = (self.index + 1) % 3;
+        if self.index == 0 {
+            Some(self.packet)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Packet {
+    buttons: u8,
+    dx: i64,
+    dy: i64,
+}
+
+impl Packet {
+    fn decode(packet: [u8; 3]) -> Option<Self> {
+        let flags = packet[0];
+        if flags.get_bit(6) || flags.get_bit(7) {
+            return None; // Overflow
+        }
+        let buttons = flags.get_bits(0..3);
+        let mut dx = packet[1] as i64;
+        let mut dy = packet[2] as i64;
+        if flags.get_bit(4) {
+            dx -= 0x100;
+        }
+        if flags.get_bit(5) {
+            dy -= 0x100;
+        }
+        // The Y axis of the packet goes up but the one of the screen goes down
+        Some(Self { buttons, dx, dy: -dy })
+    }
+}
+
+fn wait_read() -> bool {
+    let mut status: Port<u8> = Port::new(STATUS_PORT);
+    for _ in 0..100_000 {
+        if unsafe { status.read() }.get_bit(0) {
+            return true;
+        }
+    }
+    false
+}
+
+fn wait_write() -> bool {
+    let mut status: Port<u8> = Port::new(STATUS_PORT);
+    for _ in 0..100_000 {
+        if !unsafe { status.read() }.get_bit(1) {
+            return true;
+        }
+    }
+    false
+}
+
+fn write_command(cmd: u8) {
+    let mut port: Port<u8> = Port::new(COMMAND_PORT);
+    if wait_write() {
+        unsafe { port.write(cmd) };
+    }
+}
+
+fn write_data(data: u8) {
+    let mut port: Port<u8> = Port::new(DATA_PORT);
+    if wait_write() {
+        unsafe { port.write(data) };
+    }
+}
+
+fn read_data() -> Option<u8> {
+    let mut port: Port<u8> = Port::new(DATA_PORT);
+    if wait_read() {
+        Some(unsafe { port.read() })
+    } else {
+        None
+    }
+}
+
+// Send a command to the mouse and read its acknowledgement
+fn write_mouse(cmd: u8) -> bool {
+    write_command(WRITE_AUX);
+    write_data(cmd);
+    read_data() == Some(0xFA)
+}
+
+pub fn init() {
+    interrupts::without_interrupts(|| {
+        write_command(ENABLE_AUX);
+        write_command(READ_CONFIG);
+        let mut config = read_data().unwrap_or(0);
+        config.set_bit(1, true); // Enable IRQ12
+        config.set_bit(5, false); // Enable mouse clock
+        write_command(WRITE_CONFIG);
+        write_data(config);
+        write_mouse(SET_DEFAULTS);
+        write_mouse(ENABLE_REPORTING);
+    });
+    sys::idt::clear_irq_mask(2); // Cascade from the secondary PIC
+    sys::idt::set_irq_handler(12, interrupt_handler);
+}
+
+fn interrupt_handler() {
+    let mut port: Port<u8> = Port::new(DATA_PORT);
+    let byte = unsafe { port.read() };
+    let mut mouse = MOUSE.lock();
+    let packet = match mouse.add_byte(byte).and_then(Packet::decode) {
+        Some(packet) => packet,
+        None => return,
+    };
+    let (x, y) = (mouse.x / CHAR_WIDTH, mouse.y / CHAR_HEIGHT);
+    let buttons = mouse.buttons;
+    mouse.dx = (mouse.dx + packet.dx).clamp(-MAX_MOTION, MAX_MOTION);
+    mouse.dy = (mouse.dy + packet.dy).clamp(-MAX_MOTION, MAX_MOTION);
+    mouse.x = (mouse.x + packet.dx).clamp(0, COLS * CHAR_WIDTH - 1);
+    mouse.y = (mouse.y + packet.dy).clamp(0, ROWS * CHAR_HEIGHT - 1);
+    mouse.buttons = packet.buttons;
+
+    let (col, row) = (mouse.x / CHAR_WIDTH, mouse.y / CHAR_HEIGHT);
+    drop(mouse);
+    if (col, row) != (x, y) {
+        sys::console::move_pointer(col as usize, row as usize);
+    }
+    for i in 0..3 {
+        let was_pressed = buttons.get_bit(i);
+        let is_pressed = packet.buttons.get_bit(i);
+        if was_pressed != is_pressed {
+            let button = match i {
+                0 => 0, // Left
+                1 => 2, // Right
+                _ => 1, // Middle
+            };
+            let (col, row) = (col as usize, row as usize);
+            sys::console::mouse_handle(button, col, row, is_pressed);
+        }
+    }
+}
+
+// Reading the device returns the state of the buttons followed by the
+// relative motion of the mouse since the last read
+#[derive(Debug, Clone, Default)]
+pub struct Mouse;
+
+impl Mouse {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn size() -> usize {
+        16 // Enough for "7 -32767 -32767"
+    }
+}
+
+impl FileIO for Mouse {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ()> {
+        let s = interrupts::without_interrupts(|| {
+            let mut mouse = MOUSE.lock();
+            let s = format!("{} {} {}", mouse.buttons, mouse.dx, mouse.dy);
+            mouse.dx = 0;
+            mouse.dy = 0;
+            s
+        });
+        let n = s.len();
+        if buf.len() < n {
+            return Err(());
+        }
+        buf[0..n].copy_from_slice(s.as_bytes());
+        Ok(n)
+    }
+
+    fn write(&mut self, _buf: &[u8]) -> Result<usize, ()> {
+        Err(())
+    }
+
+    fn close(&mut self) {}
+
+    fn poll(&mut self, event: IO) -> bool {
+        match event {
+            IO::Read => interrupts::without_interrupts(|| {
+                let mouse = MOUSE.lock();
+                mouse.dx != 0 || mouse.dy != 0
+            }),
+            IO::Write => false,
+        }
+    }
+}
+
+#[test_case]
+fn test_mouse_packet() {
+    let mut mouse = MouseState::new();
+    assert_eq!(mouse.add_byte(0x00), None); // Out of sync
+    assert_eq!(mouse.add_byte(0x09), None);
+    assert_eq!(mouse.add_byte(0x05), None);
+    let packet = mouse.add_byte(0xFE).unwrap();
+    assert_eq!(packet, [0x09, 0x05, 0xFE]);
+    let packet = Packet::decode(packet).unwrap();
+    assert_eq!(packet, Packet { buttons: 1, dx: 5, dy: -254 });
+
+    let packet = Packet::decode([0x29, 0x05, 0xFE]).unwrap();
+    assert_eq!(packet, Packet { buttons: 1, dx: 5, dy: 2 });
+    let packet = Packet::decode([0x1A, 0xFB, 0x03]).unwrap();
+    assert_eq!(packet, Packet { buttons: 2, dx: -5, dy: -3 });
+    assert_eq!(Packet::decode([0x48, 0xFF, 0x00]), None); // Overflow
+}
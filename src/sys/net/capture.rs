@@ -0,0 +1,110 @@
+use crate::sys;
+
+use alloc::collections::vec_deque::VecDeque;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use spin::Mutex;
+
+// See https://wiki.wireshark.org/Development/LibpcapFileFormat
+
+const MAX_FRAMES: usize = 256;
+const SNAP_LEN: usize = 65535;
+const LINKTYPE_ETHERNET: u32 = 1;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static DROPPED: AtomicU64 = AtomicU64::new(0);
+static FRAMES: Mutex<VecDeque<Frame>> = Mutex::new(VecDeque::new());
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    pub time: f64,
+    pub len: usize,
+    pub data: Vec<u8>,
+}
+
+impl Frame {
+    pub fn new(time: f64, buf: &[u8]) -> Self {
+        let data = buf[0..buf.len().min(SNAP_LEN)].to_vec();
+        Self { time, len: buf.len(), data }
+    }
+
+    // Encode the frame into a pcap record
+    pub fn to_pcap(&self) -> Vec<u8> {
+        let sec = self.time as u32;
+        let usec = ((self.time - sec as f64) * 1000000.0) as u32;
+        let mut buf = Vec::with_capacity(16 + self.data.len());
+        buf.extend_from_slice(&sec.to_le_bytes());
+        buf.extend_from_slice(&usec.min(999999).to_le_bytes());
+        buf.extend_from_slice(&(self.data.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&(self.len as u32).to_le_bytes());
+        buf.extend_from_slice(&self.data);
+        buf
+    }
+}
+
+// Return the global header of a pcap file
+pub fn pcap_header() -> Vec<u8> {
+    let mut buf = Vec::with_capacity(24);
+    buf.extend_from_slice(&0xA1B2C3D4u32.to_le_bytes()); // Magic number
+    buf.extend_from_slice(&2u16.to_le_bytes()); // Major version
+    buf.extend_from_slice(&4u16.to_le_bytes()); // Minor version
+    buf.extend_from_slice(&0i32.to_le_bytes()); // Timezone offset
+    buf.extend_from_slice(&0u32.to_le_bytes()); // Timestamps accuracy
+    buf.extend_from_slice(&(SNAP_LEN as u32).to_le_bytes());
+    buf.extend_from_slice(&LINKTYPE_ETHERNET.to_le_bytes());
+    buf
+}
+
+pub fn start() {
+    FRAMES.lock().clear();
+    DROPPED.store(0, Ordering::Relaxed);
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn stop() {
+    ENABLED.store(false, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+// Return the number of frames overwritten since the capture started
+pub fn dropped() -> u64 {
+    DROPPED.load(Ordering::Relaxed)
+}
+
+// Save a copy of a frame received or transmitted by the network device,
+// overwriting the oldest frame when the ring buffer is full.
+pub fn record(buf: &[u8]) {
+    if !is_enabled() {
+        return;
+    }
+    let frame = Frame::new(sys::clk::epoch_time(), buf);
+    let mut frames = FRAMES.lock();
+    if frames.len() == MAX_FRAMES {
+        frames.pop_front();
+        DROPPED.fetch_add(1, Ordering::Relaxed);
+    }
+    frames.push_back(frame);
+}
+
+pub fn pop() -> Option<Frame> {
+    FRAMES.lock().pop_front()
+}
+
+#[test_case]
+fn test_pcap() {
+    let header = pcap_header();
+    assert_eq!(header.len(), 24);
+    assert_eq!(header[0..4], [0xD4, 0xC3, 0xB2, 0xA1]);
+    assert_eq!(header[20..24], [1, 0, 0, 0]);
+
+    let frame = Frame::new(1.5, &[0xFF; 60]);
+    let record = frame.to_pcap();
+    assert_eq!(record.len(), 16 + 60);
+    assert_eq!(record[0..4], [1, 0, 0, 0]);
+    assert_eq!(record[4..8], 500000u32.to_le_bytes());
+    assert_eq!(record[8..12], [60, 0, 0, 0]);
+    assert_eq!(record[12..16], [60, 0, 0, 0]);
+}
@@ -0,0 +1,438 @@
+use crate::sys;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+use core::sync::atomic::{AtomicU64, Ordering};
+use smoltcp::wire::{EthernetFrame, EthernetProtocol, IpCidr, IpProtocol};
+use smoltcp::wire::{IpAddress, Ipv4Packet, Ipv6Packet, TcpPacket, UdpPacket};
+use spin::Mutex;
+
+// Connections are tracked from the packets transmitted to let the replies
+// through before the rules are evaluated.
+const MAX_CONNECTIONS: usize = 256;
+const CONNECTION_TIMEOUT: f64 = 300.0;
+
+static RULES: Mutex<Vec<Rule>> = Mutex::new(Vec::new());
+static CONNECTIONS: Mutex<Vec<(Connection, f64)>> = Mutex::new(Vec::new());
+static ESTABLISHED: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Allow,
+    Deny,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Protocol {
+    Any,
+    Tcp,
+    Udp,
+    Icmp,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rule {
+    pub action: Action,
+    pub protocol: Protocol,
+    pub source: Option<IpCidr>,
+    pub ports: Option<(u16, u16)>,
+    pub hits: u64,
+}
+
+impl Rule {
+    fn matches(&self, packet: &Packet) -> bool {
+        if self.protocol != Protocol::Any && self.protocol != packet.protocol {
+            return false;
+        }
+        if let Some(source) = self.source {
+            if !source.contains_addr(&packet.src_addr) {
+                return false;
+            }
+        }
+        if let Some((first, last)) = self.ports {
+            let has_ports = matches!(
+                packet.protocol, Protocol::Tcp | Protocol::Udp
+            );
+            return has_ports && (first..=last).contains(&packet.dst_port);
+        }
+        true
+    }
+}
+
+// Parse a rule like "deny tcp from 10.0.2.0/24 port 8000-8080"
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+        let action = match words.first() {
+            Some(&"allow") => Action::Allow,
+            Some(&"deny") => Action::Deny,
+            _ => return Err("Could not parse action".into()),
+        };
+        let protocol = match words.get(1) {
+            Some(&"any") => Protocol::Any,
+            Some(&"tcp") => Protocol::Tcp,
+            Some(&"udp") => Protocol::Udp,
+            Some(&"icmp") => Protocol::Icmp,
+            _ => return Err("Could not parse protocol".into()),
+        };
+        let mut source = None;
+        let mut ports = None;
+        let mut i = 2;
+        while i < words.len() {
+            let arg = match words.get(i + 1) {
+                Some(arg) => *arg,
+                None => return Err(format!("Missing value for '{}'", words[i])),
+            };
+            match words[i] {
+                "from" if arg == "any" => {}
+                "from" => {
+                    source = Some(parse_source(arg).ok_or(
+                        format!("Could not parse source '{}'", arg)
+                    )?);
+                }
+                "port" => {
+                    ports = Some(parse_ports(arg).ok_or(
+                        format!("Could not parse port '{}'", arg)
+                    )?);
+                }
+                word => return Err(format!("Unknown keyword '{}'", word)),
+            }
+            i += 2;
+        }
+        let hits = 0;
+        Ok(Self { action, protocol, source, ports, hits })
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let action = match self.action {
+            Action::Allow => "allow",
+            Action::Deny => "deny",
+        };
+        let protocol = match self.protocol {
+            Protocol::Any => "any",
+            Protocol::Tcp => "tcp",
+            Protocol::Udp => "udp",
+            Protocol::Icmp => "icmp",
+        };
+        write!(f, "{} {}", action, protocol)?;
+        if let Some(source) = self.source {
+            write!(f, " from {}", source)?;
+        }
+        match self.ports {
+            Some((first, last)) if first == last => {
+                write!(f, " port {}", first)
+            }
+            Some((first, last)) => write!(f, " port {}-{}", first, last),
+            None => Ok(()),
+        }
+    }
+}
+
+fn parse_source(s: &str) -> Option<IpCidr> {
+    if let Ok(cidr) = IpCidr::from_str(s) {
+        return Some(cidr);
+    }
+    match IpAddress::from_str(s).ok()? {
+        addr @ IpAddress::Ipv4(_) => Some(IpCidr::new(addr, 32)),
+        addr @ IpAddress::Ipv6(_) => Some(IpCidr::new(addr, 128)),
+    }
+}
+
+fn parse_ports(s: &str) -> Option<(u16, u16)> {
+    let (first, last) = s.split_once('-').unwrap_or((s, s));
+    let first = first.parse().ok()?;
+    let last = last.parse().ok()?;
+    if first <= last {
+        Some((first, last))
+    } else {
+        None
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Packet {
+    protocol: Protocol,
+    src_addr: IpAddress,
+    dst_addr: IpAddress,
+    src_port: u16,
+    dst_port: u16,
+}
+
+impl Packet {
+    // Return None for frames without an IP packet like ARP requests, and an
+    // error for IP packets with headers that cannot be parsed
+    fn parse(buf: &[u8]) -> Result<Option<Self>, ()> {
+        let frame = EthernetFrame::new_checked(buf).map_err(|_| ())?;
+        let (protocol, src_addr, dst_addr, payload) = match frame.ethertype() {
+            EthernetProtocol::Ipv4 => {
+                let packet = Ipv4Packet::new_checked(frame.payload()).
+                    map_err(|_| ())?;
+                if packet.frag_offset() != 0 {
+                    return Err(()); // No transport header to match
+                }
+                let src = IpAddress::Ipv4(packet.src_addr());
+                let dst = IpAddress::Ipv4(packet.dst_addr());
+                (packet.next_header(), src, dst, packet.payload())
+            }
+            EthernetProtocol::Ipv6 => {
+                let packet = Ipv6Packet::new_checked(frame.payload()).
+                    map_err(|_| ())?;
+                let src = IpAddress::Ipv6(packet.src_addr());
+                let dst = IpAddress::Ipv6(packet.dst_addr());
+                let (next_header, payload) = skip_ipv6_ext_headers(
+                    packet.next_header(), packet.payload()
+                )?;
+                (next_header, src, dst, payload)
+            }
+            _ => return Ok(None),
+        };
+        let (protocol, src_port, dst_port) = match protocol {
+            IpProtocol::Tcp => {
+                let packet = TcpPacket::new_checked(payload).map_err(|_| ())?;
+                (Protocol::Tcp, packet.src_port(), packet.dst_port())
+            }
+            IpProtocol::Udp => {
+                let packet = UdpPacket::new_checked(payload).map_err(|_| ())?;
+                (Protocol::Udp, packet.src_port(), packet.dst_port())
+            }
+            IpProtocol::Icmp | IpProtocol::Icmpv6 => (Protocol::Icmp, 0, 0),
+            _ => (Protocol::Any, 0, 0),
+        };
+        Ok(Some(Self { protocol, src_addr, dst_addr, src_port, dst_port }))
+    }
+}
+
+// Walk the IPv6 extension headers to find the protocol of the payload
+fn skip_ipv6_ext_headers(
+    mut next_header: IpProtocol,
+    mut payload: &[u8]
+) -> Result<(IpProtocol, &[u8]), ()> {
+    loop {
+        match next_header {
+            IpProtocol::HopByHop
+            | IpProtocol::Ipv6Route
+            | IpProtocol::Ipv6Frag
+            | IpProtocol::Ipv6Opts => {
+                if payload.len() < 8 {
+                    return Err(());
+                }
+                if next_header == IpProtocol::Ipv6Frag {
+                    let offset = u16::from_be_bytes([payload[2], payload[3]]);
+                    if offset >> 3 != 0 {
+                        return Err(()); // No transport header to match
+                    }
+                }
+                // The length of a fragment header is always 8 bytes
+                let len = if next_header == IpProtocol::Ipv6Frag {
+                    8
+                } else {
+                    (payload[1] as usize + 1) * 8
+                };
+                if payload.len() < len {
+                    return Err(());
+                }
+                next_header = IpProtocol::from(payload[0]);
+                payload = &payload[len..];
+            }
+            _ => return Ok((next_header, payload)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Connection {
+    protocol: Protocol,
+    remote_addr: IpAddress,
+    remote_port: u16,
+    local_port: u16,
+}
+
+impl Connection {
+    // The replies to a broadcast or a multicast come from another address
+    fn matches(&self, packet: &Packet) -> bool {
+        let addr = self.remote_addr;
+        self.protocol == packet.protocol
+            && (addr == packet.src_addr || addr.is_broadcast()
+                || addr.is_multicast())
+            && self.remote_port == packet.src_port
+            && self.local_port == packet.dst_port
+    }
+}
+
+pub fn rules() -> Vec<Rule> {
+    RULES.lock().clone()
+}
+
+pub fn set_rules(rules: Vec<Rule>) {
+    *RULES.lock() = rules;
+    CONNECTIONS.lock().clear();
+    ESTABLISHED.store(0, Ordering::Relaxed);
+}
+
+pub fn add_rule(rule: Rule) {
+    RULES.lock().push(rule);
+}
+
+pub fn delete_rule(i: usize) -> bool {
+    let mut rules = RULES.lock();
+    if i < rules.len() {
+        rules.remove(i);
+        true
+    } else {
+        false
+    }
+}
+
+// Return the number of packets let through by the connection tracking
+pub fn established() -> u64 {
+    ESTABLISHED.load(Ordering::Relaxed)
+}
+
+// Return true if the frame received by the network device can be handed
+// to the network stack. The first rule matching the packet decides, and
+// packets that are not matched by any rule are allowed, but IP packets that
+// cannot be parsed are denied when there are rules.
+pub fn accept(buf: &[u8]) -> bool {
+    let mut rules = RULES.lock();
+    if rules.is_empty() {
+        return true;
+    }
+    let packet = match Packet::parse(buf) {
+        Ok(Some(packet)) => packet,
+        Ok(None) => return true,
+        Err(()) => return false,
+    };
+
+    let now = sys::clk::boot_time();
+    let mut connections = CONNECTIONS.lock();
+    connections.retain(|(_, time)| now - *time < CONNECTION_TIMEOUT);
+    if let Some(c) = connections.iter_mut().find(|(c, _)| c.matches(&packet)) {
+        c.1 = now;
+        ESTABLISHED.fetch_add(1, Ordering::Relaxed);
+        return true;
+    }
+
+    for rule in rules.iter_mut() {
+        if rule.matches(&packet) {
+            rule.hits += 1;
+            return rule.action == Action::Allow;
+        }
+    }
+    true
+}
+
+// Remember the connection of a frame transmitted by the network device
+pub fn track(buf: &[u8]) {
+    if RULES.lock().is_empty() {
+        return;
+    }
+    let packet = match Packet::parse(buf) {
+        Ok(Some(packet)) if packet.protocol != Protocol::Any => packet,
+        _ => return,
+    };
+    let connection = Connection {
+        protocol: packet.protocol,
+        remote_addr: packet.dst_addr,
+        remote_port: packet.dst_port,
+        local_port: packet.src_port,
+    };
+    let now = sys::clk::boot_time();
+    let mut connections = CONNECTIONS.lock();
+    if let Some(c) = connections.iter_mut().find(|(c, _)| *c == connection) {
+        c.1 = now;
+        return;
+    }
+    if connections.len() == MAX_CONNECTIONS {
+        connections.remove(0);
+    }
+    connections.push((connection, now));
+}
+
+#[test_case]
+fn test_filter_rule() {
+    let s = "deny tcp from 10.0.2.0/24 port 8000-8080";
+    let rule: Rule = s.parse().unwrap();
+    assert_eq!(rule.action, Action::Deny);
+    assert_eq!(rule.protocol, Protocol::Tcp);
+    assert_eq!(rule.ports, Some((8000, 8080)));
+    assert_eq!(format!("{}", rule), s);
+
+    let rule: Rule = "allow any from 10.0.2.2 port 22".parse().unwrap();
+    assert_eq!(format!("{}", rule), "allow any from 10.0.2.2/32 port 22");
+    let rule: Rule = "allow icmp from any".parse().unwrap();
+    assert_eq!(format!("{}", rule), "allow icmp");
+
+    assert!("drop tcp".parse::<Rule>().is_err());
+    assert!("deny tcp port".parse::<Rule>().is_err());
+    assert!("deny tcp port 80-22".parse::<Rule>().is_err());
+    assert!("deny tcp to 10.0.2.2".parse::<Rule>().is_err());
+
+    let packet = Packet {
+        protocol: Protocol::Tcp,
+        src_addr: IpAddress::v4(10, 0, 2, 2),
+        dst_addr: IpAddress::v4(10, 0, 2, 15),
+        src_port: 41324,
+        dst_port: 8080,
+    };
+    let rule: Rule = s.parse().unwrap();
+    assert!(rule.matches(&packet));
+    let rule: Rule = "deny udp from 10.0.2.0/24".parse().unwrap();
+    assert!(!rule.matches(&packet));
+    let rule: Rule = "deny any from 192.168.0.0/16".parse().unwrap();
+    assert!(!rule.matches(&packet));
+    let rule: Rule = "deny any port 80".parse().unwrap();
+    assert!(!rule.matches(&packet));
+
+    let connection = Connection {
+        protocol: Protocol::Tcp,
+        remote_addr: IpAddress::v4(10, 0, 2, 2),
+        remote_port: 41324,
+        local_port: 8080,
+    };
+    assert!(connection.matches(&packet));
+}
+
+#[test_case]
+fn test_filter_accept() {
+    let saved = rules();
+    set_rules(Vec::from(["deny tcp port 22".parse().unwrap()]));
+
+    let mut tcp = [0; 20];
+    tcp[0..2].copy_from_slice(&41324u16.to_be_bytes());
+    tcp[2..4].copy_from_slice(&22u16.to_be_bytes());
+    tcp[12] = 0x50; // Data offset
+
+    // IPv6 packet with a hop-by-hop options header before the TCP header
+    let mut ip = [0; 40];
+    ip[0] = 0x60;
+    ip[4..6].copy_from_slice(&28u16.to_be_bytes());
+    ip[6] = IpProtocol::HopByHop.into();
+    ip[7] = 64;
+    let mut frame = [0; 14].to_vec();
+    frame[12..14].copy_from_slice(&[0x86, 0xDD]);
+    frame.extend_from_slice(&ip);
+    frame.extend_from_slice(&[IpProtocol::Tcp.into(), 0, 1, 4, 0, 0, 0, 0]);
+    frame.extend_from_slice(&tcp);
+    assert!(!accept(&frame));
+
+    // IPv4 packet with a truncated TCP header
+    let mut ip = [0; 20];
+    ip[0] = 0x45;
+    ip[2..4].copy_from_slice(&24u16.to_be_bytes());
+    ip[8] = 64;
+    ip[9] = IpProtocol::Tcp.into();
+    let mut frame = [0; 14].to_vec();
+    frame[12..14].copy_from_slice(&[0x08, 0x00]);
+    frame.extend_from_slice(&ip);
+    frame.extend_from_slice(&tcp[0..4]);
+    assert!(!accept(&frame));
+
+    set_rules(saved);
+}
@@ -1,8 +1,9 @@
 use crate::api::fs::{FileIO, IO};
 
 use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 use core::str::FromStr;
-use smoltcp::wire::Ipv4Address;
+use smoltcp::wire::IpAddress;
 
 #[derive(Debug, Clone)]
 pub struct NetGw;
@@ -13,7 +14,7 @@ impl NetGw {
     }
 
     pub fn size() -> usize {
-        16
+        (39 + 1) * 2 // One IPv4 and one IPv6 address per line
     }
 }
 
@@ -22,8 +23,11 @@ impl FileIO for NetGw {
         if let Some((ref mut iface, _)) = *super::NET.lock() {
             let mut n = 0;
             iface.routes_mut().update(|storage| {
-                if let Some(route) = storage.iter().next() {
-                    let s = route.via_router.to_string();
+                let routes: Vec<_> = storage.iter().map(|route| {
+                    route.via_router.to_string()
+                }).collect();
+                let s = routes.join("\n");
+                if s.len() <= buf.len() {
                     n = s.len();
                     buf[0..n].copy_from_slice(s.as_bytes());
                 }
@@ -38,13 +42,27 @@ impl FileIO for NetGw {
     fn write(&mut self, buf: &[u8]) -> Result<usize, ()> {
         if let Some((ref mut iface, _)) = *super::NET.lock() {
             if let Ok(s) = String::from_utf8(buf.to_vec()) {
-                if s == "0.0.0.0" {
-                    iface.routes_mut().remove_default_ipv4_route();
-                    return Ok(s.len());
-                } else if let Ok(ip) = Ipv4Address::from_str(&s) {
-                    iface.routes_mut().add_default_ipv4_route(ip).unwrap();
-                    log!("NET GW {}", s);
-                    return Ok(s.len());
+                let s = s.trim();
+                match IpAddress::from_str(s) {
+                    Ok(IpAddress::Ipv4(ip)) if ip.is_unspecified() => {
+                        iface.routes_mut().remove_default_ipv4_route();
+                        return Ok(buf.len());
+                    }
+                    Ok(IpAddress::Ipv6(ip)) if ip.is_unspecified() => {
+                        iface.routes_mut().remove_default_ipv6_route();
+                        return Ok(buf.len());
+                    }
+                    Ok(IpAddress::Ipv4(ip)) => {
+                        iface.routes_mut().add_default_ipv4_route(ip).unwrap();
+                        log!("NET GW {}", s);
+                        return Ok(buf.len());
+                    }
+                    Ok(IpAddress::Ipv6(ip)) => {
+                        iface.routes_mut().add_default_ipv6_route(ip).unwrap();
+                        log!("NET GW {}", s);
+                        return Ok(buf.len());
+                    }
+                    Err(_) => {}
                 }
             }
         }
@@ -60,4 +78,3 @@ impl FileIO for NetGw {
         }
     }
 }
-
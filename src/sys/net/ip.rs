@@ -2,6 +2,7 @@ use crate::api::fs::{FileIO, IO};
 
 use alloc::format;
 use alloc::string::String;
+use alloc::vec::Vec;
 use core::str::FromStr;
 use smoltcp::wire::IpCidr;
 
@@ -14,16 +15,19 @@ impl NetIp {
     }
 
     pub fn size() -> usize {
-        16 + 1 + 3
+        (39 + 1 + 3 + 1) * 4 // One IPv6 address with prefix per line
     }
 }
 
 impl FileIO for NetIp {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize, ()> {
         if let Some((ref mut iface, _)) = *super::NET.lock() {
-            if let Some(ip) = iface.ip_addrs().iter().next() {
-                let s = format!("{}/{}", ip.address(), ip.prefix_len());
-                let n = s.len();
+            let addrs: Vec<_> = iface.ip_addrs().iter().map(|ip| {
+                format!("{}/{}", ip.address(), ip.prefix_len())
+            }).collect();
+            let s = addrs.join("\n");
+            let n = s.len();
+            if n > 0 && n <= buf.len() {
                 buf[0..n].copy_from_slice(s.as_bytes());
                 return Ok(n);
             }
@@ -31,14 +35,17 @@ impl FileIO for NetIp {
         Err(())
     }
 
+    // Writing an IPv4 address will replace the current IPv4 address, and
+    // writing an IPv6 address will replace the IPv6 address of the same scope
+    // (link-local or global).
     fn write(&mut self, buf: &[u8]) -> Result<usize, ()> {
         if let Ok(s) = String::from_utf8(buf.to_vec()) {
-            if let Ok(addr) = IpCidr::from_str(&s) {
+            if let Ok(addr) = IpCidr::from_str(s.trim()) {
                 if let Some((ref mut iface, _)) = *super::NET.lock() {
                     iface.update_ip_addrs(|addrs| {
-                        addrs.clear();
+                        addrs.retain(|a| !is_same_scope(a, &addr));
                         addrs.push(addr).unwrap();
-                        log!("NET IP {}", s);
+                        log!("NET IP {}", s.trim());
                     });
                     return Ok(buf.len());
                 }
@@ -57,3 +64,25 @@ impl FileIO for NetIp {
     }
 }
 
+fn is_same_scope(a: &IpCidr, b: &IpCidr) -> bool {
+    match (a, b) {
+        (IpCidr::Ipv4(_), IpCidr::Ipv4(_)) => true,
+        (IpCidr::Ipv6(a), IpCidr::Ipv6(b)) => {
+            a.address().is_unicast_link_local() ==
+            b.address().is_unicast_link_local()
+        }
+        _ => false,
+    }
+}
+
+#[test_case]
+fn test_is_same_scope() {
+    let v4 = IpCidr::from_str("10.0.2.15/24").unwrap();
+    let ll = IpCidr::from_str("fe80::5054:ff:fe12:3456/64").unwrap();
+    let v6 = IpCidr::from_str("fec0::5054:ff:fe12:3456/64").unwrap();
+    assert!(is_same_scope(&v4, &v4));
+    assert!(is_same_scope(&ll, &ll));
+    assert!(is_same_scope(&v6, &v6));
+    assert!(!is_same_scope(&v4, &v6));
+    assert!(!is_same_scope(&ll, &v6));
+}
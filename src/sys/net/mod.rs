@@ -1,4 +1,6 @@
 mod nic;
+pub mod capture;
+pub mod filter;
 pub mod gw;
 pub mod ip;
 pub mod mac;
@@ -15,7 +17,7 @@ use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use smoltcp::iface::Interface;
 use smoltcp::phy::DeviceCapabilities;
 use smoltcp::time::Instant;
-use smoltcp::wire::EthernetAddress;
+use smoltcp::wire::{EthernetAddress, IpCidr, Ipv6Address};
 use spin::Mutex;
 
 pub static NET: Mutex<Option<(Interface, EthernetDevice)>> = Mutex::new(None);
@@ -31,6 +33,12 @@ pub enum SocketStatus {
     MayRecv = 6,
 }
 
+#[repr(usize)]
+pub enum SocketOption {
+    RecvBufferSize = 1,
+    SendBufferSize = 2,
+}
+
 fn time() -> Instant {
     Instant::from_micros((sys::clk::epoch_time() * 1000000.0) as i64)
 }
@@ -108,20 +116,23 @@ impl<'a> smoltcp::phy::Device for EthernetDevice {
         &mut self,
         _instant: smoltcp::time::Instant,
     ) -> Option<(Self::RxToken<'a>, Self::TxToken<'a>)> {
-        if let Some(buffer) = self.receive_packet() {
+        while let Some(buffer) = self.receive_packet() {
+            capture::record(&buffer);
             if self.config().is_debug_enabled() {
                 debug!("NET Packet Received");
                 usr::hex::print_hex(&buffer);
             }
             self.stats().rx_add(buffer.len() as u64);
+            if !filter::accept(&buffer) {
+                continue;
+            }
             let rx = RxToken { buffer };
             let tx = TxToken {
                 device: self.clone(),
             };
-            Some((rx, tx))
-        } else {
-            None
+            return Some((rx, tx));
         }
+        None
     }
 
     fn transmit(
@@ -161,6 +172,8 @@ impl smoltcp::phy::TxToken for TxToken {
         let config = self.device.config();
         let buf = self.device.next_tx_buffer(len);
         let res = f(buf);
+        capture::record(buf);
+        filter::track(buf);
         if config.is_debug_enabled() {
             debug!("NET Packet Transmitted");
             usr::hex::print_hex(buf);
@@ -249,6 +262,20 @@ impl Stats {
     }
 }
 
+// Interface identifier in modified EUI-64 format (RFC 4291)
+pub fn interface_id(mac: EthernetAddress) -> [u8; 8] {
+    let m = mac.0;
+    [m[0] ^ 0x02, m[1], m[2], 0xFF, 0xFE, m[3], m[4], m[5]]
+}
+
+fn link_local_addr(mac: EthernetAddress) -> Ipv6Address {
+    let mut octets = [0; 16];
+    octets[0] = 0xFE;
+    octets[1] = 0x80;
+    octets[8..16].copy_from_slice(&interface_id(mac));
+    Ipv6Address::from_octets(octets)
+}
+
 fn find_device(vendor_id: u16, device_id: u16) -> Option<DeviceConfig> {
     if let Some(mut dev) = sys::pci::find_device(vendor_id, device_id) {
         dev.enable_bus_mastering();
@@ -278,7 +305,13 @@ pub fn init() {
             log!("NET MAC {}", addr);
 
             let config = smoltcp::iface::Config::new(mac.into());
-            let iface = Interface::new(config, &mut device, time());
+            let mut iface = Interface::new(config, &mut device, time());
+
+            let addr = IpCidr::new(link_local_addr(mac).into(), 64);
+            iface.update_ip_addrs(|addrs| {
+                addrs.push(addr).unwrap();
+            });
+            log!("NET IP {}", addr);
 
             *NET.lock() = Some((iface, device));
         }
@@ -303,3 +336,12 @@ pub fn init() {
         }
     }
 }
+
+#[test_case]
+fn test_link_local_addr() {
+    let mac = EthernetAddress([0x52, 0x54, 0x00, 0x12, 0x34, 0x56]);
+    assert_eq!(
+        link_local_addr(mac),
+        Ipv6Address::new(0xFE80, 0, 0, 0, 0x5054, 0x00FF, 0xFE12, 0x3456)
+    );
+}
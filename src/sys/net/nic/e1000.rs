@@ -47,6 +47,7 @@ const ICR_RXDMT0: u32 = 1 << 4; // Receive Descriptor Minimum Threshold Reached
 const ICR_RXT0: u32 =   1 << 7; // Receiver Timer Interrupt
 
 const RCTL_EN: u32 =    1 << 1;  // Receiver Enable
+const RCTL_MPE: u32 =   1 << 4;  // Multicast Promiscuous Enabled
 const RCTL_BAM: u32 =   1 << 15; // Broadcast Accept Mode
 const RCTL_SECRC: u32 = 1 << 26; // Strip Ethernet CRC
 
@@ -221,7 +222,8 @@ impl Device {
         self.write(REG_RDT, (n - 1) as u32);
 
         // Control Register
-        self.write(REG_RCTL, RCTL_EN | RCTL_BAM | RCTL_SECRC | RCTL_BSIZE);
+        let flags = RCTL_EN | RCTL_MPE | RCTL_BAM | RCTL_SECRC | RCTL_BSIZE;
+        self.write(REG_RCTL, flags);
     }
 
     fn init_tx(&mut self) {
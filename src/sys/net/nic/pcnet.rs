@@ -205,6 +205,7 @@ impl Device {
         init_struct[7] = mac[3];
         init_struct[8] = mac[4];
         init_struct[9] = mac[5];
+        init_struct[12..20].fill(0xFF); // Accept all multicast (LADRF)
         let rx_addr = self.rx_des.addr().to_le_bytes();
         init_struct[20] = rx_addr[0];
         init_struct[21] = rx_addr[1];
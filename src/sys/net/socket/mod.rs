@@ -1,6 +1,7 @@
 use crate::sys;
 
 pub mod tcp;
+pub mod telnet;
 pub mod udp;
 
 use alloc::vec;
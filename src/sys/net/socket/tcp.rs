@@ -1,17 +1,35 @@
 use crate::sys;
 
 use crate::api::fs::{FileIO, IO};
-use crate::sys::net::SocketStatus;
+use crate::sys::net::{SocketOption, SocketStatus};
 
 use super::SOCKETS;
 use super::{random_port, wait};
 
+use alloc::collections::btree_map::BTreeMap;
 use alloc::vec;
+use alloc::vec::Vec;
 use bit_field::BitField;
-use smoltcp::iface::SocketHandle;
+use core::sync::atomic::{AtomicU64, Ordering};
+use smoltcp::iface::{SocketHandle, SocketSet};
 use smoltcp::phy::Device;
 use smoltcp::socket::tcp;
 use smoltcp::wire::IpAddress;
+use spin::Mutex;
+
+// The window scale option is used with receive buffers larger than 64 KB
+pub const DEFAULT_BUFFER_SIZE: usize = 16 << 10;
+pub const MIN_BUFFER_SIZE: usize = 1 << 10;
+pub const MAX_BUFFER_SIZE: usize = 1 << 20;
+
+// Sockets waiting for the end of their connection to be removed
+static CLOSED: Mutex<Vec<SocketHandle>> = Mutex::new(Vec::new());
+
+// The slot of a removed socket can be given to a new socket, so each socket
+// gets a unique ID to detect the copies of a handle left by `dup` that would
+// otherwise point to the socket of another connection.
+static IDS: Mutex<BTreeMap<SocketHandle, u64>> = Mutex::new(BTreeMap::new());
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
 
 fn tcp_socket_status(socket: &tcp::Socket) -> u8 {
     let mut status = 0;
@@ -28,6 +46,7 @@ fn tcp_socket_status(socket: &tcp::Socket) -> u8 {
 #[derive(Debug, Clone)]
 pub struct TcpSocket {
     pub handle: SocketHandle,
+    id: u64,
 }
 
 impl TcpSocket {
@@ -35,7 +54,7 @@ impl TcpSocket {
         if let Some((_, ref mut device)) = *sys::net::NET.lock() {
             let mtu = device.capabilities().max_transmission_unit;
             let eth_header = 14;
-            let ip_header = 20;
+            let ip_header = 40; // IPv6 header is larger than IPv4
             let tcp_header = 20;
             mtu - eth_header - ip_header - tcp_header
         } else {
@@ -45,12 +64,73 @@ impl TcpSocket {
 
     pub fn new() -> Self {
         let mut sockets = SOCKETS.lock();
-        let tcp_rx_buffer = tcp::SocketBuffer::new(vec![0; 1024]);
-        let tcp_tx_buffer = tcp::SocketBuffer::new(vec![0; 1024]);
-        let tcp_socket = tcp::Socket::new(tcp_rx_buffer, tcp_tx_buffer);
-        let handle = sockets.add(tcp_socket);
 
-        Self { handle }
+        // Free the buffers of the sockets closed previously
+        CLOSED.lock().retain(|&handle| {
+            let socket = sockets.get::<tcp::Socket>(handle);
+            if socket.state() == tcp::State::Closed {
+                sockets.remove(handle);
+                IDS.lock().remove(&handle);
+                false
+            } else {
+                true
+            }
+        });
+
+        let size = DEFAULT_BUFFER_SIZE;
+        let handle = sockets.add(Self::socket(size, size));
+        let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+        IDS.lock().insert(handle, id);
+
+        Self { handle, id }
+    }
+
+    // Return the socket of the handle unless it has been removed
+    fn lookup<'a>(
+        &self,
+        sockets: &'a mut SocketSet<'static>
+    ) -> Result<&'a mut tcp::Socket<'static>, ()> {
+        if IDS.lock().get(&self.handle) == Some(&self.id) {
+            Ok(sockets.get_mut::<tcp::Socket>(self.handle))
+        } else {
+            Err(())
+        }
+    }
+
+    fn socket(rx_size: usize, tx_size: usize) -> tcp::Socket<'static> {
+        let tcp_rx_buffer = tcp::SocketBuffer::new(vec![0; rx_size]);
+        let tcp_tx_buffer = tcp::SocketBuffer::new(vec![0; tx_size]);
+        tcp::Socket::new(tcp_rx_buffer, tcp_tx_buffer)
+    }
+
+    // Set the size of the receive or send buffer before the socket is used,
+    // or get it when the value is 0.
+    pub fn ioctl(&mut self, request: usize, value: usize) -> Option<usize> {
+        let mut sockets = SOCKETS.lock();
+        let socket = self.lookup(&mut sockets).ok()?;
+        let mut rx_size = socket.recv_capacity();
+        let mut tx_size = socket.send_capacity();
+        let size = value.clamp(MIN_BUFFER_SIZE, MAX_BUFFER_SIZE);
+        match request {
+            r if r == SocketOption::RecvBufferSize as usize => {
+                if value == 0 {
+                    return Some(rx_size);
+                }
+                rx_size = size;
+            }
+            r if r == SocketOption::SendBufferSize as usize => {
+                if value == 0 {
+                    return Some(tx_size);
+                }
+                tx_size = size;
+            }
+            _ => return None,
+        }
+        if socket.state() != tcp::State::Closed {
+            return None;
+        }
+        *socket = Self::socket(rx_size, tx_size);
+        Some(size)
     }
 
     pub fn connect(&mut self, addr: IpAddress, port: u16) -> Result<(), ()> {
@@ -64,7 +144,7 @@ impl TcpSocket {
                 }
                 let mut sockets = SOCKETS.lock();
                 iface.poll(sys::net::time(), device, &mut sockets);
-                let socket = sockets.get_mut::<tcp::Socket>(self.handle);
+                let socket = self.lookup(&mut sockets)?;
 
                 match socket.state() {
                     tcp::State::Closed => {
@@ -101,7 +181,7 @@ impl TcpSocket {
         if let Some((ref mut iface, ref mut device)) = *sys::net::NET.lock() {
             let mut sockets = SOCKETS.lock();
             iface.poll(sys::net::time(), device, &mut sockets);
-            let socket = sockets.get_mut::<tcp::Socket>(self.handle);
+            let socket = self.lookup(&mut sockets)?;
 
             if socket.listen(port).is_err() {
                 return Err(());
@@ -127,7 +207,7 @@ impl TcpSocket {
                 }
                 let mut sockets = SOCKETS.lock();
                 iface.poll(sys::net::time(), device, &mut sockets);
-                let socket = sockets.get_mut::<tcp::Socket>(self.handle);
+                let socket = self.lookup(&mut sockets)?;
 
                 if let Some(endpoint) = socket.remote_endpoint() {
                     return Ok(endpoint.addr);
@@ -156,7 +236,7 @@ impl FileIO for TcpSocket {
                     return Err(());
                 }
                 iface.poll(sys::net::time(), device, &mut sockets);
-                let socket = sockets.get_mut::<tcp::Socket>(self.handle);
+                let socket = self.lookup(&mut sockets)?;
 
                 if buf.len() == 1 {
                     // 1 byte status read
@@ -182,10 +262,12 @@ impl FileIO for TcpSocket {
         }
     }
 
+    // Return the number of bytes that could be queued in the send buffer,
+    // which can be less than the size of the given buffer.
     fn write(&mut self, buf: &[u8]) -> Result<usize, ()> {
         let timeout = 5.0;
         let started = sys::clk::epoch_time();
-        let mut sent = false;
+        let mut sent = None;
         if let Some((ref mut iface, ref mut device)) = *sys::net::NET.lock() {
             let mut sockets = SOCKETS.lock();
             loop {
@@ -193,16 +275,16 @@ impl FileIO for TcpSocket {
                     return Err(());
                 }
                 iface.poll(sys::net::time(), device, &mut sockets);
-                let socket = sockets.get_mut::<tcp::Socket>(self.handle);
+                let socket = self.lookup(&mut sockets)?;
 
-                if sent {
+                if sent.is_some() {
                     break;
                 }
                 if socket.can_send() {
-                    if socket.send_slice(buf.as_ref()).is_err() {
-                        return Err(());
+                    match socket.send_slice(buf.as_ref()) {
+                        Ok(n) => sent = Some(n), // Break after next poll
+                        Err(_) => return Err(()),
                     }
-                    sent = true; // Break after next poll
                 }
 
                 if let Some(d) = iface.poll_delay(sys::net::time(), &sockets) {
@@ -210,7 +292,7 @@ impl FileIO for TcpSocket {
                 }
                 sys::clk::halt();
             }
-            Ok(buf.len())
+            sent.ok_or(())
         } else {
             Err(())
         }
@@ -222,7 +304,10 @@ impl FileIO for TcpSocket {
             let mut sockets = SOCKETS.lock();
             loop {
                 iface.poll(sys::net::time(), device, &mut sockets);
-                let socket = sockets.get_mut::<tcp::Socket>(self.handle);
+                let socket = match self.lookup(&mut sockets) {
+                    Ok(socket) => socket,
+                    Err(()) => break,
+                };
 
                 if closed {
                     break;
@@ -230,6 +315,11 @@ impl FileIO for TcpSocket {
                 socket.close();
                 closed = true;
 
+                let mut handles = CLOSED.lock();
+                if !handles.contains(&self.handle) {
+                    handles.push(self.handle);
+                }
+
                 if let Some(d) = iface.poll_delay(sys::net::time(), &sockets) {
                     wait(d);
                 }
@@ -242,7 +332,10 @@ impl FileIO for TcpSocket {
         if let Some((ref mut iface, ref mut device)) = *sys::net::NET.lock() {
             let mut sockets = SOCKETS.lock();
             iface.poll(sys::net::time(), device, &mut sockets);
-            let socket = sockets.get_mut::<tcp::Socket>(self.handle);
+            let socket = match self.lookup(&mut sockets) {
+                Ok(socket) => socket,
+                Err(()) => return false,
+            };
 
             match event {
                 IO::Read => socket.can_recv(),
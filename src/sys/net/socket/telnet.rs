@@ -0,0 +1,292 @@
+use crate::api::fs::{FileIO, IO};
+use crate::sys::console::EOT_KEY;
+use crate::sys::net::SocketStatus;
+
+use super::tcp::TcpSocket;
+
+use alloc::collections::vec_deque::VecDeque;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use bit_field::BitField;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+// See https://www.rfc-editor.org/rfc/rfc854
+
+const IAC: u8 = 255;
+const DONT: u8 = 254;
+const DO: u8 = 253;
+const WONT: u8 = 252;
+const WILL: u8 = 251;
+const SB: u8 = 250;
+const SE: u8 = 240;
+
+const ECHO: u8 = 1;
+const SUPPRESS_GO_AHEAD: u8 = 3;
+
+const DISABLE_ECHO: &[u8] = b"\x1b[12l";
+const ENABLE_ECHO: &[u8] = b"\x1b[12h";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Data,
+    Cr,
+    Iac,
+    Option(u8),
+    Sub,
+    SubIac,
+}
+
+#[derive(Debug, Clone)]
+struct Input {
+    state: State,
+    queue: VecDeque<u8>,
+}
+
+impl Input {
+    fn new() -> Self {
+        Self { state: State::Data, queue: VecDeque::new() }
+    }
+
+    // Strip the commands from the data received and translate the line
+    // endings, then return the replies to the options requested by the
+    // client.
+    fn decode(&mut self, data: &[u8]) -> Vec<u8> {
+        let mut replies = Vec::new();
+        for &b in data {
+            self.state = match (self.state, b) {
+                (State::Iac, IAC) => {
+                    self.queue.push_back(IAC);
+                    State::Data
+                }
+                (State::Iac, SB) => State::Sub,
+                (State::Iac, WILL..=DONT) => State::Option(b),
+                (State::Iac, _) => State::Data,
+                (State::Option(cmd), opt) => {
+                    match cmd {
+                        DO if opt != ECHO && opt != SUPPRESS_GO_AHEAD => {
+                            replies.extend_from_slice(&[IAC, WONT, opt]);
+                        }
+                        WILL if opt == SUPPRESS_GO_AHEAD => {
+                            replies.extend_from_slice(&[IAC, DO, opt]);
+                        }
+                        WILL => {
+                            replies.extend_from_slice(&[IAC, DONT, opt]);
+                        }
+                        _ => {}
+                    }
+                    State::Data
+                }
+                (State::Sub, IAC) => State::SubIac,
+                (State::Sub, _) => State::Sub,
+                (State::SubIac, SE) => State::Data,
+                (State::SubIac, _) => State::Sub,
+                (_, IAC) => State::Iac,
+                (State::Cr, b'\n' | b'\0') => State::Data,
+                (_, b'\r') => {
+                    self.queue.push_back(b'\n');
+                    State::Cr
+                }
+                (_, 0x7F) => {
+                    self.queue.push_back(0x08); // Backspace
+                    State::Data
+                }
+                (_, b) => {
+                    self.queue.push_back(b);
+                    State::Data
+                }
+            };
+        }
+        replies
+    }
+}
+
+// A TCP socket used as a terminal by a telnet client, that can be bound to
+// the standard handles of a shell session.
+#[derive(Debug, Clone)]
+pub struct TelnetSocket {
+    socket: TcpSocket,
+    echo: Arc<AtomicBool>,
+    input: Input,
+}
+
+impl TelnetSocket {
+    pub fn new(socket: TcpSocket) -> Self {
+        let echo = Arc::new(AtomicBool::new(true));
+        Self { socket, echo, input: Input::new() }
+    }
+
+    // Tell the client that the server will echo the input and that it can
+    // be sent one character at a time.
+    pub fn negotiate(&mut self) -> bool {
+        self.send(&[IAC, WILL, ECHO, IAC, WILL, SUPPRESS_GO_AHEAD]).is_ok()
+    }
+
+    fn send(&mut self, buf: &[u8]) -> Result<(), ()> {
+        let mut i = 0;
+        while i < buf.len() {
+            i += self.socket.write(&buf[i..])?;
+        }
+        Ok(())
+    }
+
+    // Wait without timeout for more data from the client and return false
+    // when the connection is closed.
+    fn recv(&mut self) -> bool {
+        let mut buf = [0; 256];
+        loop {
+            match self.socket.read(&mut buf) {
+                Ok(0) => return false,
+                Ok(n) => {
+                    let replies = self.input.decode(&buf[0..n]);
+                    if !replies.is_empty() {
+                        self.send(&replies).ok();
+                    }
+                    return true;
+                }
+                Err(()) => {
+                    let mut status = [0];
+                    if self.socket.read(&mut status).is_err() {
+                        return false;
+                    }
+                    if !status[0].get_bit(SocketStatus::MayRecv as usize) {
+                        return false;
+                    }
+                }
+            }
+        }
+    }
+
+    fn read_char(&mut self, buf: &mut [u8]) -> usize {
+        loop {
+            if let Some(&b) = self.input.queue.front() {
+                let n = match b {
+                    0xC0..=0xDF => 2,
+                    0xE0..=0xEF => 3,
+                    0xF0..=0xF7 => 4,
+                    _ => 1,
+                };
+                if self.input.queue.len() >= n {
+                    for (i, b) in self.input.queue.drain(0..n).enumerate() {
+                        buf[i] = b;
+                    }
+                    return n;
+                }
+            }
+            if !self.recv() {
+                buf[0] = EOT_KEY as u8;
+                return 1;
+            }
+        }
+    }
+
+    fn read_line(&mut self, buf: &mut [u8]) -> usize {
+        let echo = self.echo.load(Ordering::SeqCst);
+        let mut line = Vec::new();
+        loop {
+            while let Some(b) = self.input.queue.pop_front() {
+                match b {
+                    b'\n' => {
+                        line.push(b);
+                        if echo {
+                            self.send(b"\r\n").ok();
+                        }
+                        let n = line.len().min(buf.len());
+                        buf[0..n].copy_from_slice(&line[0..n]);
+                        return n;
+                    }
+                    0x08 => {
+                        // Remove the last UTF-8 character of the line
+                        let mut erased = false;
+                        while let Some(b) = line.pop() {
+                            erased = true;
+                            if b & 0xC0 != 0x80 {
+                                break;
+                            }
+                        }
+                        if erased && echo {
+                            self.send(b"\x08 \x08").ok();
+                        }
+                    }
+                    b if b < 0x20 => {}
+                    b => {
+                        line.push(b);
+                        if echo {
+                            self.send(&[b]).ok();
+                        }
+                    }
+                }
+            }
+            if !self.recv() {
+                return 0;
+            }
+        }
+    }
+}
+
+impl FileIO for TelnetSocket {
+    // Read one character at a time with a 4 bytes buffer like the console
+    // does in raw mode, or a whole line echoed to the client otherwise.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ()> {
+        match buf.len() {
+            1 => self.socket.read(buf), // 1 byte status read
+            4 => Ok(self.read_char(buf)),
+            _ => Ok(self.read_line(buf)),
+        }
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Result<usize, ()> {
+        let mut out = Vec::with_capacity(buf.len());
+        let mut i = 0;
+        while i < buf.len() {
+            if buf[i..].starts_with(DISABLE_ECHO) {
+                self.echo.store(false, Ordering::SeqCst);
+                i += DISABLE_ECHO.len();
+                continue;
+            }
+            if buf[i..].starts_with(ENABLE_ECHO) {
+                self.echo.store(true, Ordering::SeqCst);
+                i += ENABLE_ECHO.len();
+                continue;
+            }
+            match buf[i] {
+                b'\n' => out.extend_from_slice(b"\r\n"),
+                IAC => out.extend_from_slice(&[IAC, IAC]),
+                b => out.push(b),
+            }
+            i += 1;
+        }
+        self.send(&out)?;
+        Ok(buf.len())
+    }
+
+    fn close(&mut self) {
+        self.socket.close();
+    }
+
+    fn poll(&mut self, event: IO) -> bool {
+        match event {
+            IO::Read if !self.input.queue.is_empty() => true,
+            _ => self.socket.poll(event),
+        }
+    }
+}
+
+#[test_case]
+fn test_decode() {
+    let mut input = Input::new();
+    let replies = input.decode(b"ls\r\0cd\r\n\xFF\xFD\x01\xFF\xFD\x1F");
+    assert_eq!(replies, [IAC, WONT, 0x1F]);
+    let data: Vec<u8> = input.queue.drain(..).collect();
+    assert_eq!(data, b"ls\ncd\n");
+
+    let replies = input.decode(b"\xFF\xFA\x1F\x00\x50\xFF\xF0a\xFF\xFF\x7F");
+    assert!(replies.is_empty());
+    let data: Vec<u8> = input.queue.drain(..).collect();
+    assert_eq!(data, b"a\xFF\x08");
+
+    // Line ending split between two reads
+    input.decode(b"ls\r");
+    input.decode(b"\n");
+    let data: Vec<u8> = input.queue.drain(..).collect();
+    assert_eq!(data, b"ls\n");
+}
@@ -13,6 +13,10 @@ use smoltcp::phy::Device;
 use smoltcp::socket::udp;
 use smoltcp::wire::{IpAddress, IpEndpoint, IpListenEndpoint};
 
+// Enough space to queue a few datagrams as large as the MTU
+const MAX_PACKETS: usize = 4;
+const BUFFER_SIZE: usize = 4 << 10;
+
 fn udp_socket_status(socket: &udp::Socket) -> u8 {
     let mut status = 0;
     status.set_bit(SocketStatus::IsOpen as usize, socket.is_open());
@@ -32,7 +36,7 @@ impl UdpSocket {
         if let Some((_, ref mut device)) = *sys::net::NET.lock() {
             let mtu = device.capabilities().max_transmission_unit;
             let eth_header = 14;
-            let ip_header = 20;
+            let ip_header = 40; // IPv6 header is larger than IPv4
             let udp_header = 8;
             mtu - eth_header - ip_header - udp_header
         } else {
@@ -43,10 +47,10 @@ impl UdpSocket {
     pub fn new() -> Self {
         let mut sockets = SOCKETS.lock();
         let udp_rx_buffer = udp::PacketBuffer::new(
-            vec![udp::PacketMetadata::EMPTY], vec![0; 1024]
+            vec![udp::PacketMetadata::EMPTY; MAX_PACKETS], vec![0; BUFFER_SIZE]
         );
         let udp_tx_buffer = udp::PacketBuffer::new(
-            vec![udp::PacketMetadata::EMPTY], vec![0; 1024]
+            vec![udp::PacketMetadata::EMPTY; MAX_PACKETS], vec![0; BUFFER_SIZE]
         );
         let udp_socket = udp::Socket::new(udp_rx_buffer, udp_tx_buffer);
         let handle = sockets.add(udp_socket);
@@ -86,51 +90,62 @@ impl UdpSocket {
         Ok(())
     }
 
-    pub fn listen(&mut self, _port: u16) -> Result<(), ()> {
-        todo!()
+    pub fn listen(&mut self, port: u16) -> Result<(), ()> {
+        if let Some((ref mut iface, ref mut device)) = *sys::net::NET.lock() {
+            let mut sockets = SOCKETS.lock();
+            iface.poll(sys::net::time(), device, &mut sockets);
+            let socket = sockets.get_mut::<udp::Socket>(self.handle);
+
+            if socket.bind(IpListenEndpoint::from(port)).is_err() {
+                return Err(());
+            }
+
+            if let Some(d) = iface.poll_delay(sys::net::time(), &sockets) {
+                wait(d);
+            }
+            sys::clk::halt();
+            Ok(())
+        } else {
+            Err(())
+        }
     }
 
     pub fn accept(&mut self) -> Result<IpAddress, ()> {
         todo!()
     }
-}
 
-impl FileIO for UdpSocket {
-    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ()> {
-        let timeout = 5.0;
+    // Receive a datagram along with the endpoint that sent it, waiting at
+    // most the given number of seconds.
+    pub fn recv_from(
+        &mut self, buf: &mut [u8], timeout: f64
+    ) -> Option<(usize, IpEndpoint)> {
         let started = sys::clk::epoch_time();
         if let Some((ref mut iface, ref mut device)) = *sys::net::NET.lock() {
-            let bytes;
             let mut sockets = SOCKETS.lock();
             loop {
                 if sys::clk::epoch_time() - started > timeout {
-                    return Err(());
+                    return None;
                 }
                 iface.poll(sys::net::time(), device, &mut sockets);
                 let socket = sockets.get_mut::<udp::Socket>(self.handle);
 
-                if buf.len() == 1 {
-                    // 1 byte status read
-                    buf[0] = udp_socket_status(socket);
-                    return Ok(1);
-                }
-
                 if socket.can_recv() {
-                    (bytes, _) = socket.recv_slice(buf).map_err(|_| ())?;
-                    break;
+                    let (bytes, meta) = socket.recv_slice(buf).ok()?;
+                    return Some((bytes, meta.endpoint));
                 }
                 if let Some(d) = iface.poll_delay(sys::net::time(), &sockets) {
                     wait(d);
                 }
                 sys::clk::halt();
             }
-            Ok(bytes)
         } else {
-            Err(())
+            None
         }
     }
 
-    fn write(&mut self, buf: &[u8]) -> Result<usize, ()> {
+    pub fn send_to(
+        &mut self, buf: &[u8], endpoint: IpEndpoint
+    ) -> Option<usize> {
         let timeout = 5.0;
         let started = sys::clk::epoch_time();
         let mut sent = false;
@@ -138,7 +153,7 @@ impl FileIO for UdpSocket {
             let mut sockets = SOCKETS.lock();
             loop {
                 if sys::clk::epoch_time() - started > timeout {
-                    return Err(());
+                    return None;
                 }
                 iface.poll(sys::net::time(), device, &mut sockets);
                 let socket = sockets.get_mut::<udp::Socket>(self.handle);
@@ -147,12 +162,8 @@ impl FileIO for UdpSocket {
                     break;
                 }
                 if socket.can_send() {
-                    if let Some(endpoint) = self.remote_endpoint {
-                        if socket.send_slice(buf.as_ref(), endpoint).is_err() {
-                            return Err(());
-                        }
-                    } else {
-                        return Err(());
+                    if socket.send_slice(buf, endpoint).is_err() {
+                        return None;
                     }
                     sent = true; // Break after next poll
                 }
@@ -162,12 +173,55 @@ impl FileIO for UdpSocket {
                 }
                 sys::clk::halt();
             }
-            Ok(buf.len())
+            Some(buf.len())
+        } else {
+            None
+        }
+    }
+}
+
+impl FileIO for UdpSocket {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ()> {
+        let timeout = 5.0;
+        let started = sys::clk::epoch_time();
+        if let Some((ref mut iface, ref mut device)) = *sys::net::NET.lock() {
+            let bytes;
+            let mut sockets = SOCKETS.lock();
+            loop {
+                if sys::clk::epoch_time() - started > timeout {
+                    return Err(());
+                }
+                iface.poll(sys::net::time(), device, &mut sockets);
+                let socket = sockets.get_mut::<udp::Socket>(self.handle);
+
+                if buf.len() == 1 {
+                    // 1 byte status read
+                    buf[0] = udp_socket_status(socket);
+                    return Ok(1);
+                }
+
+                if socket.can_recv() {
+                    (bytes, _) = socket.recv_slice(buf).map_err(|_| ())?;
+                    break;
+                }
+                if let Some(d) = iface.poll_delay(sys::net::time(), &sockets) {
+                    wait(d);
+                }
+                sys::clk::halt();
+            }
+            Ok(bytes)
         } else {
             Err(())
         }
     }
 
+    fn write(&mut self, buf: &[u8]) -> Result<usize, ()> {
+        match self.remote_endpoint {
+            Some(endpoint) => self.send_to(buf, endpoint).ok_or(()),
+            None => Err(()),
+        }
+    }
+
     fn close(&mut self) {
         let mut closed = false;
         if let Some((ref mut iface, ref mut device)) = *sys::net::NET.lock() {
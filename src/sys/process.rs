@@ -83,9 +83,9 @@ impl ProcessData {
         let user = user.map(String::from);
 
         let mut handles = [(); MAX_HANDLES].map(|_| None);
-        let stdin = Resource::Device(Device::Console(Console::new()));
-        let stdout = Resource::Device(Device::Console(Console::new()));
-        let stderr = Resource::Device(Device::Console(Console::new()));
+        let stdin = Resource::Device(Device::Console(Console::new(0)));
+        let stdout = Resource::Device(Device::Console(Console::new(0)));
+        let stderr = Resource::Device(Device::Console(Console::new(0)));
         let stdnull = Resource::Device(Device::Null);
         handles[0] = Some(Box::new(stdin));
         handles[1] = Some(Box::new(stdout));
@@ -134,6 +134,12 @@ pub fn set_env(key: &str, val: &str) {
     proc.data.env.insert(key.into(), val.into());
 }
 
+pub fn delete_env(key: &str) {
+    let mut table = PROCESS_TABLE.write();
+    let proc = &mut table[id()];
+    proc.data.env.remove(key);
+}
+
 pub fn set_dir(dir: &str) {
     let mut table = PROCESS_TABLE.write();
     let proc = &mut table[id()];
@@ -146,6 +152,12 @@ pub fn set_user(user: &str) {
     proc.data.user = Some(user.into())
 }
 
+pub fn delete_user() {
+    let mut table = PROCESS_TABLE.write();
+    let proc = &mut table[id()];
+    proc.data.user = None
+}
+
 pub fn create_handle(file: Resource) -> Result<usize, ()> {
     let mut table = PROCESS_TABLE.write();
     let proc = &mut table[id()];
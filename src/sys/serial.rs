@@ -1,7 +1,10 @@
 use crate::sys;
 
+use alloc::string::String;
+use alloc::vec::Vec;
 use core::fmt;
 use core::fmt::Write;
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use lazy_static::lazy_static;
 use spin::Mutex;
 use uart_16550::SerialPort;
@@ -13,6 +16,23 @@ lazy_static! {
     pub static ref PARSER: Mutex<Parser> = Mutex::new(Parser::new());
 }
 
+// Bytes received that don't make a complete char or reply yet
+static INPUT: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+
+// Size of the remote terminal, updated with its replies to the size queries
+static COLS: AtomicUsize = AtomicUsize::new(80);
+static ROWS: AtomicUsize = AtomicUsize::new(25);
+
+// Number of replies expected from the remote terminal
+static QUERIES: AtomicUsize = AtomicUsize::new(0);
+
+// Boot time in seconds, stored as the bits of a float, until which the size
+// reports are removed from the input even if they arrive after the timeout
+static QUERY_DEADLINE: AtomicU64 = AtomicU64::new(0);
+
+const QUERY_TIMEOUT: f64 = 0.2; // seconds
+const QUERY_GRACE_PERIOD: f64 = 2.0; // seconds
+
 pub struct Serial {
     port: SerialPort,
 }
@@ -55,7 +75,9 @@ impl Perform for Serial {
             'h' => { // Enable
                 for param in params.iter() {
                     match param[0] {
-                        12 => sys::console::enable_echo(),
+                        12 => {
+                            sys::console::enable_echo(sys::console::active());
+                        }
                         _ => return,
                     }
                 }
@@ -63,7 +85,9 @@ impl Perform for Serial {
             'l' => { // Disable
                 for param in params.iter() {
                     match param[0] {
-                        12 => sys::console::disable_echo(),
+                        12 => {
+                            sys::console::disable_echo(sys::console::active());
+                        }
                         _ => return,
                     }
                 }
@@ -85,15 +109,127 @@ pub fn init() {
     sys::idt::set_irq_handler(4, interrupt_handler);
 }
 
+pub fn cols() -> usize {
+    COLS.load(Ordering::SeqCst)
+}
+
+pub fn rows() -> usize {
+    ROWS.load(Ordering::SeqCst)
+}
+
+// Ask the remote terminal for its size in chars, and for the position of the
+// cursor after moving it to the bottom right corner of the screen for the
+// terminals without the first query, then wait a little for the replies.
+pub fn query_size() {
+    let start = sys::clk::boot_time();
+    let deadline = start + QUERY_TIMEOUT + QUERY_GRACE_PERIOD;
+    QUERY_DEADLINE.store(deadline.to_bits(), Ordering::SeqCst);
+    QUERIES.store(2, Ordering::SeqCst);
+    print_fmt(format_args!("\x1b[18t\x1b7\x1b[999;999H\x1b[6n\x1b8"));
+    while QUERIES.load(Ordering::SeqCst) > 0 {
+        if sys::clk::boot_time() - start > QUERY_TIMEOUT {
+            QUERIES.store(0, Ordering::SeqCst);
+            break;
+        }
+        sys::clk::halt();
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Input {
+    Keys(String),
+    Size(usize, usize),
+}
+
+// Decode the bytes received, or return `None` if more bytes are needed
+fn decode(bytes: &[u8], is_query: bool) -> Option<Input> {
+    let n = bytes.len();
+    if is_query && bytes[0] == 0x1B && n < 16 {
+        // Wait for the final byte of a control sequence that could be a
+        // reply to a size query
+        match (n, bytes[n - 1]) {
+            (1, _) | (2, b'[') => return None,
+            (2, _) => {}
+            (_, 0x20..=0x3F) => return None,
+            (_, _) => {
+                let s = core::str::from_utf8(&bytes[2..]).unwrap_or("");
+                if let Some((cols, rows)) = parse_size_report(s) {
+                    return Some(Input::Size(cols, rows));
+                }
+            }
+        }
+    }
+    match core::str::from_utf8(bytes) {
+        Ok(s) => Some(Input::Keys(s.into())),
+        Err(e) if e.error_len().is_none() => None, // Incomplete char
+        Err(_) => Some(Input::Keys(String::new())), // Ignore invalid bytes
+    }
+}
+
+// Parse the parameters and the final char of the replies `CSI 8;rows;cols t`
+// and `CSI row;col R` into a size
+fn parse_size_report(s: &str) -> Option<(usize, usize)> {
+    let (params, c) = s.split_at(s.len().checked_sub(1)?);
+    let values: Option<Vec<usize>> = params.split(';').map(|s|
+        s.parse().ok()
+    ).collect();
+    match (c, &values?[..]) {
+        ("t", [8, rows, cols]) | ("R", [rows, cols]) => {
+            if *rows > 0 && *cols > 0 {
+                Some((*cols, *rows))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
 fn interrupt_handler() {
     let b = SERIAL.lock().read_byte();
     if b == 0xFF { // Ignore invalid bytes
         return;
     }
-    let c = match b as char {
-        '\r' => '\n',
-        '\x7F' => '\x08', // Delete => Backspace
-        c => c,
-    };
-    sys::console::key_handle(c);
+    let mut input = INPUT.lock();
+    input.push(b);
+    let deadline = f64::from_bits(QUERY_DEADLINE.load(Ordering::SeqCst));
+    let is_query = sys::clk::boot_time() < deadline;
+    match decode(&input, is_query) {
+        Some(Input::Size(cols, rows)) => {
+            COLS.store(cols, Ordering::SeqCst);
+            ROWS.store(rows, Ordering::SeqCst);
+            if QUERIES.load(Ordering::SeqCst) > 0 {
+                QUERIES.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+        Some(Input::Keys(keys)) => {
+            for c in keys.chars() {
+                let c = match c {
+                    '\r' => '\n',
+                    '\x7F' => '\x08', // Delete => Backspace
+                    c => c,
+                };
+                sys::console::key_handle(c);
+            }
+        }
+        None => return,
+    }
+    input.clear();
+}
+
+#[test_case]
+fn test_decode() {
+    let keys = |s: &str| Some(Input::Keys(s.into()));
+    assert_eq!(decode(b"a", false), keys("a"));
+    assert_eq!(decode(&[0xC3], false), None);
+    assert_eq!(decode(&[0xC3, 0xA9], false), keys("é"));
+    assert_eq!(decode(&[0xA9], false), keys(""));
+    assert_eq!(decode(b"\x1b", false), keys("\x1b"));
+    assert_eq!(decode(b"\x1b", true), None);
+    assert_eq!(decode(b"\x1b[24", true), None);
+    assert_eq!(decode(b"\x1b[24;80R", true), Some(Input::Size(80, 24)));
+    assert_eq!(decode(b"\x1b[8;50;132t", true), Some(Input::Size(132, 50)));
+    assert_eq!(decode(b"\x1b[24;80R", false), keys("\x1b[24;80R"));
+    assert_eq!(decode(b"\x1b[A", true), keys("\x1b[A"));
+    assert_eq!(decode(b"\x1bb", true), keys("\x1bb"));
 }
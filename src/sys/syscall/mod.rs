@@ -5,10 +5,11 @@ use crate::api::process::ExitCode;
 use crate::sys;
 use crate::sys::fs::FileInfo;
 
+use alloc::vec;
 use core::arch::asm;
 use core::convert::TryInto;
 use smoltcp::wire::IpAddress;
-use smoltcp::wire::Ipv4Address;
+use smoltcp::wire::{Ipv4Address, Ipv6Address};
 
 fn utf8_from_raw_parts(ptr: *mut u8, len: usize) -> &'static str {
     unsafe {
@@ -105,8 +106,16 @@ pub fn dispatcher(
             let ptr = sys::process::ptr_from_addr(arg2 as u64);
             let len = arg3;
             let buf = unsafe { core::slice::from_raw_parts(ptr, len) };
-            if let Ok(buf) = buf.try_into() {
-                let addr = IpAddress::from(Ipv4Address::from_octets(buf));
+            let addr = match len {
+                4 => buf.try_into().ok().map(|buf: [u8; 4]| {
+                    IpAddress::from(Ipv4Address::from_octets(buf))
+                }),
+                16 => buf.try_into().ok().map(|buf: [u8; 16]| {
+                    IpAddress::from(Ipv6Address::from_octets(buf))
+                }),
+                _ => None,
+            };
+            if let Some(addr) = addr {
                 let port = arg4 as u16;
                 service::connect(handle, addr, port) as usize
             } else {
@@ -118,14 +127,26 @@ pub fn dispatcher(
             let port = arg2 as u16;
             service::listen(handle, port) as usize
         }
+        number::IOCTL => {
+            let handle = arg1;
+            let request = arg2;
+            let value = arg3;
+            service::ioctl(handle, request, value) as usize
+        }
         number::ACCEPT => {
             let handle = arg1;
             let ptr = sys::process::ptr_from_addr(arg2 as u64);
             let len = arg3;
             let buf = unsafe { core::slice::from_raw_parts_mut(ptr, len) };
-            if let Ok(IpAddress::Ipv4(addr)) = service::accept(handle) {
-                buf[0..len].clone_from_slice(&addr.octets());
-                0
+            let octets = match service::accept(handle) {
+                Ok(IpAddress::Ipv4(addr)) => addr.octets().to_vec(),
+                Ok(IpAddress::Ipv6(addr)) => addr.octets().to_vec(),
+                Err(()) => vec![],
+            };
+            let n = octets.len();
+            if 0 < n && n <= len {
+                buf[0..n].clone_from_slice(&octets);
+                n
             } else {
                 -1 as isize as usize
             }
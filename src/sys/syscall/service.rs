@@ -192,6 +192,22 @@ pub fn accept(handle: usize) -> Result<IpAddress, ()> {
     Err(())
 }
 
+pub fn ioctl(handle: usize, request: usize, value: usize) -> isize {
+    if let Some(mut file) = sys::process::handle(handle) {
+        let res = match *file {
+            Resource::Device(Device::TcpSocket(ref mut dev)) => {
+                dev.ioctl(request, value)
+            }
+            _ => None,
+        };
+        if let Some(res) = res {
+            sys::process::update_handle(handle, *file);
+            return res as isize;
+        }
+    }
+    -1
+}
+
 pub fn alloc(size: usize, align: usize) -> *mut u8 {
     if let Ok(layout) = Layout::from_size_align(size, align) {
         unsafe { sys::process::alloc(layout) }
@@ -1,3 +1,5 @@
+use super::vbe;
+
 use crate::api::fs::{FileIO, IO};
 
 #[derive(Debug, Clone)]
@@ -23,6 +25,9 @@ impl FileIO for Buffer {
     }
 
     fn write(&mut self, buf: &[u8]) -> Result<usize, ()> {
+        if vbe::is_enabled() {
+            return vbe::write(buf).ok_or(());
+        }
         let len = buf.len();
         let src = buf.as_ptr();
         let dst = Self::addr() as *mut u8;
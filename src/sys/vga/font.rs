@@ -1,6 +1,7 @@
-use super::writer::WRITER;
+use super::vbe;
+use super::writer;
 
-use crate::api::font::Font;
+use crate::api::font::{fallback, Font};
 use crate::api::fs::{FileIO, IO};
 
 use core::convert::TryFrom;
@@ -9,6 +10,26 @@ use x86_64::instructions::interrupts;
 
 static FONT: Mutex<Option<Font>> = Mutex::new(None);
 
+// Upper half of the code page 437 of the VGA BIOS
+const CP437: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç',
+    'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å',
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù',
+    'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ',
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º',
+    '¿', '⌐', '¬', '½', '¼', '¡', '«', '»',
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖',
+    '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐',
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟',
+    '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧',
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫',
+    '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀',
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ',
+    'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩',
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈',
+    '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{A0}',
+];
+
 #[derive(Debug, Clone)]
 pub struct VgaFont;
 
@@ -25,8 +46,14 @@ impl FileIO for VgaFont {
 
     fn write(&mut self, buf: &[u8]) -> Result<usize, ()> {
         if let Ok(font) = Font::try_from(buf) {
-            *FONT.lock() = Some(font.clone());
-            write_font(&font);
+            interrupts::without_interrupts(||
+                *FONT.lock() = Some(font.clone())
+            );
+            if vbe::is_enabled() {
+                vbe::set_font(font);
+            } else {
+                write_font(&font);
+            }
             Ok(buf.len()) // TODO: Use font.data.len() ?
         } else {
             Err(())
@@ -45,12 +72,50 @@ impl FileIO for VgaFont {
 
 fn write_font(font: &Font) {
     interrupts::without_interrupts(||
-        WRITER.lock().set_font(font)
+        writer::set_font(font)
     )
 }
 
+// Return the font loaded in text mode or the default font of the system
+pub fn current_font() -> Font {
+    let font = interrupts::without_interrupts(|| FONT.lock().clone());
+    if let Some(font) = font {
+        return font;
+    }
+    let buf = include_bytes!("../../../dsk/ini/fonts/zap-light-8x16.psf");
+    Font::try_from(&buf[..]).expect("Could not parse default font")
+}
+
 pub fn restore_font() {
-    if let Some(ref font) = *FONT.lock() {
-        write_font(font);
+    let font = interrupts::without_interrupts(|| FONT.lock().clone());
+    if let Some(font) = font {
+        write_font(&font);
     }
 }
+
+// Return the index of the glyph of a char in the loaded font, or in the
+// code page of the VGA BIOS before a font is loaded
+pub fn glyph(c: char) -> Option<u8> {
+    let code = interrupts::without_interrupts(|| match *FONT.lock() {
+        Some(ref font) => font.glyph(c),
+        None => cp437(c).or_else(|| fallback(c).and_then(cp437)),
+    })?;
+    u8::try_from(code).ok()
+}
+
+fn cp437(c: char) -> Option<u16> {
+    if c.is_ascii() {
+        Some(c as u16)
+    } else {
+        CP437.iter().position(|&x| x == c).map(|i| 0x80 + i as u16)
+    }
+}
+
+#[test_case]
+fn test_cp437() {
+    assert_eq!(cp437('A'), Some(0x41));
+    assert_eq!(cp437('é'), Some(0x82));
+    assert_eq!(cp437('─'), Some(0xC4));
+    assert_eq!(cp437('\u{A0}'), Some(0xFF));
+    assert_eq!(cp437('ő'), None);
+}
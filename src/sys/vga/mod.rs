@@ -3,16 +3,19 @@ mod font;
 mod buffer;
 mod palette;
 mod screen;
+mod vbe;
 mod writer;
 
 pub use font::VgaFont;
 pub use screen::VgaMode;
 pub use palette::Palette as VgaPalette;
 pub use buffer::Buffer as VgaBuffer;
+pub use vbe::init as init_vbe;
+pub use vbe::is_enabled as is_vbe_enabled;
 
 use color::Color;
 use palette::Palette;
-use writer::WRITER;
+use writer::WRITERS;
 
 use alloc::string::String;
 use bit_field::BitField;
@@ -40,9 +43,40 @@ const INPUT_STATUS_REG:        u16 = 0x3DA;
 const INSTAT_READ_REG:         u16 = 0x3DA;
 
 #[doc(hidden)]
-pub fn print_fmt(args: fmt::Arguments) {
+pub fn print_fmt(vt: usize, args: fmt::Arguments) {
     interrupts::without_interrupts(||
-        WRITER.lock().write_fmt(args).expect("Could not print to VGA")
+        WRITERS[vt].lock().write_fmt(args).expect("Could not print to VGA")
+    )
+}
+
+// Display the virtual console on the screen
+pub fn switch(vt: usize) {
+    interrupts::without_interrupts(|| {
+        for (i, writer) in WRITERS.iter().enumerate() {
+            if i != vt {
+                writer.lock().set_active(false);
+            }
+        }
+        WRITERS[vt].lock().set_active(true);
+    })
+}
+
+// Scroll the screen of the virtual console into its scrollback history
+pub fn scroll_up(vt: usize) {
+    interrupts::without_interrupts(||
+        WRITERS[vt].lock().scroll_up(writer::SCREEN_HEIGHT / 2)
+    )
+}
+
+pub fn scroll_down(vt: usize) {
+    interrupts::without_interrupts(||
+        WRITERS[vt].lock().scroll_down(writer::SCREEN_HEIGHT / 2)
+    )
+}
+
+pub fn set_pointer(vt: usize, x: usize, y: usize) {
+    interrupts::without_interrupts(||
+        WRITERS[vt].lock().set_pointer(x, y)
     )
 }
 
@@ -137,5 +171,7 @@ pub fn init() {
     disable_blinking();
     disable_underline();
 
-    WRITER.lock().clear_screen();
+    for writer in WRITERS.iter() {
+        writer.lock().clear_screen();
+    }
 }
@@ -118,13 +118,13 @@ impl FileIO for VgaPalette {
 
 fn write_palette(i: usize, r: u8, g: u8, b: u8) {
     interrupts::without_interrupts(||
-        WRITER.lock().set_palette(i, r, g, b)
+        writer::set_palette(i, r, g, b)
     )
 }
 
 fn read_palette(i: usize) -> (u8, u8, u8) {
     interrupts::without_interrupts(||
-        WRITER.lock().palette(i)
+        writer::palette(i)
     )
 }
 
@@ -134,6 +134,11 @@ pub fn restore_palette() {
     }
 }
 
+// Return the palette saved before leaving text mode
+pub fn saved_palette() -> Palette {
+    PALETTE.lock().clone().unwrap_or_else(Palette::default)
+}
+
 pub fn backup_palette() {
     *PALETTE.lock() = Some(Palette::read())
 }
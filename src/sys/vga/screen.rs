@@ -11,6 +11,8 @@ enum ModeName {
     T80x25,
     G320x200x256,
     G640x480x16,
+    G800x600x32,
+    G1024x768x32,
 }
 
 const BUFFER: [u8; 640 * 480] = [0; 640 * 480];
@@ -78,7 +80,11 @@ fn set_mode(mode: ModeName) {
         ModeName::T80x25 => T_80_25,
         ModeName::G320x200x256 => G_320_200_256,
         ModeName::G640x480x16 => G_640_480_16,
+        _ => return, // VBE modes are set with `vbe::set_mode`
     }.to_vec();
+    if vbe::is_enabled() {
+        vbe::disable();
+    }
 
     interrupts::without_interrupts(|| {
         let mut misc_write: Port<u8> = Port::new(MISC_WRITE_REG);
@@ -156,6 +162,7 @@ fn set_80x25_mode() {
     disable_underline();
     palette::restore_palette();
     font::restore_font();
+    writer::redraw();
 }
 
 fn set_320x200_mode() {
@@ -174,6 +181,21 @@ fn set_640x480_mode() {
     clear_screen();
 }
 
+// The text console is drawn with the font in the linear framebuffer
+fn set_vbe_mode(mode: ModeName, width: usize, height: usize) -> bool {
+    if !vbe::is_available() {
+        return false;
+    }
+    if is_80x25_mode() {
+        palette::backup_palette();
+    }
+    *MODE.lock() = Some(mode);
+    let palette = palette::saved_palette();
+    vbe::set_mode(width, height, font::current_font(), &palette);
+    writer::redraw();
+    true
+}
+
 fn clear_screen() {
     // Clear screen
     let size = match *MODE.lock() {
@@ -208,6 +230,8 @@ impl FileIO for VgaMode {
             Some(ModeName::T80x25) | None => write_mode(buf, b"80x25"),
             Some(ModeName::G320x200x256) => write_mode(buf, b"320x200"),
             Some(ModeName::G640x480x16) => write_mode(buf, b"640x480"),
+            Some(ModeName::G800x600x32) => write_mode(buf, b"800x600"),
+            Some(ModeName::G1024x768x32) => write_mode(buf, b"1024x768"),
         }
     }
 
@@ -216,6 +240,16 @@ impl FileIO for VgaMode {
             b"80x25" => set_80x25_mode(),
             b"320x200" => set_320x200_mode(),
             b"640x480" => set_640x480_mode(),
+            b"800x600" => {
+                if !set_vbe_mode(ModeName::G800x600x32, 800, 600) {
+                    return Err(());
+                }
+            }
+            b"1024x768" => {
+                if !set_vbe_mode(ModeName::G1024x768x32, 1024, 768) {
+                    return Err(());
+                }
+            }
             _ => return Err(()),
         }
         Ok(buf.len())
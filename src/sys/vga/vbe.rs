@@ -0,0 +1,194 @@
+use super::*;
+
+use crate::api::font::Font;
+use crate::sys;
+
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use spin::Mutex;
+use x86_64::PhysAddr;
+
+// Bochs VBE extensions available with `qemu -vga std`
+// Source: https://wiki.osdev.org/Bochs_VBE_Extensions
+const VENDOR_ID: u16 = 0x1234;
+const DEVICE_ID: u16 = 0x1111;
+
+const INDEX_PORT: u16 = 0x01CE;
+const DATA_PORT:  u16 = 0x01CF;
+
+const REG_ID:     u16 = 0x0;
+const REG_XRES:   u16 = 0x1;
+const REG_YRES:   u16 = 0x2;
+const REG_BPP:    u16 = 0x3;
+const REG_ENABLE: u16 = 0x4;
+
+const DISABLED:    u16 = 0x00;
+const ENABLED:     u16 = 0x01;
+const LFB_ENABLED: u16 = 0x40;
+
+const MIN_ID: u16 = 0xB0C0;
+const BPP: usize = 32;
+
+// Physical address of the linear framebuffer or 0 without the device
+static LFB_ADDR: AtomicU64 = AtomicU64::new(0);
+static IS_ENABLED: AtomicBool = AtomicBool::new(false);
+static FRAMEBUFFER: Mutex<Option<Framebuffer>> = Mutex::new(None);
+
+struct Framebuffer {
+    addr: u64,
+    width: usize,
+    height: usize,
+    font: Font,
+    colors: [u32; 16],
+}
+
+impl Framebuffer {
+    fn pixels(&mut self) -> &mut [u32] {
+        let ptr = self.addr as *mut u32;
+        let len = self.width * self.height;
+        unsafe { core::slice::from_raw_parts_mut(ptr, len) }
+    }
+}
+
+fn write_reg(index: u16, value: u16) {
+    let mut addr: Port<u16> = Port::new(INDEX_PORT);
+    let mut data: Port<u16> = Port::new(DATA_PORT);
+    unsafe {
+        addr.write(index);
+        data.write(value);
+    }
+}
+
+fn read_reg(index: u16) -> u16 {
+    let mut addr: Port<u16> = Port::new(INDEX_PORT);
+    let mut data: Port<u16> = Port::new(DATA_PORT);
+    unsafe {
+        addr.write(index);
+        data.read()
+    }
+}
+
+pub fn init() {
+    if let Some(dev) = sys::pci::find_device(VENDOR_ID, DEVICE_ID) {
+        if read_reg(REG_ID) >= MIN_ID {
+            let addr = dev.mem_base().as_u64();
+            LFB_ADDR.store(addr, Ordering::SeqCst);
+            log!("VGA VBE {:#X}", addr);
+        }
+    }
+}
+
+pub fn is_available() -> bool {
+    LFB_ADDR.load(Ordering::SeqCst) != 0
+}
+
+pub fn is_enabled() -> bool {
+    IS_ENABLED.load(Ordering::SeqCst)
+}
+
+// Set a 32 bits mode and map the 16 colors of the text console to the
+// given palette
+pub fn set_mode(width: usize, height: usize, font: Font, palette: &Palette) {
+    let phys = PhysAddr::new(LFB_ADDR.load(Ordering::SeqCst));
+    let addr = sys::mem::phys_to_virt(phys).as_u64();
+    let mut colors = [0; 16];
+    for (i, color) in colors.iter_mut().enumerate() {
+        let (r, g, b) = palette.colors[Color::from_index(i).register()];
+        *color = (r as u32) << 16 | (g as u32) << 8 | (b as u32);
+    }
+    interrupts::without_interrupts(|| {
+        write_reg(REG_ENABLE, DISABLED);
+        write_reg(REG_XRES, width as u16);
+        write_reg(REG_YRES, height as u16);
+        write_reg(REG_BPP, BPP as u16);
+        write_reg(REG_ENABLE, ENABLED | LFB_ENABLED);
+
+        let mut fb = Framebuffer { addr, width, height, font, colors };
+        fb.pixels().fill(0);
+        *FRAMEBUFFER.lock() = Some(fb);
+        IS_ENABLED.store(true, Ordering::SeqCst);
+    });
+}
+
+pub fn disable() {
+    interrupts::without_interrupts(|| {
+        write_reg(REG_ENABLE, DISABLED);
+        *FRAMEBUFFER.lock() = None;
+        IS_ENABLED.store(false, Ordering::SeqCst);
+    });
+}
+
+pub fn set_font(font: Font) {
+    interrupts::without_interrupts(|| {
+        if let Some(fb) = FRAMEBUFFER.lock().as_mut() {
+            fb.font = font;
+        }
+    });
+}
+
+// Copy raw pixels in the BGRX format to the framebuffer
+pub fn write(buf: &[u8]) -> Option<usize> {
+    let (addr, size) = interrupts::without_interrupts(|| {
+        let lock = FRAMEBUFFER.lock();
+        lock.as_ref().map(|fb| (fb.addr, fb.width * fb.height * BPP / 8))
+    })?;
+    let n = buf.len();
+    if n > size {
+        return None;
+    }
+    let dst = addr as *mut u8;
+    unsafe {
+        core::ptr::copy_nonoverlapping(buf.as_ptr(), dst, n);
+    }
+    Some(n)
+}
+
+// Draw a char of the text console with the glyph of the font at the given
+// column and row
+pub fn draw_char(x: usize, y: usize, c: u8, fg: usize, bg: usize) {
+    if let Some(fb) = FRAMEBUFFER.lock().as_mut() {
+        let h = fb.font.height as usize;
+        let (px, py) = (x * 8, y * h);
+        if px + 8 > fb.width || py + h > fb.height {
+            return;
+        }
+        let code = if (c as u16) < fb.font.size { c as usize } else { 0 };
+        let (fg, bg) = (fb.colors[fg & 0xF], fb.colors[bg & 0xF]);
+        let ptr = fb.addr as *mut u32;
+        let glyph = &fb.font.data[code * h..(code + 1) * h];
+        for (dy, bits) in glyph.iter().enumerate() {
+            let i = (py + dy) * fb.width + px;
+            for dx in 0..8 {
+                let color = if bits & (0x80 >> dx) != 0 { fg } else { bg };
+                unsafe { ptr.add(i + dx).write_volatile(color) }
+            }
+        }
+    }
+}
+
+// Draw the cursor of the text console as an underline
+pub fn draw_cursor(x: usize, y: usize, fg: usize) {
+    if let Some(fb) = FRAMEBUFFER.lock().as_mut() {
+        let h = fb.font.height as usize;
+        let (px, py) = (x * 8, y * h);
+        if px + 8 > fb.width || py + h > fb.height {
+            return;
+        }
+        let color = fb.colors[fg & 0xF];
+        let width = fb.width;
+        let pixels = fb.pixels();
+        for dy in (h - 2)..h {
+            let i = (py + dy) * width + px;
+            pixels[i..i + 8].fill(color);
+        }
+    }
+}
+
+// Move the rows of the text console up by one
+pub fn shift_up(rows: usize) {
+    if let Some(fb) = FRAMEBUFFER.lock().as_mut() {
+        let h = fb.font.height as usize;
+        let n = cmp::min(rows * h, fb.height);
+        let width = fb.width;
+        fb.pixels().copy_within(h * width..n * width, 0);
+    }
+}
@@ -4,10 +4,11 @@ use buffer::Buffer;
 
 use crate::api::font::Font;
 use crate::sys;
+use crate::sys::console::MAX_CONSOLES;
 
 //use core::fmt::Write;
 use lazy_static::lazy_static;
-use spin::Mutex;
+use spin::{Mutex, MutexGuard};
 use vte::{Params, Parser, Perform};
 
 const FG: Color = Color::DarkWhite;
@@ -19,7 +20,7 @@ const UNPRINTABLE: u8 = 0x00; // Unprintable chars will be replaced by this one
 struct ColorCode(u8);
 
 impl ColorCode {
-    fn new(foreground: Color, background: Color) -> ColorCode {
+    const fn new(foreground: Color, background: Color) -> ColorCode {
         ColorCode((background as u8) << 4 | (foreground as u8))
     }
 }
@@ -31,78 +32,230 @@ struct ScreenChar {
     color_code: ColorCode,
 }
 
-impl ScreenChar {
-    fn new() -> Self {
-        Self {
-            ascii_code: b' ',
-            color_code: ColorCode::new(FG, BG),
-        }
-    }
-}
-
 const SCREEN_WIDTH: usize = 80;
-const SCREEN_HEIGHT: usize = 25;
-const SCROLL_HEIGHT: usize = 250;
+pub const SCREEN_HEIGHT: usize = 25;
+
+// Number of lines kept in the scrollback history of each console
+const SCROLLBACK: usize = 1000;
+
+// Lines that have not been written yet are zeroed
+const EMPTY_CHAR: ScreenChar = ScreenChar {
+    ascii_code: 0,
+    color_code: ColorCode(0),
+};
+
+type ScrollBuffer = [[ScreenChar; SCREEN_WIDTH]; SCROLLBACK];
 
 #[repr(transparent)]
 struct ScreenBuffer {
     chars: [[ScreenChar; SCREEN_WIDTH]; SCREEN_HEIGHT],
 }
 
+// The scroll buffers are kept outside of the writers to be entirely zeroed
+// and placed in the BSS section instead of the kernel image.
+static SCROLL_BUFFERS: [Mutex<ScrollBuffer>; MAX_CONSOLES] = [
+    const { Mutex::new([[EMPTY_CHAR; SCREEN_WIDTH]; SCROLLBACK]) };
+    MAX_CONSOLES
+];
+
+// Each virtual console has its own writer but only the active one is drawn
+// on the screen, the others are only writing to their scroll buffer.
+pub static WRITERS: [Mutex<Writer>; MAX_CONSOLES] = [
+    Mutex::new(Writer::new(0)),
+    Mutex::new(Writer::new(1)),
+    Mutex::new(Writer::new(2)),
+    Mutex::new(Writer::new(3)),
+];
+
 lazy_static! {
-    pub static ref PARSER: Mutex<Parser> = Mutex::new(Parser::new());
-    pub static ref WRITER: Mutex<Writer> = Mutex::new(Writer {
-        cursor: [0; 2],
-        writer: [0; 2],
-        color_code: ColorCode::new(FG, BG),
-        screen_buffer: unsafe { &mut *(0xB8000 as *mut ScreenBuffer) },
-        scroll_buffer: [[ScreenChar::new(); SCREEN_WIDTH]; SCROLL_HEIGHT],
-        scroll_reader: 0,
-        scroll_bottom: SCREEN_HEIGHT,
-    });
+    pub static ref PARSERS: [Mutex<Parser>; MAX_CONSOLES] = [
+        Mutex::new(Parser::new()),
+        Mutex::new(Parser::new()),
+        Mutex::new(Parser::new()),
+        Mutex::new(Parser::new()),
+    ];
+}
+
+fn screen_buffer() -> &'static mut ScreenBuffer {
+    unsafe { &mut *(0xB8000 as *mut ScreenBuffer) }
+}
+
+// Draw a char in the text buffer or with the font in the framebuffer of a
+// VBE mode
+fn draw_char(x: usize, y: usize, c: ScreenChar) {
+    if vbe::is_enabled() {
+        let fg = (c.color_code.0 & 0xF) as usize;
+        let bg = (c.color_code.0 >> 4) as usize;
+        vbe::draw_char(x, y, c.ascii_code, fg, bg);
+    } else {
+        let ptr = &mut screen_buffer().chars[y][x];
+        unsafe { core::ptr::write_volatile(ptr, c); }
+    }
+}
+
+fn pointer_char(c: ScreenChar) -> ScreenChar {
+    let code = c.color_code.0;
+    ScreenChar {
+        ascii_code: c.ascii_code,
+        color_code: ColorCode(code.rotate_left(4)),
+    }
+}
+
+fn shift_screen_up() {
+    if vbe::is_enabled() {
+        vbe::shift_up(SCREEN_HEIGHT);
+    } else {
+        let screen = screen_buffer();
+        for y in 1..SCREEN_HEIGHT {
+            screen.chars[y - 1] = screen.chars[y];
+        }
+    }
+}
+
+// Draw the active console again after a change of mode
+pub fn redraw() {
+    let vt = sys::console::active();
+    interrupts::without_interrupts(|| WRITERS[vt].lock().scroll());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Style {
+    fg: Color,
+    bg: Color,
+    is_bold: bool,
+    is_reverse: bool,
+}
+
+impl Style {
+    const fn new() -> Self {
+        Self { fg: FG, bg: BG, is_bold: false, is_reverse: false }
+    }
+
+    // There is no bold font in text mode so the bright colors are used
+    fn color_code(&self) -> ColorCode {
+        let fg = if self.is_bold {
+            Color::from_index((self.fg as usize) | 8)
+        } else {
+            self.fg
+        };
+        if self.is_reverse {
+            ColorCode::new(self.bg, fg)
+        } else {
+            ColorCode::new(fg, self.bg)
+        }
+    }
 }
 
 pub struct Writer {
+    vt: usize,
+    is_active: bool,
     cursor: [usize; 2], // x, y
     writer: [usize; 2], // x, y
+    region: [usize; 2], // top, bottom
+    saved: ([usize; 2], Style),
+    pointer: Option<[usize; 2]>, // x, y
+    style: Style,
     color_code: ColorCode,
-    screen_buffer: &'static mut ScreenBuffer,
-    scroll_buffer: [[ScreenChar; SCREEN_WIDTH]; SCROLL_HEIGHT],
     scroll_reader: usize, // Top of the screen
     scroll_bottom: usize, // Bottom of the buffer
 }
 
 // Scroll Buffer
-// +----------------------------+
-// | line 01                    |
-// | line 02                    |
-// | line 03                    |
-// | line 04                    |
-// +----------------------------+
-// | line 05                    | <-- scroll_reader
-// | line 06                    |
-// | line 07                    |
-// | line 08                    |
+//
+// The lines are numbered since the last clear of the screen and stored in a
+// ring buffer at the index `line % SCROLLBACK` so that the oldest lines are
+// overwritten when the buffer is full.
+//
 // +----------------------------+
 // | line 09                    |
 // | line 10                    |
 // | line 11                    |
 // | line 12                    | <-- scroll_bottom
-// |                            |
-// |                            |
-// |                            |
-// |                            |
+// | line 05                    | <-- oldest line
+// | line 06                    |
+// +----------------------------+
+// | line 07                    | <-- scroll_reader
+// | line 08                    |
+// | line 09                    |
+// | line 10                    |
 // +----------------------------+
 //
 // Screen Buffer
 // +----------------------------+
-// | line 05                    |
-// | line 06                    |
 // | line 07                    |
 // | line 08                    |
+// | line 09                    |
+// | line 10                    |
 // +----------------------------+
 
 impl Writer {
+    const fn new(vt: usize) -> Self {
+        Self {
+            vt,
+            is_active: vt == 0,
+            cursor: [0; 2],
+            writer: [0; 2],
+            region: [0, SCREEN_HEIGHT - 1],
+            saved: ([0; 2], Style::new()),
+            pointer: None,
+            style: Style::new(),
+            color_code: ColorCode::new(FG, BG),
+            scroll_reader: 0,
+            scroll_bottom: SCREEN_HEIGHT,
+        }
+    }
+
+    fn scroll_buffer(&self) -> MutexGuard<'static, ScrollBuffer> {
+        SCROLL_BUFFERS[self.vt].lock()
+    }
+
+    // Return the index in the scroll buffer of a row of the current screen
+    fn scroll_index(&self, y: usize) -> usize {
+        (self.scroll_bottom - SCREEN_HEIGHT + y) % SCROLLBACK
+    }
+
+    fn is_drawn(&self) -> bool {
+        self.is_active && !self.is_scrolling()
+    }
+
+    fn write_screen_char(&mut self, x: usize, y: usize, c: ScreenChar) {
+        let i = self.scroll_index(y);
+        self.scroll_buffer()[i][x] = c;
+        if self.is_drawn() {
+            self.draw_screen_char(x, y, c);
+        }
+    }
+
+    fn draw_screen_char(&self, x: usize, y: usize, c: ScreenChar) {
+        if self.pointer == Some([x, y]) {
+            draw_char(x, y, pointer_char(c));
+        } else {
+            draw_char(x, y, c);
+        }
+    }
+
+    // The pointer of the mouse is drawn by swapping the colors of the char
+    // under it
+    pub fn set_pointer(&mut self, x: usize, y: usize) {
+        if x >= SCREEN_WIDTH || y >= SCREEN_HEIGHT {
+            return;
+        }
+        let old = self.pointer.replace([x, y]);
+        if self.is_active {
+            if let Some([x, y]) = old {
+                self.draw_row_char(x, y);
+            }
+            self.draw_row_char(x, y);
+        }
+    }
+
+    // Draw a char of the screen from the scroll buffer
+    fn draw_row_char(&self, x: usize, y: usize) {
+        let i = (y + self.scroll_reader) % SCROLLBACK;
+        let c = self.scroll_buffer()[i][x];
+        self.draw_screen_char(x, y, c);
+    }
+
     fn writer_position(&self) -> (usize, usize) {
         (self.writer[0], self.writer[1])
     }
@@ -116,11 +269,29 @@ impl Writer {
     }
 
     fn set_cursor_position(&mut self, x: usize, y: usize) {
+        if vbe::is_enabled() && self.is_drawn() {
+            // Erase the cursor drawn over the char
+            let [x, y] = self.cursor;
+            if x < SCREEN_WIDTH {
+                self.draw_row_char(x, y);
+            }
+        }
         self.cursor = [x, y];
         self.write_cursor();
     }
 
     fn write_cursor(&mut self) {
+        if !self.is_active {
+            return;
+        }
+        if vbe::is_enabled() {
+            let [x, y] = self.cursor;
+            if x < SCREEN_WIDTH && !self.is_scrolling() {
+                let fg = (self.color_code.0 & 0xF) as usize;
+                vbe::draw_cursor(x, y, fg);
+            }
+            return;
+        }
         let pos = self.cursor[0] + self.cursor[1] * SCREEN_WIDTH;
         let mut addr = Port::new(CRTC_ADDR_REG);
         let mut data = Port::new(CRTC_DATA_REG);
@@ -134,6 +305,9 @@ impl Writer {
 
     // Source: http://www.osdever.net/FreeVGA/vga/crtcreg.htm#0A
     fn disable_cursor(&self) {
+        if !self.is_active {
+            return;
+        }
         let mut addr = Port::new(CRTC_ADDR_REG);
         let mut data = Port::new(CRTC_DATA_REG);
         unsafe {
@@ -143,6 +317,9 @@ impl Writer {
     }
 
     fn enable_cursor(&self) {
+        if !self.is_active {
+            return;
+        }
         let mut addr: Port<u8> = Port::new(CRTC_ADDR_REG);
         let mut data: Port<u8> = Port::new(CRTC_DATA_REG);
         let cursor_start = 13; // Starting row
@@ -159,26 +336,24 @@ impl Writer {
     }
 
     fn disable_echo(&self) {
-        sys::console::disable_echo();
+        sys::console::disable_echo(self.vt);
     }
 
     fn enable_echo(&self) {
-        sys::console::enable_echo();
+        sys::console::enable_echo(self.vt);
     }
 
     fn write_byte(&mut self, byte: u8) {
-        if self.is_scrolling() {
-            // Scroll to the current screen
-            self.scroll_reader = self.scroll_bottom - SCREEN_HEIGHT;
-            self.scroll();
-        }
+        self.scroll_to_bottom();
 
         match byte {
             0x0A => {
                 // Newline
                 self.new_line();
             }
-            0x0D => { // Carriage Return
+            0x0D => {
+                // Carriage Return
+                self.writer[0] = 0;
             }
             0x08 => {
                 // Backspace
@@ -190,56 +365,64 @@ impl Writer {
                     };
                     let x = self.writer[0];
                     let y = self.writer[1];
-                    let ptr = &mut self.screen_buffer.chars[y][x];
-                    unsafe { core::ptr::write_volatile(ptr, c); }
-
-                    let dy = self.scroll_reader;
-                    self.scroll_buffer[y + dy][x] = c;
+                    self.write_screen_char(x, y, c);
                 }
             }
             byte => {
-                if self.writer[0] >= SCREEN_WIDTH {
-                    self.new_line();
-                }
-
-                let x = self.writer[0];
-                let y = self.writer[1];
-                let ascii_code = if is_printable(byte) {
-                    byte
+                if is_printable(byte) {
+                    self.write_glyph(byte);
                 } else {
-                    UNPRINTABLE
-                };
-                let color_code = self.color_code;
-                let c = ScreenChar {
-                    ascii_code,
-                    color_code,
-                };
-                let ptr = &mut self.screen_buffer.chars[y][x];
-                unsafe { core::ptr::write_volatile(ptr, c); }
-                self.writer[0] += 1;
-
-                let dy = self.scroll_reader;
-                self.scroll_buffer[y + dy][x] = c;
+                    self.write_glyph(UNPRINTABLE);
+                }
             }
         }
     }
 
+    fn write_glyph(&mut self, ascii_code: u8) {
+        self.scroll_to_bottom();
+        if self.writer[0] >= SCREEN_WIDTH {
+            self.new_line();
+        }
+
+        let x = self.writer[0];
+        let y = self.writer[1];
+        let color_code = self.color_code;
+        let c = ScreenChar {
+            ascii_code,
+            color_code,
+        };
+        self.write_screen_char(x, y, c);
+        self.writer[0] += 1;
+    }
+
+    fn scroll_to_bottom(&mut self) {
+        if self.is_scrolling() {
+            // Scroll to the current screen
+            self.scroll_reader = self.scroll_bottom - SCREEN_HEIGHT;
+            self.scroll();
+        }
+    }
+
     fn new_line(&mut self) {
-        if self.writer[1] < SCREEN_HEIGHT - 1 {
+        let [top, bottom] = self.region;
+        let is_region = top > 0 || bottom < SCREEN_HEIGHT - 1;
+        if is_region && self.writer[1] == bottom {
+            // Only the lines of the scrolling region are moved
+            self.delete_lines(top, 1);
+        } else if self.writer[1] < SCREEN_HEIGHT - 1 {
             self.writer[1] += 1;
         } else {
-            for y in 1..SCREEN_HEIGHT {
-                self.screen_buffer.chars[y - 1] = self.screen_buffer.chars[y];
-            }
-            if self.scroll_bottom == SCROLL_HEIGHT - 1 {
-                for y in 1..SCROLL_HEIGHT {
-                    self.scroll_buffer[y - 1] = self.scroll_buffer[y];
-                }
-            } else {
-                self.scroll_reader += 1;
-                self.scroll_bottom += 1;
+            if self.is_drawn() {
+                shift_screen_up();
             }
+            self.scroll_reader += 1;
+            self.scroll_bottom += 1;
             self.clear_row_after(0, SCREEN_HEIGHT - 1);
+            if let Some([x, y]) = self.pointer.filter(|_| self.is_drawn()) {
+                // The pointer has been shifted up with the screen
+                self.draw_row_char(x, y.saturating_sub(1));
+                self.draw_row_char(x, y);
+            }
         }
         self.writer[0] = 0;
     }
@@ -249,87 +432,112 @@ impl Writer {
             ascii_code: b' ',
             color_code: self.color_code,
         };
-        self.screen_buffer.chars[y][x..SCREEN_WIDTH].fill(c);
+        if self.is_drawn() {
+            for x in x..SCREEN_WIDTH {
+                self.draw_screen_char(x, y, c);
+            }
+        }
 
-        let dy = self.scroll_reader;
-        self.scroll_buffer[y + dy][x..SCREEN_WIDTH].fill(c);
+        let i = self.scroll_index(y);
+        self.scroll_buffer()[i][x..SCREEN_WIDTH].fill(c);
     }
 
-    pub fn clear_screen(&mut self) {
-        self.scroll_reader = 0;
-        self.scroll_bottom = SCREEN_HEIGHT;
-        for y in 0..SCREEN_HEIGHT {
-            self.clear_row_after(0, y);
+    fn clear_row_before(&mut self, x: usize, y: usize) {
+        let c = ScreenChar {
+            ascii_code: b' ',
+            color_code: self.color_code,
+        };
+        let x = cmp::min(x + 1, SCREEN_WIDTH);
+        if self.is_drawn() {
+            for x in 0..x {
+                self.draw_screen_char(x, y, c);
+            }
         }
-    }
 
-    fn set_color(&mut self, foreground: Color, background: Color) {
-        self.color_code = ColorCode::new(foreground, background);
+        let i = self.scroll_index(y);
+        self.scroll_buffer()[i][0..x].fill(c);
     }
 
-    // Source: https://slideplayer.com/slide/3888880
-    pub fn set_font(&mut self, font: &Font) {
-        let mut sequencer: Port<u16> = Port::new(SEQUENCER_ADDR_REG);
-        let mut graphics: Port<u16> = Port::new(GRAPHICS_ADDR_REG);
-        let buffer = Buffer::addr() as *mut u8;
-
-        unsafe {
-            sequencer.write(0x0100); // do a sync reset
-            sequencer.write(0x0402); // write plane 2 only
-            sequencer.write(0x0704); // sequetial access
-            sequencer.write(0x0300); // end the reset
-            graphics.write(0x0204); // read plane 2 only
-            graphics.write(0x0005); // disable odd/even
-            graphics.write(0x0006); // VRAM at 0xA0000
-
-            for i in 0..font.size as usize {
-                for j in 0..font.height as usize {
-                    let vga_offset = j + i * 32 as usize;
-                    let fnt_offset = j + i * font.height as usize;
-                    let ptr = buffer.add(vga_offset);
-                    ptr.write_volatile(font.data[fnt_offset]);
-                }
+    fn draw_row(&mut self, y: usize) {
+        if self.is_drawn() {
+            let i = self.scroll_index(y);
+            let row = self.scroll_buffer()[i];
+            for (x, c) in row.iter().enumerate() {
+                self.draw_screen_char(x, y, *c);
             }
+        }
+    }
+
+    fn copy_row(&mut self, src: usize, dst: usize) {
+        let i = self.scroll_index(src);
+        let j = self.scroll_index(dst);
+        let mut buffer = self.scroll_buffer();
+        buffer[j] = buffer[i];
+        drop(buffer);
+        self.draw_row(dst);
+    }
 
-            sequencer.write(0x0100); // do a sync reset
-            sequencer.write(0x0302); // write plane 0 & 1
-            sequencer.write(0x0304); // even/odd access
-            sequencer.write(0x0300); // end the reset
-            graphics.write(0x0004); // restore to default
-            graphics.write(0x1005); // resume odd/even
-            graphics.write(0x0E06); // VRAM at 0xB800
+    // Insert blank lines at the given row and move the following lines down
+    // until the bottom of the scrolling region
+    fn insert_lines(&mut self, y: usize, n: usize) {
+        let bottom = self.region[1];
+        let n = cmp::min(n, bottom + 1 - y);
+        for dst in (y + n..=bottom).rev() {
+            self.copy_row(dst - n, dst);
+        }
+        for dst in y..y + n {
+            self.clear_row_after(0, dst);
         }
     }
 
-    pub fn set_palette(&mut self, i: usize, r: u8, g: u8, b: u8) {
-        let mut addr: Port<u8> = Port::new(DAC_ADDR_WRITE_MODE_REG);
-        let mut data: Port<u8> = Port::new(DAC_DATA_REG);
-        unsafe {
-            addr.write(i as u8);
-            data.write(r >> 2); // Convert 8-bit to 6-bit color
-            data.write(g >> 2);
-            data.write(b >> 2);
+    // Delete lines at the given row and move the following lines up until
+    // the bottom of the scrolling region
+    fn delete_lines(&mut self, y: usize, n: usize) {
+        let bottom = self.region[1];
+        let n = cmp::min(n, bottom + 1 - y);
+        for dst in y..=(bottom - n) {
+            self.copy_row(dst + n, dst);
+        }
+        for dst in (bottom + 1 - n)..=bottom {
+            self.clear_row_after(0, dst);
         }
     }
 
-    pub fn palette(&mut self, i: usize) -> (u8, u8, u8) {
-        let mut addr: Port<u8> = Port::new(DAC_ADDR_READ_MODE_REG);
-        let mut data: Port<u8> = Port::new(DAC_DATA_REG);
-        unsafe {
-            addr.write(i as u8);
-            let r = data.read() << 2; // Convert 6-bit to 8-bit color
-            let g = data.read() << 2;
-            let b = data.read() << 2;
-            (r, g, b)
+    fn save_cursor(&mut self) {
+        self.saved = (self.writer, self.style);
+    }
+
+    fn restore_cursor(&mut self) {
+        let ([x, y], style) = self.saved;
+        self.style = style;
+        self.color_code = style.color_code();
+        self.set_writer_position(x, y);
+        self.set_cursor_position(x, y);
+    }
+
+    pub fn clear_screen(&mut self) {
+        self.scroll_reader = 0;
+        self.scroll_bottom = SCREEN_HEIGHT;
+        for y in 0..SCREEN_HEIGHT {
+            self.clear_row_after(0, y);
         }
     }
 
-    fn scroll_up(&mut self, n: usize) {
-        self.scroll_reader = self.scroll_reader.saturating_sub(n);
+    fn set_style(&mut self, style: Style) {
+        self.style = style;
+        self.color_code = style.color_code();
+    }
+
+    pub fn scroll_up(&mut self, n: usize) {
+        let oldest = self.scroll_bottom.saturating_sub(SCROLLBACK);
+        self.scroll_reader = cmp::max(
+            self.scroll_reader.saturating_sub(n),
+            oldest
+        );
         self.scroll();
     }
 
-    fn scroll_down(&mut self, n: usize) {
+    pub fn scroll_down(&mut self, n: usize) {
         self.scroll_reader = cmp::min(
             self.scroll_reader + n,
             self.scroll_bottom - SCREEN_HEIGHT
@@ -338,12 +546,12 @@ impl Writer {
     }
 
     fn scroll(&mut self) {
-        let dy = self.scroll_reader;
+        if !self.is_active {
+            return;
+        }
         for y in 0..SCREEN_HEIGHT {
             for x in 0..SCREEN_WIDTH {
-                let c = self.scroll_buffer[y + dy][x];
-                let ptr = &mut self.screen_buffer.chars[y][x];
-                unsafe { core::ptr::write_volatile(ptr, c); }
+                self.draw_row_char(x, y);
             }
         }
         if self.is_scrolling() {
@@ -357,12 +565,23 @@ impl Writer {
         // If the current screen is reached we are not scrolling anymore
         self.scroll_reader != self.scroll_bottom - SCREEN_HEIGHT
     }
+
+    // Draw the content of the console on the screen when it becomes active
+    pub fn set_active(&mut self, is_active: bool) {
+        self.is_active = is_active;
+        if is_active {
+            self.scroll();
+            self.write_cursor();
+        }
+    }
 }
 
 /// Source: https://vt100.net/emu/dec_ansi_parser
 impl Perform for Writer {
+    // Chars are displayed with the glyph given by the unicode table of the
+    // font, or the glyph of a similar char
     fn print(&mut self, c: char) {
-        self.write_byte(c as u8);
+        self.write_glyph(font::glyph(c).unwrap_or(UNPRINTABLE));
     }
 
     fn execute(&mut self, byte: u8) {
@@ -372,24 +591,26 @@ impl Perform for Writer {
     fn csi_dispatch(&mut self, params: &Params, _: &[u8], _: bool, c: char) {
         match c {
             'm' => {
-                let mut fg = FG;
-                let mut bg = BG;
+                let mut style = self.style;
                 for param in params.iter() {
                     match param[0] {
-                        0 => {
-                            fg = FG;
-                            bg = BG;
-                        }
+                        0 => style = Style::new(),
+                        1 => style.is_bold = true,
+                        7 => style.is_reverse = true,
+                        22 => style.is_bold = false,
+                        27 => style.is_reverse = false,
                         30..=37 | 90..=97 => {
-                            fg = Color::from_ansi(param[0] as u8);
+                            style.fg = Color::from_ansi(param[0] as u8);
                         }
+                        39 => style.fg = FG,
                         40..=47 | 100..=107 => {
-                            bg = Color::from_ansi((param[0] as u8) - 10);
+                            style.bg = Color::from_ansi((param[0] as u8) - 10);
                         }
+                        49 => style.bg = BG,
                         _ => {}
                     }
                 }
-                self.set_color(fg, bg);
+                self.set_style(style);
             }
             'A' => { // Cursor Up
                 let mut n = 1;
@@ -458,13 +679,27 @@ impl Perform for Writer {
                 for param in params.iter() {
                     n = param[0] as usize;
                 }
+                let (x, y) = self.cursor_position();
                 match n {
-                    // TODO: 0 and 1, cursor to beginning or to end of screen
-                    2 => self.clear_screen(),
-                    _ => return,
+                    0 => {
+                        self.clear_row_after(x, y);
+                        for y in (y + 1)..SCREEN_HEIGHT {
+                            self.clear_row_after(0, y);
+                        }
+                    }
+                    1 => {
+                        for y in 0..y {
+                            self.clear_row_after(0, y);
+                        }
+                        self.clear_row_before(x, y);
+                    }
+                    2 => {
+                        self.clear_screen();
+                        self.set_writer_position(0, 0);
+                        self.set_cursor_position(0, 0);
+                    }
+                    _ => {}
                 }
-                self.set_writer_position(0, 0);
-                self.set_cursor_position(0, 0);
             }
             'K' => { // Erase in Line
                 let (x, y) = self.cursor_position();
@@ -474,18 +709,57 @@ impl Perform for Writer {
                 }
                 match n {
                     0 => self.clear_row_after(x, y),
-                    1 => return, // TODO: self.clear_row_before(x, y),
+                    1 => self.clear_row_before(x, y),
                     2 => self.clear_row_after(0, y),
                     _ => return,
                 }
                 self.set_writer_position(x, y);
                 self.set_cursor_position(x, y);
             }
+            'L' | 'M' => { // Insert or Delete Lines
+                let (_, y) = self.cursor_position();
+                let [top, bottom] = self.region;
+                if y < top || y > bottom {
+                    return;
+                }
+                let mut n = 1;
+                for param in params.iter() {
+                    n = cmp::max(param[0] as usize, 1);
+                }
+                if c == 'L' {
+                    self.insert_lines(y, n);
+                } else {
+                    self.delete_lines(y, n);
+                }
+                self.set_writer_position(0, y);
+                self.set_cursor_position(0, y);
+            }
+            'r' => { // Set Scrolling Region
+                let mut top = 1;
+                let mut bottom = SCREEN_HEIGHT;
+                for (i, param) in params.iter().enumerate() {
+                    match i {
+                        0 if param[0] > 0 => top = param[0] as usize,
+                        1 if param[0] > 0 => bottom = param[0] as usize,
+                        _ => {}
+                    }
+                }
+                if top >= bottom || bottom > SCREEN_HEIGHT {
+                    return;
+                }
+                self.region = [top - 1, bottom - 1]; // 1-indexed values
+                self.set_writer_position(0, 0);
+                self.set_cursor_position(0, 0);
+            }
+            's' => self.save_cursor(),
+            'u' => self.restore_cursor(),
             'h' => { // Enable
                 for param in params.iter() {
                     match param[0] {
                         12 => self.enable_echo(),
                         25 => self.enable_cursor(),
+                        1000 => sys::console::enable_mouse(self.vt),
+                        1006 => sys::console::enable_mouse_sgr(self.vt),
                         _ => return,
                     }
                 }
@@ -495,6 +769,8 @@ impl Perform for Writer {
                     match param[0] {
                         12 => self.disable_echo(),
                         25 => self.disable_cursor(),
+                        1000 => sys::console::disable_mouse(self.vt),
+                        1006 => sys::console::disable_mouse_sgr(self.vt),
                         _ => return,
                     }
                 }
@@ -512,6 +788,14 @@ impl Perform for Writer {
         }
     }
 
+    fn esc_dispatch(&mut self, _: &[u8], _: bool, byte: u8) {
+        match byte {
+            b'7' => self.save_cursor(),
+            b'8' => self.restore_cursor(),
+            _ => {}
+        }
+    }
+
     fn osc_dispatch(&mut self, params: &[&[u8]], _: bool) {
         if params.len() == 1 {
             let s = String::from_utf8_lossy(params[0]);
@@ -519,13 +803,13 @@ impl Perform for Writer {
                 Some('P') if s.len() == 8 => {
                     if let Ok((i, r, g, b)) = parse_palette(&s) {
                         let i = Color::from_index(i).register();
-                        self.set_palette(i, r, g, b);
+                        set_palette(i, r, g, b);
                     }
                 }
                 Some('R') if s.len() == 1 => {
                     let palette = Palette::default();
                     for (i, (r, g, b)) in palette.colors.iter().enumerate() {
-                        self.set_palette(i, *r, *g, *b);
+                        set_palette(i, *r, *g, *b);
                     }
                 }
                 _ => {}
@@ -536,7 +820,7 @@ impl Perform for Writer {
 
 impl fmt::Write for Writer {
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        let mut parser = PARSER.lock();
+        let mut parser = PARSERS[self.vt].lock();
         for byte in s.bytes() {
             parser.advance(self, byte);
         }
@@ -546,6 +830,63 @@ impl fmt::Write for Writer {
     }
 }
 
+// Source: https://slideplayer.com/slide/3888880
+pub fn set_font(font: &Font) {
+    let mut sequencer: Port<u16> = Port::new(SEQUENCER_ADDR_REG);
+    let mut graphics: Port<u16> = Port::new(GRAPHICS_ADDR_REG);
+    let buffer = Buffer::addr() as *mut u8;
+
+    unsafe {
+        sequencer.write(0x0100); // do a sync reset
+        sequencer.write(0x0402); // write plane 2 only
+        sequencer.write(0x0704); // sequetial access
+        sequencer.write(0x0300); // end the reset
+        graphics.write(0x0204); // read plane 2 only
+        graphics.write(0x0005); // disable odd/even
+        graphics.write(0x0006); // VRAM at 0xA0000
+
+        for i in 0..font.size as usize {
+            for j in 0..font.height as usize {
+                let vga_offset = j + i * 32 as usize;
+                let fnt_offset = j + i * font.height as usize;
+                let ptr = buffer.add(vga_offset);
+                ptr.write_volatile(font.data[fnt_offset]);
+            }
+        }
+
+        sequencer.write(0x0100); // do a sync reset
+        sequencer.write(0x0302); // write plane 0 & 1
+        sequencer.write(0x0304); // even/odd access
+        sequencer.write(0x0300); // end the reset
+        graphics.write(0x0004); // restore to default
+        graphics.write(0x1005); // resume odd/even
+        graphics.write(0x0E06); // VRAM at 0xB800
+    }
+}
+
+pub fn set_palette(i: usize, r: u8, g: u8, b: u8) {
+    let mut addr: Port<u8> = Port::new(DAC_ADDR_WRITE_MODE_REG);
+    let mut data: Port<u8> = Port::new(DAC_DATA_REG);
+    unsafe {
+        addr.write(i as u8);
+        data.write(r >> 2); // Convert 8-bit to 6-bit color
+        data.write(g >> 2);
+        data.write(b >> 2);
+    }
+}
+
+pub fn palette(i: usize) -> (u8, u8, u8) {
+    let mut addr: Port<u8> = Port::new(DAC_ADDR_READ_MODE_REG);
+    let mut data: Port<u8> = Port::new(DAC_DATA_REG);
+    unsafe {
+        addr.write(i as u8);
+        let r = data.read() << 2; // Convert 6-bit to 8-bit color
+        let g = data.read() << 2;
+        let b = data.read() << 2;
+        (r, g, b)
+    }
+}
+
 fn parse_palette(palette: &str) -> Result<(usize, u8, u8, u8), ParseIntError> {
     debug_assert!(palette.len() == 8);
     debug_assert!(palette.starts_with('P'));
@@ -565,3 +906,62 @@ fn test_parse_palette() {
     assert!(parse_palette("BAAAAAAD").is_ok());
     assert!(parse_palette("GOOOOOOD").is_err());
 }
+
+#[test_case]
+fn test_writer_csi() {
+    // The last console is not on the screen so its writer is only writing to
+    // its scroll buffer during the tests
+    let mut writer = WRITERS[MAX_CONSOLES - 1].lock();
+    let row = |writer: &Writer, y: usize| -> String {
+        let i = writer.scroll_index(y);
+        let s: String = writer.scroll_buffer()[i].iter().
+            map(|c| c.ascii_code as char).collect();
+        s.trim_end().into()
+    };
+
+    writer.write_str("\x1b[2Jabc\ndef\nghi").ok();
+    assert_eq!(row(&writer, 0), "abc");
+    assert_eq!(row(&writer, 2), "ghi");
+
+    writer.write_str("\x1b[2;2H\x1b[1K").ok(); // Erase start of line
+    assert_eq!(row(&writer, 1), "  f");
+    writer.write_str("\x1b[1;2H\x1b[0J").ok(); // Erase end of screen
+    assert_eq!(row(&writer, 0), "a");
+    assert_eq!(row(&writer, 1), "");
+    writer.write_str("\x1b[2Jabc\ndef\x1b[2;2H\x1b[1J").ok();
+    assert_eq!(row(&writer, 0), ""); // Erase start of screen
+    assert_eq!(row(&writer, 1), "  f");
+
+    writer.write_str("\x1b[2J\x1b[3;4H\x1b7\x1b[HA\x1b8B").ok();
+    assert_eq!(row(&writer, 0), "A");
+    assert_eq!(row(&writer, 2), "   B");
+    writer.write_str("\x1b[2;2H\x1b[s\x1b[HC\x1b[uD").ok();
+    assert_eq!(row(&writer, 0), "C");
+    assert_eq!(row(&writer, 1), " D");
+
+    writer.write_str("\x1b[2J1\n2\n3\x1b[2;1H\x1b[L").ok(); // Insert line
+    assert_eq!(row(&writer, 1), "");
+    assert_eq!(row(&writer, 2), "2");
+    assert_eq!(row(&writer, 3), "3");
+    writer.write_str("\x1b[M").ok(); // Delete line
+    assert_eq!(row(&writer, 1), "2");
+    assert_eq!(row(&writer, 2), "3");
+    assert_eq!(row(&writer, 3), "");
+
+    writer.write_str("\x1b[2J\x1b[1;3r1\n2\n3\n4").ok(); // Scrolling region
+    assert_eq!(row(&writer, 0), "2");
+    assert_eq!(row(&writer, 2), "4");
+    assert_eq!(row(&writer, 3), "");
+    writer.write_str("\x1b[r").ok();
+    assert_eq!(writer.region, [0, SCREEN_HEIGHT - 1]);
+
+    writer.write_str("\x1b[2J\x1b[31;1mA\x1b[7mB\x1b[0mC").ok();
+    let i = writer.scroll_index(0);
+    let red = Color::BrightRed;
+    let row = writer.scroll_buffer()[i];
+    assert_eq!(row[0].color_code, ColorCode::new(red, BG));
+    assert_eq!(row[1].color_code, ColorCode::new(BG, red));
+    assert_eq!(row[2].color_code, ColorCode::new(FG, BG));
+
+    writer.write_str("\x1b[2J").ok();
+}
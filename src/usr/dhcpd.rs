@@ -0,0 +1,423 @@
+use crate::api::clock;
+use crate::api::console::Style;
+use crate::api::dns;
+use crate::api::fs;
+use crate::api::fs::FileIO;
+use crate::api::process::ExitCode;
+use crate::sys::console;
+use crate::sys::net::socket::udp::UdpSocket;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::str::FromStr;
+use smoltcp::wire::{DhcpMessageType, DhcpOption, DhcpPacket, DhcpRepr};
+use smoltcp::wire::{DHCP_CLIENT_PORT, DHCP_SERVER_PORT};
+use smoltcp::wire::{EthernetAddress, IpAddress, IpEndpoint};
+use smoltcp::wire::{Ipv4Address, Ipv4Cidr};
+
+// See https://www.rfc-editor.org/rfc/rfc2131
+
+const LEASES: &str = "/var/dhcpd.csv";
+const DEFAULT_LEASE_TIME: u32 = 86400;
+const OFFER_TIME: u64 = 60; // Time to wait for a request after an offer
+const MIN_PACKET_LEN: usize = 300; // Minimum BOOTP message size
+const OPT_DOMAIN_NAME_SERVER: u8 = 6;
+
+#[derive(Clone, Debug, PartialEq)]
+struct Lease {
+    mac: EthernetAddress,
+    ip: Ipv4Address,
+    expires: u64,
+}
+
+impl Lease {
+    fn parse(line: &str) -> Option<Self> {
+        let fields: Vec<_> = line.split(',').map(|s| s.trim()).collect();
+        if fields.len() != 3 {
+            return None;
+        }
+        let mac = EthernetAddress::from_str(fields[0]).ok()?;
+        let ip = Ipv4Address::from_str(fields[1]).ok()?;
+        let expires = fields[2].parse().ok()?;
+        Some(Self { mac, ip, expires })
+    }
+
+    fn to_line(&self) -> String {
+        format!("{},{},{}\n", self.mac, self.ip, self.expires)
+    }
+}
+
+struct Server {
+    cidr: Ipv4Cidr,
+    router: Ipv4Address,
+    dns: Vec<Ipv4Address>,
+    first: Ipv4Address,
+    last: Ipv4Address,
+    lease_time: u32,
+    leases: Vec<Lease>,
+    declined: Vec<(Ipv4Address, u64)>, // Addresses used by unknown hosts
+}
+
+impl Server {
+    fn is_free(&self, mac: EthernetAddress, ip: Ipv4Address, now: u64) -> bool {
+        self.first <= ip && ip <= self.last
+            && ip != self.cidr.address()
+            && ip != self.router
+            && !self.leases.iter().any(|lease| {
+                lease.ip == ip && lease.mac != mac && lease.expires > now
+            })
+            && !self.declined.iter().any(|(addr, expires)| {
+                *addr == ip && *expires > now
+            })
+    }
+
+    // Give back the previous address of the client when possible, or the
+    // requested address, or the first address that has never been leased,
+    // or the first address with an expired lease.
+    fn allocate(
+        &self, mac: EthernetAddress, requested: Option<Ipv4Address>, now: u64
+    ) -> Option<Ipv4Address> {
+        let previous = self.leases.iter().find(|lease| lease.mac == mac);
+        let pool = u32::from(self.first)..=u32::from(self.last);
+        previous.map(|lease| lease.ip).into_iter()
+            .chain(requested)
+            .chain(pool.clone().map(Ipv4Address::from).filter(|ip| {
+                !self.leases.iter().any(|lease| lease.ip == *ip)
+            }))
+            .chain(pool.map(Ipv4Address::from))
+            .find(|ip| self.is_free(mac, *ip, now))
+    }
+
+    fn update(&mut self, mac: EthernetAddress, ip: Ipv4Address, expires: u64) {
+        self.leases.retain(|lease| lease.mac != mac && lease.ip != ip);
+        self.leases.push(Lease { mac, ip, expires });
+    }
+
+    fn decline(&mut self, ip: Ipv4Address, expires: u64, now: u64) {
+        self.leases.retain(|lease| lease.ip != ip);
+        self.declined.retain(|(addr, t)| *addr != ip && *t > now);
+        self.declined.push((ip, expires));
+    }
+
+    // Return the type of the reply to a message and the address given to
+    // the client.
+    fn handle(
+        &mut self, req: &DhcpRepr, now: u64
+    ) -> Option<(DhcpMessageType, Ipv4Address)> {
+        let mac = req.client_hardware_address;
+        match req.message_type {
+            DhcpMessageType::Discover => {
+                let ip = self.allocate(mac, req.requested_ip, now)?;
+                self.update(mac, ip, now + OFFER_TIME);
+                Some((DhcpMessageType::Offer, ip))
+            }
+            DhcpMessageType::Request => {
+                if let Some(id) = req.server_identifier {
+                    if id != self.cidr.address() {
+                        return None; // The client chose another server
+                    }
+                }
+                let ip = req.requested_ip.unwrap_or(req.client_ip);
+                if self.allocate(mac, Some(ip), now) == Some(ip) {
+                    let expires = now + self.lease_time as u64;
+                    self.update(mac, ip, expires);
+                    Some((DhcpMessageType::Ack, ip))
+                } else {
+                    Some((DhcpMessageType::Nak, Ipv4Address::UNSPECIFIED))
+                }
+            }
+            DhcpMessageType::Release => {
+                self.leases.retain(|lease| lease.mac != mac);
+                None
+            }
+            DhcpMessageType::Decline => {
+                // The address is already used by another host on the network
+                let ip = req.requested_ip?;
+                self.decline(ip, now + self.lease_time as u64, now);
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn reply<'a>(
+        &self,
+        req: &DhcpRepr,
+        message_type: DhcpMessageType,
+        ip: Ipv4Address,
+        options: &'a [DhcpOption<'a>]
+    ) -> DhcpRepr<'a> {
+        let is_nak = message_type == DhcpMessageType::Nak;
+        DhcpRepr {
+            message_type,
+            transaction_id: req.transaction_id,
+            secs: 0,
+            client_hardware_address: req.client_hardware_address,
+            client_ip: Ipv4Address::UNSPECIFIED,
+            your_ip: ip,
+            server_ip: Ipv4Address::UNSPECIFIED,
+            router: if is_nak { None } else { Some(self.router) },
+            subnet_mask: if is_nak { None } else { Some(self.cidr.netmask()) },
+            relay_agent_ip: req.relay_agent_ip,
+            broadcast: req.broadcast,
+            requested_ip: None,
+            client_identifier: None,
+            server_identifier: Some(self.cidr.address()),
+            parameter_request_list: None,
+            dns_servers: None,
+            max_size: None,
+            lease_duration: if is_nak { None } else { Some(self.lease_time) },
+            renew_duration: None,
+            rebind_duration: None,
+            additional_options: if is_nak { &[] } else { options },
+        }
+    }
+
+    fn load(&mut self) {
+        if let Ok(contents) = fs::read_to_string(LEASES) {
+            self.leases = contents.lines().filter_map(Lease::parse).collect();
+        }
+    }
+
+    fn save(&self) -> bool {
+        let contents: String = self.leases.iter().map(Lease::to_line).collect();
+        fs::write(LEASES, contents.as_bytes()).is_ok()
+    }
+}
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let csi_color = Style::color("yellow");
+    let csi_reset = Style::reset();
+
+    let cidr = fs::read_to_string("/dev/net/ip").ok().and_then(|s| {
+        s.lines().find_map(|line| Ipv4Cidr::from_str(line.trim()).ok())
+    });
+    let cidr = match cidr {
+        Some(cidr) if !cidr.address().is_unspecified() => cidr,
+        _ => {
+            error!("Could not find the IPv4 address of the server");
+            return Err(ExitCode::Failure);
+        }
+    };
+
+    // Default to a pool of 100 addresses starting at the 100th address of
+    // the subnet with the server as router and the DNS servers it uses.
+    let network = u32::from(cidr.network().address());
+    let broadcast = u32::from(cidr.broadcast().unwrap_or(cidr.address()));
+    let mut first = Ipv4Address::from((network + 100).min(broadcast - 1));
+    let mut last = Ipv4Address::from((network + 199).min(broadcast - 1));
+    let mut router = cidr.address();
+    let mut dns: Vec<_> = dns::servers().into_iter().filter_map(|addr| {
+        match addr {
+            IpAddress::Ipv4(addr) => Some(addr),
+            _ => None,
+        }
+    }).take(3).collect();
+    let mut lease_time = DEFAULT_LEASE_TIME;
+
+    let mut i = 1;
+    let n = args.len();
+    while i < n {
+        match args[i] {
+            "-h" | "--help" => {
+                usage();
+                return Ok(());
+            }
+            "-p" | "--pool" if i + 1 < n => {
+                i += 1;
+                let pool = args[i].split_once('-').and_then(|(a, b)| {
+                    let a = Ipv4Address::from_str(a).ok()?;
+                    let b = Ipv4Address::from_str(b).ok()?;
+                    Some((a, b))
+                });
+                match pool {
+                    Some((a, b)) if a <= b => {
+                        first = a;
+                        last = b;
+                    }
+                    _ => {
+                        error!("Could not parse pool '{}'", args[i]);
+                        return Err(ExitCode::UsageError);
+                    }
+                }
+            }
+            "-r" | "--router" if i + 1 < n => {
+                i += 1;
+                match Ipv4Address::from_str(args[i]) {
+                    Ok(addr) => router = addr,
+                    Err(_) => {
+                        error!("Could not parse address '{}'", args[i]);
+                        return Err(ExitCode::UsageError);
+                    }
+                }
+            }
+            "-d" | "--dns" if i + 1 < n => {
+                i += 1;
+                let addrs: Result<Vec<_>, _> = args[i].split(',').map(|s|
+                    Ipv4Address::from_str(s.trim())
+                ).collect();
+                match addrs {
+                    Ok(addrs) if addrs.len() <= 3 => dns = addrs,
+                    _ => {
+                        error!("Could not parse addresses '{}'", args[i]);
+                        return Err(ExitCode::UsageError);
+                    }
+                }
+            }
+            "-l" | "--lease" if i + 1 < n => {
+                i += 1;
+                match args[i].parse() {
+                    Ok(secs) if secs > 0 => lease_time = secs,
+                    _ => {
+                        error!("Could not parse lease time '{}'", args[i]);
+                        return Err(ExitCode::UsageError);
+                    }
+                }
+            }
+            _ => {
+                usage();
+                return Err(ExitCode::UsageError);
+            }
+        }
+        i += 1;
+    }
+
+    let mut server = Server {
+        cidr, router, dns, first, last, lease_time,
+        leases: Vec::new(),
+        declined: Vec::new(),
+    };
+    server.load();
+
+    let mut socket = UdpSocket::new();
+    if socket.listen(DHCP_SERVER_PORT).is_err() {
+        error!("Could not listen to 0.0.0.0:{}", DHCP_SERVER_PORT);
+        return Err(ExitCode::Failure);
+    }
+    println!(
+        "{}DHCP Server listening on 0.0.0.0:{} with pool {}-{}{}",
+        csi_color, DHCP_SERVER_PORT, first, last, csi_reset
+    );
+
+    let dns: Vec<u8> = server.dns.iter().flat_map(|ip| ip.octets()).collect();
+    let options = [DhcpOption { kind: OPT_DOMAIN_NAME_SERVER, data: &dns }];
+    let options = if dns.is_empty() { &options[0..0] } else { &options[..] };
+
+    let mut buf = vec![0; 1500];
+    loop {
+        if console::end_of_text() || console::end_of_transmission() {
+            println!();
+            socket.close();
+            return Ok(());
+        }
+        let n = match socket.recv_from(&mut buf, 0.1) {
+            Some((n, _)) => n,
+            None => continue,
+        };
+        let packet = DhcpPacket::new_unchecked(&buf[0..n]);
+        let req = match DhcpRepr::parse(&packet) {
+            Ok(req) => req,
+            Err(_) => continue,
+        };
+        let now = clock::epoch_time() as u64;
+        let leases = server.leases.clone();
+        let res = server.handle(&req, now);
+        if server.leases != leases && !server.save() {
+            warning!("Could not write to '{}'", LEASES);
+        }
+        if let Some((message_type, ip)) = res {
+            let rep = server.reply(&req, message_type, ip, options);
+            let mut out = vec![0; rep.buffer_len().max(MIN_PACKET_LEN)];
+            let mut packet = DhcpPacket::new_unchecked(&mut out);
+            if rep.emit(&mut packet).is_err() {
+                continue;
+            }
+
+            // Reply to a bound client directly, and broadcast otherwise
+            let is_bound = !req.client_ip.is_unspecified();
+            let dst = if is_bound && message_type != DhcpMessageType::Nak {
+                req.client_ip
+            } else {
+                Ipv4Address::BROADCAST
+            };
+            let endpoint = IpEndpoint::new(dst.into(), DHCP_CLIENT_PORT);
+            socket.send_to(&out, endpoint);
+            println!(
+                "{}{}{} {:?} {}",
+                csi_color, req.client_hardware_address, csi_reset,
+                message_type, ip
+            );
+        }
+    }
+}
+
+fn usage() {
+    let csi_option = Style::color("aqua");
+    let csi_title = Style::color("yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} dhcpd {}<options>{1}",
+        csi_title, csi_reset, csi_option
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-p{1}, {0}--pool <first>-<last>{1}    Lease addresses from pool",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-r{1}, {0}--router <address>{1}       Set router option",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-d{1}, {0}--dns <addresses>{1}        Set DNS servers option",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-l{1}, {0}--lease <seconds>{1}        Set lease time",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_dhcpd_leases() {
+    let cidr = Ipv4Cidr::new(Ipv4Address::new(10, 0, 2, 15), 24);
+    let mut server = Server {
+        cidr,
+        router: Ipv4Address::new(10, 0, 2, 2),
+        dns: Vec::new(),
+        first: Ipv4Address::new(10, 0, 2, 14),
+        last: Ipv4Address::new(10, 0, 2, 16),
+        lease_time: 3600,
+        leases: Vec::new(),
+        declined: Vec::new(),
+    };
+    let a = EthernetAddress([0x52, 0x54, 0, 0x12, 0x34, 0x01]);
+    let b = EthernetAddress([0x52, 0x54, 0, 0x12, 0x34, 0x02]);
+    let c = EthernetAddress([0x52, 0x54, 0, 0x12, 0x34, 0x03]);
+    let ip14 = Ipv4Address::new(10, 0, 2, 14);
+    let ip16 = Ipv4Address::new(10, 0, 2, 16);
+
+    // The address of the server is not in the pool
+    assert_eq!(server.allocate(a, None, 0), Some(ip14));
+    server.update(a, ip14, 100);
+    assert_eq!(server.allocate(a, Some(ip16), 0), Some(ip14));
+    assert_eq!(server.allocate(b, Some(ip14), 0), Some(ip16));
+    server.update(b, ip16, 100);
+    assert_eq!(server.allocate(c, None, 0), None);
+    assert_eq!(server.allocate(c, None, 200), Some(ip14));
+
+    // Each declined address is kept until it expires
+    server.decline(ip14, 300, 200);
+    server.decline(ip16, 300, 200);
+    assert_eq!(server.allocate(c, None, 200), None);
+    assert_eq!(server.allocate(c, None, 300), Some(ip14));
+
+    let lease = Lease { mac: a, ip: ip14, expires: 100 };
+    let line = lease.to_line();
+    assert_eq!(line, "52-54-00-12-34-01,10.0.2.14,100\n");
+    assert_eq!(Lease::parse(line.trim()), Some(lease));
+    assert_eq!(Lease::parse("52-54-00-12-34-01,10.0.2.14"), None);
+}
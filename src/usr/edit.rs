@@ -298,6 +298,7 @@ impl Editor {
         self.print_editing_status();
         self.print_highlighted();
         print!("\x1b[1;1H"); // Move cursor to the top of the screen
+        print!("\x1b[?1000h\x1b[?1006h"); // Enable mouse reporting
 
         let mut escape = false;
         let mut csi = false;
@@ -322,6 +323,7 @@ impl Editor {
                     continue;
                 }
                 '\x11' | '\x03' => { // Ctrl Q or Ctrl C
+                    print!("\x1b[?1006l\x1b[?1000l"); // Disable mouse
                     print!("\x1b[2J\x1b[1;1H"); // Clear screen and move to top
                     print!("\x1b[?25h"); // Enable cursor
                     break;
@@ -333,6 +335,7 @@ impl Editor {
                 }
                 '\x18' => { // Ctrl X
                     let res = self.save(&self.pathname.clone());
+                    print!("\x1b[?1006l\x1b[?1000l"); // Disable mouse
                     print!("\x1b[2J\x1b[1;1H"); // Clear screen and move to top
                     print!("\x1b[?25h"); // Enable cursor
                     return res;
@@ -384,6 +387,20 @@ impl Editor {
                         self.cursor.x -= 1;
                     }
                 }
+                'M' if csi => { // Mouse button press
+                    let event = console::parse_mouse_event(&csi_params);
+                    if let Some((0, x, y)) = event { // Left button
+                        let n = self.lines.len() - self.offset.y;
+                        if y < cmp::min(rows(), n) {
+                            self.cursor.x = x;
+                            self.cursor.y = y;
+                            self.align_cursor();
+                        }
+                    }
+                }
+                'm' if csi => { // Mouse button release
+                    // Do nothing
+                }
                 'Z' if csi => { // Backtab (Shift + Tab)
                      // Do nothing
                 }
@@ -847,6 +864,7 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
         return Err(ExitCode::UsageError);
     }
 
+    api::console::update_size();
     let mut editor = Editor::new(path);
 
     if !cmd.is_empty() {
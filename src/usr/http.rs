@@ -1,49 +1,535 @@
+use crate::api::clock;
 use crate::api::console::Style;
+use crate::api::dns;
+use crate::api::fs;
 use crate::api::process::ExitCode;
 use crate::api::syscall;
+use crate::api::tls;
 use crate::sys::console;
 use crate::sys::fs::OpenFlag;
-use crate::usr;
 
+use alloc::collections::btree_map::BTreeMap;
 use alloc::format;
 use alloc::string::{String, ToString};
 use alloc::vec;
+use alloc::vec::Vec;
 use core::str::{self, FromStr};
 use smoltcp::wire::IpAddress;
 
-#[derive(Debug)]
+const MAX_REDIRECTS: usize = 5;
+const MAX_LINE_LEN: usize = 8192;
+
+#[derive(Debug, Clone)]
 struct URL {
+    pub is_secure: bool,
     pub host: String,
     pub port: u16,
     pub path: String,
 }
 
-enum ResponseState {
-    Headers,
-    Body,
-}
-
 impl URL {
     pub fn parse(url: &str) -> Option<Self> {
-        if !url.starts_with("http://") {
+        let (is_secure, url) = if let Some(url) = url.strip_prefix("http://") {
+            (false, url)
+        } else if let Some(url) = url.strip_prefix("https://") {
+            (true, url)
+        } else {
             return None;
-        }
-        let url = &url[7..];
+        };
+        let default_port = if is_secure { 443 } else { 80 };
         let (server, path) = match url.find('/') {
             Some(i) => url.split_at(i),
             None => (url, "/"),
         };
-        let (host, port) = match server.find(':') {
-            Some(i) => server.split_at(i),
-            None => (server, ":80"),
+        let (host, port) = if let Some(server) = server.strip_prefix('[') {
+            // IPv6 address literal (RFC 2732)
+            server.split_once(']')?
+        } else {
+            match server.find(':') {
+                Some(i) => server.split_at(i),
+                None => (server, ""),
+            }
+        };
+        let port = match port.strip_prefix(':') {
+            Some(port) => port.parse().unwrap_or(default_port),
+            None => default_port,
         };
-        let port = &port[1..];
         Some(Self {
+            is_secure,
             host: host.into(),
-            port: port.parse().unwrap_or(80),
+            port,
             path: path.into(),
         })
     }
+
+    // Return the host as it should appear in a `Host` header
+    pub fn authority(&self) -> String {
+        let host = if self.host.contains(':') {
+            format!("[{}]", self.host)
+        } else {
+            self.host.clone()
+        };
+        if self.port == self.default_port() {
+            host
+        } else {
+            format!("{}:{}", host, self.port)
+        }
+    }
+
+    pub fn default_port(&self) -> u16 {
+        if self.is_secure {
+            443
+        } else {
+            80
+        }
+    }
+
+    // Return the URL pointed to by the `Location` header of a redirection
+    pub fn join(&self, location: &str) -> Option<Self> {
+        if location.contains("://") {
+            return Self::parse(location);
+        }
+        let path = if location.starts_with('/') {
+            location.to_string()
+        } else {
+            let path = self.path.split('?').next().unwrap_or("/");
+            let dir = match path.rfind('/') {
+                Some(i) => &path[..(i + 1)],
+                None => "/",
+            };
+            format!("{}{}", dir, location)
+        };
+        Some(Self {
+            is_secure: self.is_secure,
+            host: self.host.clone(),
+            port: self.port,
+            path,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ChunkState {
+    Size,
+    Data(usize),
+    DataEnd,
+    Trailer,
+    Done,
+}
+
+// Decoder of the chunked transfer coding (RFC 9112)
+struct ChunkedDecoder {
+    state: ChunkState,
+    line: Vec<u8>,
+}
+
+impl ChunkedDecoder {
+    pub fn new() -> Self {
+        Self {
+            state: ChunkState::Size,
+            line: Vec::new(),
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.state == ChunkState::Done
+    }
+
+    // Decode the chunks of the buffer into `out` and return the number of
+    // bytes consumed, which will be less than the size of the buffer if it
+    // contains data following the last chunk.
+    pub fn decode(
+        &mut self,
+        buf: &[u8],
+        out: &mut Vec<u8>
+    ) -> Result<usize, ()> {
+        let mut i = 0;
+        while i < buf.len() {
+            match self.state {
+                ChunkState::Done => {
+                    break;
+                }
+                ChunkState::Data(n) => {
+                    let m = n.min(buf.len() - i);
+                    out.extend_from_slice(&buf[i..(i + m)]);
+                    i += m;
+                    self.state = if m == n {
+                        ChunkState::DataEnd
+                    } else {
+                        ChunkState::Data(n - m)
+                    };
+                }
+                state => {
+                    let c = buf[i];
+                    i += 1;
+                    if c != b'\n' {
+                        if self.line.len() == MAX_LINE_LEN {
+                            return Err(());
+                        }
+                        self.line.push(c);
+                        continue;
+                    }
+                    let line = String::from_utf8_lossy(&self.line).to_string();
+                    let line = line.trim();
+                    self.state = match state {
+                        ChunkState::Size => {
+                            // Ignore chunk extensions
+                            let size = line.split(';').next().unwrap_or("");
+                            match usize::from_str_radix(size.trim(), 16) {
+                                Ok(0) => ChunkState::Trailer,
+                                Ok(n) => ChunkState::Data(n),
+                                Err(_) => return Err(()),
+                            }
+                        }
+                        ChunkState::DataEnd if line.is_empty() => {
+                            ChunkState::Size
+                        }
+                        ChunkState::Trailer if line.is_empty() => {
+                            ChunkState::Done
+                        }
+                        ChunkState::Trailer => {
+                            ChunkState::Trailer
+                        }
+                        _ => {
+                            return Err(());
+                        }
+                    };
+                    self.line.clear();
+                }
+            }
+        }
+        Ok(i)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum BodyLength {
+    Chunked,
+    Fixed(usize),
+    UntilClose,
+}
+
+struct Response {
+    pub code: usize,
+    pub headers: BTreeMap<String, String>,
+}
+
+impl Response {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(|value| value.as_str())
+    }
+
+    pub fn is_redirect(&self) -> bool {
+        matches!(self.code, 301 | 302 | 303 | 307 | 308)
+    }
+
+    pub fn body_length(&self, method: &str) -> BodyLength {
+        if method == "HEAD" || self.code == 204 || self.code == 304 {
+            return BodyLength::Fixed(0);
+        }
+        if let Some(encoding) = self.header("transfer-encoding") {
+            if encoding.to_lowercase().contains("chunked") {
+                return BodyLength::Chunked;
+            }
+        }
+        if let Some(len) = self.header("content-length") {
+            if let Ok(len) = len.parse() {
+                return BodyLength::Fixed(len);
+            }
+        }
+        BodyLength::UntilClose
+    }
+
+    pub fn is_keep_alive(&self) -> bool {
+        match self.header("connection") {
+            Some(value) => !value.eq_ignore_ascii_case("close"),
+            None => true,
+        }
+    }
+}
+
+struct Connection {
+    pub is_secure: bool,
+    pub host: String,
+    pub port: u16,
+    handle: usize,
+    session: Option<tls::Session>,
+    buf_len: usize,
+    buf: Vec<u8>, // Data received but not consumed yet
+    timeout: f64,
+}
+
+impl Connection {
+    pub fn open(
+        url: &URL,
+        timeout: f64,
+        verify: bool
+    ) -> Result<Self, ExitCode> {
+        let addr = if let Ok(ip_addr) = IpAddress::from_str(&url.host) {
+            ip_addr
+        } else {
+            match dns::resolve(&url.host) {
+                Ok(ip_addr) => ip_addr,
+                Err(e) => {
+                    error!("Could not resolve host: {:?}", e);
+                    return Err(ExitCode::Failure);
+                }
+            }
+        };
+
+        let socket_path = "/dev/net/tcp";
+        let buf_len = if let Some(info) = syscall::info(socket_path) {
+            info.size() as usize
+        } else {
+            error!("Could not open '{}'", socket_path);
+            return Err(ExitCode::Failure);
+        };
+
+        let flags = OpenFlag::Device as u8;
+        if let Some(handle) = syscall::open(socket_path, flags) {
+            if syscall::connect(handle, addr, url.port).is_err() {
+                error!("Could not connect to {}:{}", addr, url.port);
+                syscall::close(handle);
+                return Err(ExitCode::Failure);
+            }
+            let session = if url.is_secure {
+                match tls::Session::connect(handle, &url.host, verify) {
+                    Ok(session) => Some(session),
+                    Err(e) => {
+                        error!("TLS handshake failed: {:?}", e);
+                        syscall::close(handle);
+                        return Err(ExitCode::Failure);
+                    }
+                }
+            } else {
+                None
+            };
+            Ok(Self {
+                is_secure: url.is_secure,
+                host: url.host.clone(),
+                port: url.port,
+                handle,
+                session,
+                buf_len,
+                buf: Vec::new(),
+                timeout,
+            })
+        } else {
+            error!("Could not open '{}'", socket_path);
+            Err(ExitCode::Failure)
+        }
+    }
+
+    pub fn is_connected_to(&self, url: &URL) -> bool {
+        self.is_secure == url.is_secure
+            && self.host == url.host
+            && self.port == url.port
+    }
+
+    pub fn close(mut self) {
+        if let Some(session) = &mut self.session {
+            session.close();
+        }
+        syscall::close(self.handle);
+    }
+
+    // Read more data from the socket into the buffer and return false when
+    // the connection has been closed by the server.
+    fn fill(&mut self) -> Result<bool, ExitCode> {
+        let started = clock::epoch_time();
+        let mut data = vec![0; self.buf_len];
+        loop {
+            if console::end_of_text() || console::end_of_transmission() {
+                eprintln!();
+                return Err(ExitCode::Failure);
+            }
+            let res = match &mut self.session {
+                Some(session) => match session.read(&mut data) {
+                    Ok(n) => Some(n),
+                    Err(tls::Error::NetworkError) => None,
+                    Err(e) => {
+                        error!("TLS error: {:?}", e);
+                        return Err(ExitCode::Failure);
+                    }
+                },
+                None => syscall::read(self.handle, &mut data),
+            };
+            match res {
+                Some(0) => {
+                    return Ok(false);
+                }
+                Some(n) => {
+                    self.buf.extend_from_slice(&data[0..n]);
+                    return Ok(true);
+                }
+                None => {
+                    if clock::epoch_time() - started > self.timeout {
+                        error!("Timeout reached");
+                        return Err(ExitCode::Failure);
+                    }
+                    syscall::sleep(0.01);
+                }
+            }
+        }
+    }
+
+    pub fn read_line(&mut self) -> Result<Option<String>, ExitCode> {
+        loop {
+            if let Some(i) = self.buf.iter().position(|&c| c == b'\n') {
+                let line: Vec<u8> = self.buf.drain(0..(i + 1)).collect();
+                let line = String::from_utf8_lossy(&line);
+                return Ok(Some(line.trim_end().to_string()));
+            }
+            if self.buf.len() > MAX_LINE_LEN {
+                error!("Invalid response");
+                return Err(ExitCode::Failure);
+            }
+            if !self.fill()? {
+                return Ok(None);
+            }
+        }
+    }
+
+    // Write the buffer to the socket and return false if the connection has
+    // been closed.
+    fn write_all(&mut self, buf: &[u8]) -> bool {
+        if let Some(session) = &mut self.session {
+            return session.write(buf).is_ok();
+        }
+        let mut i = 0;
+        while i < buf.len() {
+            let j = (i + self.buf_len).min(buf.len());
+            match syscall::write(self.handle, &buf[i..j]) {
+                Some(n) => i += n,
+                None => return false,
+            }
+        }
+        true
+    }
+
+    // Send a request with its optional body and read the head of the
+    // response, or return `None` if the connection has been closed by the
+    // server before the response.
+    pub fn request(
+        &mut self,
+        req: &[u8],
+        data: Option<&[u8]>,
+        is_verbose: bool
+    ) -> Result<Option<Response>, ExitCode> {
+        if !self.write_all(req) {
+            return Ok(None);
+        }
+        if let Some(buf) = data {
+            if !self.write_all(buf) {
+                return Ok(None);
+            }
+        }
+        self.read_response(is_verbose)
+    }
+
+    fn read_response(
+        &mut self,
+        is_verbose: bool
+    ) -> Result<Option<Response>, ExitCode> {
+        let csi_verbose = Style::color("blue");
+        let csi_reset = Style::reset();
+        loop {
+            let mut code = None;
+            let mut headers = BTreeMap::new();
+            let mut is_closed = true;
+            if is_verbose {
+                print!("{}", csi_verbose);
+            }
+            while let Some(line) = self.read_line()? {
+                is_closed = false;
+                if is_verbose {
+                    println!("< {}", line);
+                }
+                if code.is_none() {
+                    code = line.split(' ').nth(1).and_then(|word|
+                        word.parse::<usize>().ok()
+                    );
+                    if code.is_none() {
+                        break;
+                    }
+                } else if line.is_empty() {
+                    break;
+                } else if let Some((name, value)) = line.split_once(':') {
+                    let name = name.trim().to_lowercase();
+                    headers.insert(name, value.trim().to_string());
+                }
+            }
+            if is_verbose {
+                print!("{}", csi_reset);
+            }
+            match code {
+                Some(100..=199) => continue, // Skip informational responses
+                Some(code) => return Ok(Some(Response { code, headers })),
+                None if is_closed => return Ok(None),
+                None => {
+                    error!("Invalid response");
+                    return Err(ExitCode::Failure);
+                }
+            }
+        }
+    }
+
+    // Read the body of the response and write it to STDOUT unless it's
+    // discarded, then return false if the connection cannot be reused.
+    pub fn read_body(
+        &mut self,
+        len: BodyLength,
+        discard: bool
+    ) -> Result<bool, ExitCode> {
+        // NOTE: The buffer may not be convertible to a UTF-8 string so we
+        // write it to STDOUT directly instead of using print.
+        let output = |buf: &[u8]| {
+            if !discard && !buf.is_empty() {
+                syscall::write(1, buf);
+            }
+        };
+        match len {
+            BodyLength::Fixed(mut n) => {
+                while n > 0 {
+                    if self.buf.is_empty() && !self.fill()? {
+                        return Ok(false);
+                    }
+                    let m = n.min(self.buf.len());
+                    output(&self.buf[0..m]);
+                    self.buf.drain(0..m);
+                    n -= m;
+                }
+                Ok(true)
+            }
+            BodyLength::Chunked => {
+                let mut decoder = ChunkedDecoder::new();
+                let mut data = Vec::new();
+                while !decoder.is_done() {
+                    if self.buf.is_empty() && !self.fill()? {
+                        return Ok(false);
+                    }
+                    if let Ok(n) = decoder.decode(&self.buf, &mut data) {
+                        self.buf.drain(0..n);
+                        output(&data);
+                        data.clear();
+                    } else {
+                        error!("Invalid chunked encoding");
+                        return Err(ExitCode::Failure);
+                    }
+                }
+                Ok(true)
+            }
+            BodyLength::UntilClose => {
+                loop {
+                    output(&self.buf);
+                    self.buf.clear();
+                    if !self.fill()? {
+                        return Ok(false);
+                    }
+                }
+            }
+        }
+    }
 }
 
 pub fn main(args: &[&str]) -> Result<(), ExitCode> {
@@ -52,9 +538,15 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
 
     // Parse command line options
     let mut is_verbose = false;
+    let mut is_insecure = false;
+    let mut scheme = "http://";
     let mut host = "";
     let mut path = "";
     let mut timeout = 5.0;
+    let mut method = None;
+    let mut data = None;
+    let mut headers = Vec::new();
+    let mut max_redirects = MAX_REDIRECTS;
     let mut i = 1;
     let n = args.len();
     while i < n {
@@ -65,6 +557,9 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
             "-v" | "--verbose" => {
                 is_verbose = true;
             }
+            "-k" | "--insecure" => {
+                is_insecure = true;
+            }
             "-t" | "--timeout" => {
                 if i + 1 < n {
                     i += 1;
@@ -74,14 +569,58 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
                     return Err(ExitCode::UsageError);
                 }
             }
+            "-m" | "--method" => {
+                if i + 1 < n {
+                    i += 1;
+                    method = Some(args[i].to_uppercase());
+                } else {
+                    error!("Missing method");
+                    return Err(ExitCode::UsageError);
+                }
+            }
+            "-d" | "--data" => {
+                if i + 1 < n {
+                    i += 1;
+                    if let Ok(buf) = fs::read_to_bytes(args[i]) {
+                        data = Some(buf);
+                    } else {
+                        error!("Could not read '{}'", args[i]);
+                        return Err(ExitCode::Failure);
+                    }
+                } else {
+                    error!("Missing data file");
+                    return Err(ExitCode::UsageError);
+                }
+            }
+            "-H" | "--header" => {
+                if i + 1 < n && args[i + 1].contains(':') {
+                    i += 1;
+                    headers.push(args[i].to_string());
+                } else {
+                    error!("Missing header");
+                    return Err(ExitCode::UsageError);
+                }
+            }
+            "-r" | "--max-redirects" => {
+                if i + 1 < n {
+                    i += 1;
+                    max_redirects = args[i].parse().unwrap_or(max_redirects);
+                } else {
+                    error!("Missing max redirects");
+                    return Err(ExitCode::UsageError);
+                }
+            }
             _ => {
                 if args[i].starts_with('-') {
                     error!("Invalid option '{}'", args[i]);
                     return Err(ExitCode::UsageError);
                 } else if host.is_empty() {
-                    host = args[i].
-                        trim_start_matches("http://").
-                        trim_start_matches("https://");
+                    host = args[i];
+                    if let Some(h) = host.strip_prefix("https://") {
+                        (scheme, host) = ("https://", h);
+                    } else {
+                        host = host.trim_start_matches("http://");
+                    }
                 } else if path.is_empty() {
                     path = args[i];
                 } else {
@@ -104,50 +643,46 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
         }
     }
 
-    let url = "http://".to_string() + host + path;
-    let url = URL::parse(&url).expect("invalid URL format");
-    let port = url.port;
-    let addr = if url.host.ends_with(char::is_numeric) {
-        match IpAddress::from_str(&url.host) {
-            Ok(ip_addr) => ip_addr,
-            Err(_) => {
-                error!("Invalid address format");
-                return Err(ExitCode::UsageError);
-            }
-        }
-    } else {
-        match usr::host::resolve(&url.host) {
-            Ok(ip_addr) => ip_addr,
-            Err(e) => {
-                error!("Could not resolve host: {:?}", e);
-                return Err(ExitCode::Failure);
-            }
+    let url = scheme.to_string() + host + path;
+    let mut url = match URL::parse(&url) {
+        Some(url) => url,
+        None => {
+            error!("Invalid URL format");
+            return Err(ExitCode::UsageError);
         }
     };
+    let mut method = method.unwrap_or_else(|| {
+        if data.is_some() { "POST" } else { "GET" }.to_string()
+    });
 
-    let socket_path = "/dev/net/tcp";
-    let buf_len = if let Some(info) = syscall::info(socket_path) {
-        info.size() as usize
-    } else {
-        error!("Could not open '{}'", socket_path);
-        return Err(ExitCode::Failure);
-    };
+    let mut conn: Option<Connection> = None;
+    let mut redirects = 0;
+    loop {
+        // Reuse the connection of the previous request if possible
+        let (mut c, mut is_reused) = match conn.take() {
+            Some(c) if c.is_connected_to(&url) => (c, true),
+            Some(c) => {
+                c.close();
+                (Connection::open(&url, timeout, !is_insecure)?, false)
+            }
+            None => {
+                (Connection::open(&url, timeout, !is_insecure)?, false)
+            }
+        };
 
-    let mut code = None;
-    let flags = OpenFlag::Device as u8;
-    if let Some(handle) = syscall::open(socket_path, flags) {
-        if syscall::connect(handle, addr, port).is_err() {
-            error!("Could not connect to {}:{}", addr, port);
-            syscall::close(handle);
-            return Err(ExitCode::Failure);
-        }
-        let req = vec![
-            format!("GET {} HTTP/1.1\r\n", url.path),
-            format!("Host: {}\r\n", url.host),
+        let mut req = vec![
+            format!("{} {} HTTP/1.1\r\n", method, url.path),
+            format!("Host: {}\r\n", url.authority()),
             format!("User-Agent: MOROS/{}\r\n", env!("CARGO_PKG_VERSION")),
-            format!("Connection: close\r\n"),
-            format!("\r\n"),
+            format!("Connection: keep-alive\r\n"),
         ];
+        if let Some(buf) = &data {
+            req.push(format!("Content-Length: {}\r\n", buf.len()));
+        }
+        for header in &headers {
+            req.push(format!("{}\r\n", header));
+        }
+        req.push("\r\n".to_string());
         if is_verbose {
             print!("{}", csi_verbose);
             for line in &req {
@@ -156,80 +691,81 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
             print!("{}", csi_reset);
         }
         let req = req.join("");
-        syscall::write(handle, req.as_bytes());
-
-        let mut state = ResponseState::Headers;
-        loop {
-            if console::end_of_text() || console::end_of_transmission() {
-                eprintln!();
-                syscall::close(handle);
-                return Err(ExitCode::Failure);
-            }
-            let mut data = vec![0; buf_len];
-            if let Some(n) = syscall::read(handle, &mut data) {
-                if n == 0 {
-                    break;
+        let res = loop {
+            match c.request(req.as_bytes(), data.as_deref(), is_verbose) {
+                Ok(Some(res)) => break res,
+                Ok(None) if is_reused => {
+                    // The server has closed the previous connection
+                    c.close();
+                    c = Connection::open(&url, timeout, !is_insecure)?;
+                    is_reused = false;
                 }
-                data.resize(n, 0);
-                let mut i = 0;
-                while i < n {
-                    match state {
-                        ResponseState::Headers => {
-                            let mut j = i;
-                            while j < n {
-                                if data[j] == b'\n' {
-                                    break;
-                                }
-                                j += 1;
-                            }
-                            // TODO: check i == j
-                            let line = String::from_utf8_lossy(&data[i..j]);
-                            if i == 0 {
-                                code = line.split(" ").nth(1).map(|word|
-                                    word.to_string()
-                                );
-                            }
-                            if is_verbose {
-                                if i == 0 {
-                                    print!("{}", csi_verbose);
-                                }
-                                println!("< {}", line);
-                            }
-                            if line.trim().is_empty() {
-                                if is_verbose {
-                                    print!("{}", csi_reset);
-                                }
-                                state = ResponseState::Body;
-                            }
-                            i = j + 1;
-                        }
-                        ResponseState::Body => {
-                            // NOTE: The buffer may not be convertible to a
-                            // UTF-8 string so we write it to STDOUT directly
-                            // instead of using print.
-                            syscall::write(1, &data[i..n]);
-                            break;
-                        }
-                    }
+                Ok(None) => {
+                    error!("Connection closed by {}:{}", c.host, c.port);
+                    c.close();
+                    return Err(ExitCode::Failure);
                 }
-            } else {
-                error!("Could not read from {}:{}", addr, port);
-                syscall::close(handle);
-                return Err(ExitCode::Failure);
+                Err(code) => {
+                    c.close();
+                    return Err(code);
+                }
+            }
+        };
+
+        // The body of a redirection is not displayed
+        let location = if res.is_redirect() {
+            res.header("location")
+        } else {
+            None
+        };
+        let len = res.body_length(&method);
+        match c.read_body(len, location.is_some()) {
+            Ok(true) if res.is_keep_alive() => {
+                conn = Some(c);
+            }
+            Ok(_) => {
+                c.close();
+            }
+            Err(code) => {
+                c.close();
+                return Err(code);
             }
         }
-        syscall::close(handle);
-        if let Some(s) = code {
-            if let Ok(n) = s.parse::<usize>() {
-                if n < 400 {
-                    return Ok(());
+
+        if let Some(location) = location {
+            redirects += 1;
+            if redirects > max_redirects {
+                error!("Too many redirects");
+                break;
+            }
+            url = match url.join(location) {
+                Some(url) => url,
+                None => {
+                    error!("Invalid redirect location '{}'", location);
+                    break;
                 }
+            };
+            let is_post = method == "POST";
+            if res.code == 303 || (is_post && res.code <= 302) {
+                method = "GET".to_string();
+                data = None;
             }
+            continue;
         }
-        Err(ExitCode::Failure)
-    } else {
-        Err(ExitCode::Failure)
+
+        if let Some(c) = conn {
+            c.close();
+        }
+        return if res.code < 400 {
+            Ok(())
+        } else {
+            Err(ExitCode::Failure)
+        };
     }
+    if let Some(c) = conn {
+        c.close();
+    }
+    Err(ExitCode::Failure)
 }
 
 fn help() -> Result<(), ExitCode> {
@@ -250,5 +786,112 @@ fn help() -> Result<(), ExitCode> {
         "  {0}-t{1}, {0}--timeout <seconds>{1}    Request timeout",
         csi_option, csi_reset
     );
+    println!(
+        "  {0}-k{1}, {0}--insecure{1}             Skip TLS verification",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-m{1}, {0}--method <method>{1}      Request method",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-d{1}, {0}--data <file>{1}          Send file as request body",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-H{1}, {0}--header <header>{1}      Add request header",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-r{1}, {0}--max-redirects <n>{1}    Maximum number of redirects",
+        csi_option, csi_reset
+    );
     Ok(())
 }
+
+#[test_case]
+fn test_url_parse() {
+    let url = URL::parse("http://example.com").unwrap();
+    assert_eq!(url.host, "example.com");
+    assert_eq!(url.port, 80);
+    assert_eq!(url.path, "/");
+    assert_eq!(url.authority(), "example.com");
+
+    let url = URL::parse("http://10.0.2.2:8080/index.html").unwrap();
+    assert_eq!(url.host, "10.0.2.2");
+    assert_eq!(url.port, 8080);
+    assert_eq!(url.path, "/index.html");
+    assert_eq!(url.authority(), "10.0.2.2:8080");
+
+    let url = URL::parse("http://[::1]:80/").unwrap();
+    assert_eq!(url.host, "::1");
+    assert_eq!(url.port, 80);
+    assert_eq!(url.path, "/");
+    assert_eq!(url.authority(), "[::1]");
+
+    let url = URL::parse("http://[fe80::2]/test").unwrap();
+    assert_eq!(url.host, "fe80::2");
+    assert_eq!(url.port, 80);
+    assert_eq!(url.authority(), "[fe80::2]");
+
+    let url = URL::parse("https://example.com/index.html").unwrap();
+    assert!(url.is_secure);
+    assert_eq!(url.port, 443);
+    assert_eq!(url.authority(), "example.com");
+
+    let url = URL::parse("https://example.com:80/").unwrap();
+    assert_eq!(url.authority(), "example.com:80");
+
+    assert!(URL::parse("http://[::1").is_none());
+    assert!(URL::parse("ftp://example.com").is_none());
+}
+
+#[test_case]
+fn test_url_join() {
+    let url = URL::parse("http://example.com:8080/a/b.html?c=/d").unwrap();
+
+    let next = url.join("/e.html").unwrap();
+    assert_eq!(next.authority(), "example.com:8080");
+    assert_eq!(next.path, "/e.html");
+
+    let next = url.join("e.html").unwrap();
+    assert_eq!(next.path, "/a/e.html");
+
+    let next = url.join("http://moros.cc/").unwrap();
+    assert_eq!(next.authority(), "moros.cc");
+    assert_eq!(next.path, "/");
+
+    let next = url.join("https://moros.cc/").unwrap();
+    assert!(next.is_secure);
+    assert_eq!(next.port, 443);
+
+    assert!(url.join("ftp://moros.cc/").is_none());
+}
+
+#[test_case]
+fn test_chunked_decoder() {
+    let buf = b"4\r\nMoro\r\n5;ext=1\r\ns OS!\r\n0\r\nFoo: bar\r\n\r\nHTTP";
+    let mut out = Vec::new();
+    let mut decoder = ChunkedDecoder::new();
+    assert_eq!(decoder.decode(buf, &mut out), Ok(buf.len() - 4));
+    assert!(decoder.is_done());
+    assert_eq!(out, b"Moros OS!");
+
+    // Decode the buffer byte by byte
+    let mut out = Vec::new();
+    let mut decoder = ChunkedDecoder::new();
+    for i in 0..buf.len() {
+        assert_eq!(decoder.decode(&buf[i..(i + 1)], &mut out), Ok(1));
+        if decoder.is_done() {
+            break;
+        }
+    }
+    assert!(decoder.is_done());
+    assert_eq!(out, b"Moros OS!");
+
+    let mut out = Vec::new();
+    let mut decoder = ChunkedDecoder::new();
+    assert!(decoder.decode(b"z\r\n", &mut out).is_err());
+    let mut decoder = ChunkedDecoder::new();
+    assert!(decoder.decode(b"1\r\nab\r\n", &mut out).is_err());
+}
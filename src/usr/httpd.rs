@@ -1,3 +1,4 @@
+use crate::api::base64::Base64;
 use crate::api::clock;
 use crate::api::clock::DATE_TIME_ZONE;
 use crate::api::console::Style;
@@ -7,6 +8,9 @@ use crate::api::syscall;
 use crate::api::time;
 use crate::sys;
 use crate::sys::console;
+use crate::sys::fs::{FileIO, SeekFrom};
+use crate::sys::net::socket::tcp::{MAX_BUFFER_SIZE, MIN_BUFFER_SIZE};
+use crate::usr;
 
 use alloc::collections::btree_map::BTreeMap;
 use alloc::collections::vec_deque::VecDeque;
@@ -15,21 +19,30 @@ use alloc::string::{String, ToString};
 use alloc::vec;
 use alloc::vec::Vec;
 use core::fmt;
+use core::str;
 use smoltcp::iface::SocketSet;
-use smoltcp::phy::Device;
 use smoltcp::socket::tcp;
 use smoltcp::time::Instant;
 use smoltcp::wire::IpAddress;
+use ::time::PrimitiveDateTime;
 
 const MAX_CONNECTIONS: usize = 32;
+const BUFFER_SIZE: usize = 4 << 10; // Per connection and direction
 const POLL_DELAY_DIV: usize = 128;
 const INDEX: [&str; 4] = ["", "/index.html", "/index.htm", "/index.txt"];
+const CGI_STDIN: &str = "/tmp/httpd-stdin";
+const CGI_STDOUT: &str = "/tmp/httpd-stdout";
+const HTTP_DATE: &str = "%a, %d %b %Y %H:%M:%S GMT";
+const LOG_DATE: &str = "%d/%b/%Y:%H:%M:%S %z";
+const MAX_LOG_SIZE: usize = 64 << 10;
+const MAX_LOG_FILES: usize = 4;
 
 #[derive(Clone)]
 struct Request {
     addr: IpAddress,
     verb: String,
     path: String,
+    version: String,
     body: Vec<u8>,
     headers: BTreeMap<String, String>,
 }
@@ -40,43 +53,55 @@ impl Request {
             addr,
             verb: String::new(),
             path: String::new(),
+            version: String::new(),
             body: Vec::new(),
             headers: BTreeMap::new(),
         }
     }
 
+    // Return the value of a header with a case insensitive name
+    pub fn header(&self, key: &str) -> Option<&String> {
+        self.headers.iter().find_map(|(k, v)|
+            if k.eq_ignore_ascii_case(key) { Some(v) } else { None }
+        )
+    }
+
     pub fn from(addr: IpAddress, buf: &[u8]) -> Option<Self> {
-        let msg = String::from_utf8_lossy(buf);
-        if !msg.is_empty() {
-            let mut req = Request::new(addr);
-            let mut is_header = true;
-            for (i, line) in msg.lines().enumerate() {
-                if i == 0 {
-                    // Request line
-                    let fields: Vec<_> = line.split(' ').collect();
-                    if fields.len() >= 2 {
-                        req.verb = fields[0].to_string();
-                        req.path = fields[1].to_string();
-                    }
-                } else if is_header {
-                    // Message header
-                    if let Some((key, val)) = line.split_once(':') {
-                        let k = key.trim().to_string();
-                        let v = val.trim().to_string();
-                        req.headers.insert(k, v);
-                    } else if line.is_empty() {
-                        is_header = false;
-                    }
-                } else if !is_header {
-                    // Message body
-                    let s = format!("{}\n", line);
-                    req.body.extend_from_slice(s.as_bytes());
+        if buf.is_empty() {
+            return None;
+        }
+        let mut req = Request::new(addr);
+
+        // The message body is kept as raw bytes after the empty line that
+        // ends the message header.
+        let head = match header_end(buf) {
+            Some((i, n)) => {
+                req.body.extend_from_slice(&buf[(i + n)..]);
+                &buf[0..i]
+            }
+            None => buf,
+        };
+
+        let msg = String::from_utf8_lossy(head);
+        for (i, line) in msg.lines().enumerate() {
+            if i == 0 {
+                // Request line
+                let fields: Vec<_> = line.split(' ').collect();
+                if fields.len() >= 2 {
+                    req.verb = fields[0].to_string();
+                    req.path = fields[1].to_string();
                 }
+                if fields.len() >= 3 {
+                    req.version = fields[2].to_string();
+                }
+            } else if let Some((key, val)) = line.split_once(':') {
+                // Message header
+                let k = key.trim().to_string();
+                let v = val.trim().to_string();
+                req.headers.insert(k, v);
             }
-            Some(req)
-        } else {
-            None
         }
+        Some(req)
     }
 }
 
@@ -98,7 +123,7 @@ impl Response {
         let mut headers = BTreeMap::new();
         headers.insert(
             "Date".to_string(),
-            time::now_utc().format("%a, %d %b %Y %H:%M:%S GMT"),
+            time::now_utc().format(HTTP_DATE),
         );
         headers.insert(
             "Server".to_string(),
@@ -119,10 +144,12 @@ impl Response {
 
     pub fn end(&mut self) {
         self.size = self.body.len();
-        self.headers.insert(
-            "Content-Length".to_string(),
-            self.size.to_string()
-        );
+        if self.code != 304 {
+            self.headers.insert(
+                "Content-Length".to_string(),
+                self.size.to_string()
+            );
+        }
         self.headers.insert(
             "Connection".to_string(),
             if self.is_persistent() {
@@ -131,14 +158,16 @@ impl Response {
                 "close".to_string()
             }
         );
-        self.headers.insert(
-            "Content-Type".to_string(),
-            if self.mime.starts_with("text/") {
-                format!("{}; charset=utf-8", self.mime)
-            } else {
-                format!("{}", self.mime)
-            }
-        );
+        if self.code != 304 {
+            self.headers.insert(
+                "Content-Type".to_string(),
+                if self.mime.starts_with("text/") && !self.mime.contains(';') {
+                    format!("{}; charset=utf-8", self.mime)
+                } else {
+                    format!("{}", self.mime)
+                }
+            );
+        }
         self.write();
     }
 
@@ -159,10 +188,15 @@ impl Response {
     fn status(&self) -> String {
         let msg = match self.code {
             200 => "OK",
+            206 => "Partial Content",
             301 => "Moved Permanently",
+            302 => "Found",
+            304 => "Not Modified",
             400 => "Bad Request",
+            401 => "Unauthorized",
             403 => "Forbidden",
             404 => "Not Found",
+            416 => "Range Not Satisfiable",
             500 => "Internal Server Error",
             _ => "Unknown Error",
         };
@@ -177,6 +211,25 @@ impl Response {
         }
         true
     }
+
+    // Return the response in Common Log Format
+    fn to_log(&self) -> String {
+        let size = if self.size > 0 {
+            self.size.to_string()
+        } else {
+            "-".to_string()
+        };
+        format!(
+            "{} - - [{}] \"{} {} {}\" {} {}\n",
+            self.req.addr,
+            time::now().format(LOG_DATE),
+            self.req.verb,
+            self.req.path,
+            self.req.version,
+            self.code,
+            size
+        )
+    }
 }
 
 impl fmt::Display for Response {
@@ -218,10 +271,8 @@ fn get(req: &Request, res: &mut Response) {
             if fs::is_dir(&real_path) {
                 continue;
             }
-            if let Ok(buf) = fs::read_to_bytes(&real_path) {
-                res.code = 200;
-                res.mime = content_type(&real_path);
-                res.body.extend_from_slice(&buf);
+            if fs::is_file(&real_path) {
+                get_file(req, res, &real_path);
                 not_found = false;
                 break;
             }
@@ -252,6 +303,394 @@ fn get(req: &Request, res: &mut Response) {
     }
 }
 
+// Return the interpreter of a lisp or shell script
+fn cgi_interpreter(path: &str) -> Option<&'static str> {
+    if fs::is_dir(path) {
+        return None;
+    }
+    let buf = fs::read_to_bytes(path).ok()?;
+    let line = buf.split(|b| *b == b'\n').next()?;
+    match String::from_utf8_lossy(line).trim() {
+        "#! lisp" | "#!lisp" => Some("lisp"),
+        "#! shell" | "#!shell" => Some("shell"),
+        _ => None,
+    }
+}
+
+fn cgi_env(req: &Request, path: &str, query: &str) -> Vec<(String, String)> {
+    let mut env = vec![
+        ("REQUEST_METHOD".to_string(), req.verb.clone()),
+        ("SCRIPT_NAME".to_string(), path.to_string()),
+        ("QUERY_STRING".to_string(), query.to_string()),
+        ("REMOTE_ADDR".to_string(), req.addr.to_string()),
+        ("CONTENT_LENGTH".to_string(), req.body.len().to_string()),
+    ];
+    if let Some((username, password)) = basic_auth(req) {
+        if usr::user::check_password(&username, &password) {
+            env.push(("REMOTE_USER".to_string(), username));
+        }
+    }
+    for (key, val) in &req.headers {
+        let key = key.to_uppercase().replace('-', "_");
+        if key == "AUTHORIZATION" || key == "PROXY_AUTHORIZATION" {
+            continue; // Credentials are not given to scripts
+        } else if key == "CONTENT_TYPE" {
+            env.push((key, val.clone()));
+        } else {
+            env.push((format!("HTTP_{}", key), val.clone()));
+        }
+    }
+    env
+}
+
+// Execute a script with the request passed in environment variables and on
+// stdin, and send its output as the response.
+fn cgi(req: &Request, res: &mut Response, interpreter: &str) {
+    let (path, query) = req.path.split_once('?').unwrap_or((&req.path, ""));
+    let env = cgi_env(req, path, query);
+    let vars: Vec<_> = env.iter().map(|(key, _)|
+        (key, sys::process::env(key))
+    ).collect();
+    for (key, val) in &env {
+        sys::process::set_env(key, val);
+    }
+    let is_ok = fs::write(CGI_STDIN, &req.body).is_ok()
+        && (!fs::exists(CGI_STDOUT) || fs::delete(CGI_STDOUT).is_ok())
+        && fs::reopen(CGI_STDIN, 0, false).is_ok()
+        && fs::reopen(CGI_STDOUT, 1, false).is_ok();
+    let is_ok = is_ok && match interpreter {
+        "lisp" => usr::lisp::main(&["lisp", &res.real_path]).is_ok(),
+        "shell" => usr::shell::main(&["shell", &res.real_path]).is_ok(),
+        _ => false,
+    };
+    for i in 0..2 {
+        fs::reopen("/dev/console", i, false).ok();
+    }
+    for (key, val) in vars {
+        match val {
+            Some(val) => sys::process::set_env(key, &val),
+            None => sys::process::delete_env(key),
+        }
+    }
+
+    match fs::read_to_bytes(CGI_STDOUT) {
+        Ok(buf) if is_ok => parse_cgi_output(&buf, res),
+        _ => {
+            res.code = 500;
+            res.mime = "text/html".to_string();
+            res.body.extend_from_slice(b"<h1>Internal Server Error</h1>\n");
+        }
+    }
+}
+
+// Return the position and the length of the empty line ending a header
+fn header_end(buf: &[u8]) -> Option<(usize, usize)> {
+    [&b"\r\n\r\n"[..], &b"\n\n"[..]].iter().filter_map(|sep|
+        buf.windows(sep.len()).position(|w| w == *sep).map(|i| (i, sep.len()))
+    ).min()
+}
+
+// Parse the optional headers at the beginning of the output of a script
+fn parse_cgi_output(buf: &[u8], res: &mut Response) {
+    res.code = 200;
+    res.mime = "text/plain".to_string();
+    let mut body = buf;
+    if let Some((i, n)) = header_end(buf) {
+        let head = String::from_utf8_lossy(&buf[0..i]);
+        let is_header = |line: &str| match line.split_once(':') {
+            Some((key, _)) => !key.is_empty() && !key.contains(' '),
+            None => false,
+        };
+        if head.lines().all(is_header) {
+            body = &buf[(i + n)..];
+            for line in head.lines() {
+                let (key, val) = line.split_once(':').unwrap();
+                let val = val.trim();
+                match key.to_lowercase().as_str() {
+                    "status" => {
+                        let code = val.split(' ').next().unwrap_or("");
+                        res.code = code.parse().unwrap_or(500);
+                    }
+                    "content-type" => {
+                        res.mime = val.to_string();
+                    }
+                    "location" => {
+                        res.code = 302;
+                        res.headers.insert(key.to_string(), val.to_string());
+                    }
+                    _ => {
+                        res.headers.insert(key.to_string(), val.to_string());
+                    }
+                }
+            }
+        }
+    }
+    res.body.extend_from_slice(body);
+}
+
+// Access rule with a path prefix, the allowed methods, and the users allowed
+// to use them, where `*` matches any method or any user (even anonymous).
+struct AccessRule {
+    path: String,
+    methods: Vec<String>,
+    users: Vec<String>,
+}
+
+impl AccessRule {
+    fn matches(&self, method: &str, path: &str) -> bool {
+        let prefix = self.path.trim_end_matches('/');
+        let is_inside =
+            path == prefix || path.starts_with(&format!("{}/", prefix));
+        let is_allowed = self.methods.iter().any(|m| m == "*" || m == method);
+        is_inside && is_allowed
+    }
+}
+
+// Parse the access rules with one `<path>,<methods>,<users>` rule per line
+// and the methods and users separated by spaces.
+fn parse_access_rules(contents: &str) -> Vec<AccessRule> {
+    let mut rules = Vec::new();
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<_> = line.split(',').map(|f| f.trim()).collect();
+        if fields.len() == 3 && fields[0].starts_with('/') {
+            let split = |s: &str|
+                s.split_whitespace().map(String::from).collect();
+            rules.push(AccessRule {
+                path: fields[0].to_string(),
+                methods: split(fields[1]),
+                users: split(fields[2]),
+            });
+        }
+    }
+    rules
+}
+
+// Return the username and password of a request with Basic authentication
+fn basic_auth(req: &Request) -> Option<(String, String)> {
+    let val = req.header("Authorization")?;
+    let (scheme, credentials) = val.split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("Basic") {
+        return None;
+    }
+    let credentials = credentials.trim().trim_end_matches('=');
+    let buf = Base64::decode(credentials.as_bytes()).ok()?;
+    let (username, password) = str::from_utf8(&buf).ok()?.split_once(':')?;
+    Some((username.to_string(), password.to_string()))
+}
+
+// Check the request against the rule with the longest matching path and
+// return the status code of the response when the access is denied.
+fn authorize(
+    req: &Request,
+    path: &str,
+    rules: &[AccessRule]
+) -> Option<usize> {
+    let rule = rules.iter().filter(|rule|
+        rule.matches(&req.verb, path)
+    ).max_by_key(|rule| rule.path.trim_end_matches('/').len());
+    let rule = match rule {
+        Some(rule) => rule,
+        None => return Some(403),
+    };
+    if rule.users.iter().any(|u| u == "*") {
+        return None;
+    }
+    match basic_auth(req) {
+        Some((username, password)) => {
+            if !usr::user::check_password(&username, &password) {
+                Some(401)
+            } else if !rule.users.iter().any(|u| *u == username) {
+                Some(403)
+            } else {
+                None
+            }
+        }
+        None => Some(401),
+    }
+}
+
+fn deny(res: &mut Response, code: usize) {
+    res.code = code;
+    res.mime = "text/html".to_string();
+    if code == 401 {
+        res.headers.insert(
+            "WWW-Authenticate".to_string(),
+            "Basic realm=\"MOROS\"".to_string(),
+        );
+        res.body.extend_from_slice(b"<h1>Unauthorized</h1>\n");
+    } else if code == 400 {
+        res.body.extend_from_slice(b"<h1>Bad Request</h1>\n");
+    } else {
+        res.body.extend_from_slice(b"<h1>Forbidden</h1>\n");
+    }
+}
+
+fn get_file(req: &Request, res: &mut Response, path: &str) {
+    let info = match syscall::info(path) {
+        Some(info) => info,
+        None => {
+            res.code = 500;
+            return;
+        }
+    };
+    let size = info.size() as usize;
+    let time = info.time();
+    let etag = format!("\"{:x}-{:x}\"", time, size);
+    let last_modified = time::from_timestamp_utc(time as i64).format(HTTP_DATE);
+    res.mime = content_type(path);
+    res.headers.insert("ETag".to_string(), etag.clone());
+    res.headers.insert("Last-Modified".to_string(), last_modified);
+    res.headers.insert("Accept-Ranges".to_string(), "bytes".to_string());
+
+    if is_not_modified(req, &etag, time) {
+        res.code = 304;
+        return;
+    }
+
+    // The header is ignored when it cannot be parsed
+    let range = match req.header("Range") {
+        Some(range) => parse_range(range, size),
+        None => Range::Ignored,
+    };
+    let mut file = match sys::fs::File::open(path) {
+        Some(file) => file,
+        None => {
+            res.code = 500;
+            return;
+        }
+    };
+    match range {
+        Range::Satisfiable(start, end) => {
+            let mut buf = vec![0; end - start + 1];
+            let is_read = file.seek(SeekFrom::Start(start as u32)).is_ok()
+                && file.read(&mut buf) == Ok(buf.len());
+            if is_read {
+                res.code = 206;
+                res.headers.insert(
+                    "Content-Range".to_string(),
+                    format!("bytes {}-{}/{}", start, end, size)
+                );
+                res.body.extend_from_slice(&buf);
+            } else {
+                res.code = 500;
+            }
+        }
+        Range::Unsatisfiable => {
+            res.code = 416;
+            res.headers.insert(
+                "Content-Range".to_string(),
+                format!("bytes */{}", size)
+            );
+        }
+        Range::Ignored => {
+            let mut buf = vec![0; size];
+            if file.read(&mut buf) == Ok(size) {
+                res.code = 200;
+                res.body.extend_from_slice(&buf);
+            } else {
+                res.code = 500;
+            }
+        }
+    }
+}
+
+fn is_not_modified(req: &Request, etag: &str, time: u64) -> bool {
+    if let Some(tags) = req.header("If-None-Match") {
+        return tags.split(',').any(|tag| {
+            let tag = tag.trim();
+            tag == "*" || tag.trim_start_matches("W/") == etag
+        });
+    }
+    if let Some(date) = req.header("If-Modified-Since") {
+        if let Ok(date) = PrimitiveDateTime::parse(date, HTTP_DATE) {
+            return time as i64 <= date.assume_utc().unix_timestamp();
+        }
+    }
+    false
+}
+
+#[derive(Debug, PartialEq)]
+enum Range {
+    Satisfiable(usize, usize), // First and last positions of the range
+    Unsatisfiable,
+    Ignored,
+}
+
+// Parse a single byte range of a `Range` header
+fn parse_range(range: &str, size: usize) -> Range {
+    match range_bounds(range, size) {
+        Some((start, _)) if start >= size => Range::Unsatisfiable,
+        Some((start, end)) => Range::Satisfiable(start, end),
+        None => Range::Ignored,
+    }
+}
+
+fn range_bounds(range: &str, size: usize) -> Option<(usize, usize)> {
+    let range = range.trim().strip_prefix("bytes=")?;
+    if range.contains(',') {
+        return None; // Multiple ranges are not supported
+    }
+    let (start, end) = range.split_once('-')?;
+    let last = size.saturating_sub(1);
+    match (start.trim(), end.trim()) {
+        ("", "") => None,
+        ("", n) => {
+            let n: usize = n.parse().ok()?;
+            Some((size.saturating_sub(n), last)) // Suffix of `n` bytes
+        }
+        (a, "") => Some((a.parse().ok()?, last)),
+        (a, b) => {
+            let (a, b): (usize, usize) = (a.parse().ok()?, b.parse().ok()?);
+            if b < a {
+                return None;
+            }
+            Some((a, b.min(last)))
+        }
+    }
+}
+
+// Append a line to the log file after rotating the previous files when it
+// would become too large: `<path>` is moved to `<path>.1` which is moved to
+// `<path>.2` and so on until the oldest file is deleted.
+fn write_log(path: &str, line: &str) {
+    let size = syscall::info(path).map_or(0, |info| info.size() as usize);
+    if size > 0 && size + line.len() > MAX_LOG_SIZE {
+        for i in (0..MAX_LOG_FILES).rev() {
+            let src = log_path(path, i);
+            if !fs::exists(&src) {
+                continue;
+            }
+            if i + 1 < MAX_LOG_FILES {
+                if let Ok(buf) = fs::read_to_bytes(&src) {
+                    fs::write(&log_path(path, i + 1), &buf).ok();
+                }
+            }
+            fs::delete(&src).ok();
+        }
+    }
+    let handle = if fs::exists(path) {
+        fs::append_file(path)
+    } else {
+        fs::create_file(path)
+    };
+    if let Some(handle) = handle {
+        syscall::write(handle, line.as_bytes());
+        syscall::close(handle);
+    }
+}
+
+fn log_path(path: &str, i: usize) -> String {
+    if i == 0 {
+        path.to_string()
+    } else {
+        format!("{}.{}", path, i)
+    }
+}
+
 fn put(req: &Request, res: &mut Response) {
     if res.real_path.ends_with('/') {
         // Write directory
@@ -294,6 +733,10 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
     let mut read_only = false;
     let mut port = 80;
     let mut dir = sys::process::dir();
+    let mut cgi_dir = None;
+    let mut access_rules = None;
+    let mut log = None;
+    let mut buf_size = BUFFER_SIZE;
     let mut i = 1;
     let n = args.len();
     while i < n {
@@ -323,6 +766,53 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
                     return Err(ExitCode::UsageError);
                 }
             }
+            "-c" | "--cgi" => {
+                if i + 1 < n {
+                    i += 1;
+                    let path = args[i].trim_matches('/');
+                    cgi_dir = Some(format!("/{}/", path).replace("//", "/"));
+                } else {
+                    error!("Missing CGI path");
+                    return Err(ExitCode::UsageError);
+                }
+            }
+            "-l" | "--log" => {
+                if i + 1 < n {
+                    i += 1;
+                    log = Some(fs::realpath(args[i]));
+                } else {
+                    error!("Missing log path");
+                    return Err(ExitCode::UsageError);
+                }
+            }
+            "-b" | "--buffer" => {
+                if i + 1 < n {
+                    i += 1;
+                    if let Ok(size) = args[i].parse::<usize>() {
+                        buf_size = size.clamp(MIN_BUFFER_SIZE, MAX_BUFFER_SIZE);
+                    } else {
+                        error!("Invalid buffer size");
+                        return Err(ExitCode::UsageError);
+                    }
+                } else {
+                    error!("Missing buffer size");
+                    return Err(ExitCode::UsageError);
+                }
+            }
+            "-a" | "--access" => {
+                if i + 1 < n {
+                    i += 1;
+                    if let Ok(contents) = fs::read_to_string(args[i]) {
+                        access_rules = Some(parse_access_rules(&contents));
+                    } else {
+                        error!("Could not read '{}'", args[i]);
+                        return Err(ExitCode::Failure);
+                    }
+                } else {
+                    error!("Missing access rules file");
+                    return Err(ExitCode::UsageError);
+                }
+            }
             _ => {}
         }
         i += 1;
@@ -331,68 +821,102 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
     // NOTE: This specific format is needed by `join_path`
     let dir = format!("/{}", fs::realpath(&dir).trim_matches('/'));
 
-    if let Some((ref mut iface, ref mut device)) = *sys::net::NET.lock() {
-        let mut sockets = SocketSet::new(vec![]);
+    if sys::net::NET.lock().is_none() {
+        error!("Could not find network interface");
+        return Err(ExitCode::Failure);
+    }
+    let mut sockets = SocketSet::new(vec![]);
+    let mut connections = Vec::new();
+    for _ in 0..MAX_CONNECTIONS {
+        let size = buf_size;
+        let tcp_rx_buffer = tcp::SocketBuffer::new(vec![0; size]);
+        let tcp_tx_buffer = tcp::SocketBuffer::new(vec![0; size]);
+        let tcp_socket = tcp::Socket::new(tcp_rx_buffer, tcp_tx_buffer);
+        let tcp_handle = sockets.add(tcp_socket);
 
-        let mtu = device.capabilities().max_transmission_unit;
-        let buf_len = mtu - 14 - 20 - 20; // ETH+TCP+IP headers
-        let mut connections = Vec::new();
-        for _ in 0..MAX_CONNECTIONS {
-            let tcp_rx_buffer = tcp::SocketBuffer::new(vec![0; buf_len]);
-            let tcp_tx_buffer = tcp::SocketBuffer::new(vec![0; buf_len]);
-            let tcp_socket = tcp::Socket::new(tcp_rx_buffer, tcp_tx_buffer);
-            let tcp_handle = sockets.add(tcp_socket);
+        let send_queue: VecDeque<(Vec<u8>, usize)> = VecDeque::new();
+        let keep_alive = true;
+        connections.push((tcp_handle, send_queue, keep_alive));
+    }
 
-            let send_queue: VecDeque<Vec<u8>> = VecDeque::new();
-            let keep_alive = true;
-            connections.push((tcp_handle, send_queue, keep_alive));
-        }
+    println!(
+        "{}HTTP Server listening on 0.0.0.0:{}{}",
+        csi_color, port, csi_reset
+    );
 
-        println!(
-            "{}HTTP Server listening on 0.0.0.0:{}{}",
-            csi_color, port, csi_reset
-        );
+    loop {
+        if console::end_of_text() || console::end_of_transmission() {
+            println!();
+            return Ok(());
+        }
 
-        loop {
-            if console::end_of_text() || console::end_of_transmission() {
-                println!();
-                return Ok(());
-            }
+        let ms = (clock::epoch_time() * 1000000.0) as i64;
+        let time = Instant::from_micros(ms);
 
-            let ms = (clock::epoch_time() * 1000000.0) as i64;
-            let time = Instant::from_micros(ms);
+        // The network is locked only during the polls to let CGI scripts
+        // use it while the requests are handled
+        if let Some((ref mut iface, ref mut device)) = *sys::net::NET.lock() {
             iface.poll(time, device, &mut sockets);
+        }
 
-            for (tcp_handle, send_queue, keep_alive) in &mut connections {
-                let socket = sockets.get_mut::<tcp::Socket>(*tcp_handle);
+        for (tcp_handle, send_queue, keep_alive) in &mut connections {
+            let socket = sockets.get_mut::<tcp::Socket>(*tcp_handle);
 
-                if !socket.is_open() {
-                    socket.listen(port).unwrap();
-                }
-                let endpoint = match socket.remote_endpoint() {
-                    Some(endpoint) => endpoint,
-                    None => continue,
-                };
-                if socket.may_recv() {
-                    // The amount of octets queued in the receive buffer may be
-                    // larger than the contiguous slice returned by `recv` so
-                    // we need to loop over chunks of it until it is empty.
-                    let recv_queue = socket.recv_queue();
-                    let mut receiving = true;
-                    let mut buf = vec![];
-                    while receiving {
-                        let res = socket.recv(|chunk| {
-                            buf.extend_from_slice(chunk);
-                            if buf.len() < recv_queue {
-                                return (chunk.len(), None);
+            if !socket.is_open() {
+                socket.listen(port).unwrap();
+            }
+            let endpoint = match socket.remote_endpoint() {
+                Some(endpoint) => endpoint,
+                None => continue,
+            };
+            if socket.may_recv() {
+                // The amount of octets queued in the receive buffer may be
+                // larger than the contiguous slice returned by `recv` so
+                // we need to loop over chunks of it until it is empty.
+                let recv_queue = socket.recv_queue();
+                let mut receiving = true;
+                let mut buf = vec![];
+                while receiving {
+                    let res = socket.recv(|chunk| {
+                        buf.extend_from_slice(chunk);
+                        if buf.len() < recv_queue {
+                            return (chunk.len(), None);
+                        }
+                        receiving = false;
+
+                        let addr = endpoint.addr;
+                        if let Some(req) = Request::from(addr, &buf) {
+                            let mut res = Response::new(req.clone());
+                            let path = match req.path.split_once('?') {
+                                Some((path, _)) => path,
+                                None => &req.path,
+                            };
+                            let path = normalize_path(path);
+                            if let Some(ref path) = path {
+                                res.real_path = join_path(&dir, path);
                             }
-                            receiving = false;
 
-                            let addr = endpoint.addr;
-                            if let Some(req) = Request::from(addr, &buf) {
-                                let mut res = Response::new(req.clone());
-                                res.real_path = join_path(&dir, &req.path);
+                            let interpreter = match (&cgi_dir, &path) {
+                                (Some(cgi_dir), Some(path))
+                                    if path.starts_with(cgi_dir) => {
+                                    cgi_interpreter(&res.real_path)
+                                }
+                                _ => None,
+                            };
+
+                            let denied = match (&access_rules, &path) {
+                                (_, None) => Some(400),
+                                (Some(rules), Some(path)) => {
+                                    authorize(&req, path, rules)
+                                }
+                                (None, Some(_)) => None,
+                            };
 
+                            if let Some(code) = denied {
+                                deny(&mut res, code);
+                            } else if let Some(cmd) = interpreter {
+                                cgi(&req, &mut res, cmd);
+                            } else {
                                 match req.verb.as_str() {
                                     "GET" => {
                                         get(&req, &mut res)
@@ -410,48 +934,57 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
                                         res.mime = "text/html".to_string();
                                     }
                                 }
-                                res.end();
-                                println!("{}", res);
-                                (chunk.len(), Some(res))
-                            } else {
-                                (0, None)
                             }
-                        });
-                        if receiving {
-                            continue;
-                        }
-                        if let Ok(Some(res)) = res {
-                            *keep_alive = res.is_persistent();
-                            for chunk in res.buf.chunks(buf_len) {
-                                send_queue.push_back(chunk.to_vec());
+                            res.end();
+                            println!("{}", res);
+                            if let Some(ref path) = log {
+                                write_log(path, &res.to_log());
                             }
+                            (chunk.len(), Some(res))
+                        } else {
+                            (0, None)
                         }
+                    });
+                    if receiving {
+                        continue;
                     }
-                    if socket.can_send() {
-                        if let Some(chunk) = send_queue.pop_front() {
-                            let sent = socket.send_slice(&chunk).
-                                expect("Could not send chunk");
-                            debug_assert!(sent == chunk.len());
-                        }
+                    if let Ok(Some(res)) = res {
+                        *keep_alive = res.is_persistent();
+                        send_queue.push_back((res.buf, 0));
                     }
-                    if send_queue.is_empty() && !*keep_alive {
-                        socket.close();
+                }
+                // Fill the send buffer with the responses in the queue and
+                // keep the offset of a response that doesn't fit for later
+                while let Some((buf, offset)) = send_queue.front_mut() {
+                    if !socket.can_send() {
+                        break;
+                    }
+                    let sent = socket.send_slice(&buf[*offset..]).
+                        expect("Could not send response");
+                    *offset += sent;
+                    if *offset < buf.len() {
+                        break;
                     }
-                } else if socket.may_send() {
+                    send_queue.pop_front();
+                }
+                if send_queue.is_empty() && !*keep_alive {
                     socket.close();
-                    send_queue.clear();
                 }
+            } else if socket.may_send() {
+                socket.close();
+                send_queue.clear();
             }
-            if let Some(delay) = iface.poll_delay(time, &sockets) {
-                let d = delay.total_micros() / POLL_DELAY_DIV as u64;
-                if d > 0 {
-                    syscall::sleep((d as f64) / 1000000.0);
-                }
+        }
+        let delay = match *sys::net::NET.lock() {
+            Some((ref mut iface, _)) => iface.poll_delay(time, &sockets),
+            None => None,
+        };
+        if let Some(delay) = delay {
+            let d = delay.total_micros() / POLL_DELAY_DIV as u64;
+            if d > 0 {
+                syscall::sleep((d as f64) / 1000000.0);
             }
         }
-    } else {
-        error!("Could not find network interface");
-        Err(ExitCode::Failure)
     }
 }
 
@@ -475,6 +1008,26 @@ fn content_type(path: &str) -> String {
 }
 
 // Join the requested file path to the root dir of the server
+// Collapse the repeated slashes of a request path and reject the paths
+// that are not absolute or that contain `.` or `..` segments.
+fn normalize_path(path: &str) -> Option<String> {
+    if !path.starts_with('/') {
+        return None;
+    }
+    let mut res = String::new();
+    for segment in path.split('/').filter(|s| !s.is_empty()) {
+        if segment == "." || segment == ".." {
+            return None;
+        }
+        res.push('/');
+        res.push_str(segment);
+    }
+    if res.is_empty() || path.ends_with('/') {
+        res.push('/');
+    }
+    Some(res)
+}
+
 fn join_path(dir: &str, path: &str) -> String {
     debug_assert!(dir.starts_with('/'));
     debug_assert!(path.starts_with('/'));
@@ -505,6 +1058,22 @@ fn usage() {
         "  {0}-r{1}, {0}--read-only{1}        Set read-only mode",
         csi_option, csi_reset
     );
+    println!(
+        "  {0}-c{1}, {0}--cgi <path>{1}       Execute scripts in {0}<path>{1}",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-a{1}, {0}--access <file>{1}    Set access rules",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-b{1}, {0}--buffer <bytes>{1}   Set buffer size of connections",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-l{1}, {0}--log <file>{1}       Write access log to {0}<file>{1}",
+        csi_option, csi_reset
+    );
 }
 
 #[test_case]
@@ -516,3 +1085,126 @@ fn test_join_path() {
     assert_eq!(join_path("/", "/bar"), "/bar");
     assert_eq!(join_path("/", "/"), "/");
 }
+
+#[test_case]
+fn test_request() {
+    let addr = IpAddress::v4(127, 0, 0, 1);
+    let buf = b"PUT /a.bin HTTP/1.1\r\nHost: localhost\r\n\r\na\r\nb\0\n";
+    let req = Request::from(addr, buf).unwrap();
+    assert_eq!(req.verb, "PUT");
+    assert_eq!(req.path, "/a.bin");
+    assert_eq!(req.version, "HTTP/1.1");
+    assert_eq!(req.header("host"), Some(&"localhost".to_string()));
+    assert_eq!(req.body, b"a\r\nb\0\n");
+
+    let req = Request::from(addr, b"GET / HTTP/1.1\r\n").unwrap();
+    assert_eq!(req.verb, "GET");
+    assert!(req.body.is_empty());
+}
+
+#[test_case]
+fn test_cgi_env() {
+    let mut req = Request::new(IpAddress::v4(127, 0, 0, 1));
+    req.verb = "GET".to_string();
+    req.headers.insert(
+        "Authorization".to_string(),
+        "Basic dXNlcjp3cm9uZw==".to_string(), // user:wrong
+    );
+    req.headers.insert(
+        "Proxy-Authorization".to_string(),
+        "Basic dXNlcjp3cm9uZw==".to_string(),
+    );
+    req.headers.insert("Accept".to_string(), "*/*".to_string());
+    let env = cgi_env(&req, "/cgi/a.lsp", "");
+    assert!(env.iter().any(|(k, v)| k == "HTTP_ACCEPT" && v == "*/*"));
+    assert!(!env.iter().any(|(k, _)| k.contains("AUTHORIZATION")));
+    assert!(!env.iter().any(|(k, _)| k == "REMOTE_USER"));
+}
+
+#[test_case]
+fn test_parse_cgi_output() {
+    let req = Request::new(IpAddress::v4(127, 0, 0, 1));
+
+    let mut res = Response::new(req.clone());
+    parse_cgi_output(b"Content-Type: text/html\n\n<h1>Hello</h1>\n", &mut res);
+    assert_eq!(res.code, 200);
+    assert_eq!(res.mime, "text/html");
+    assert_eq!(res.body, b"<h1>Hello</h1>\n");
+
+    let mut res = Response::new(req.clone());
+    parse_cgi_output(b"Status: 404 Not Found\r\n\r\nNot Found", &mut res);
+    assert_eq!(res.code, 404);
+    assert_eq!(res.mime, "text/plain");
+    assert_eq!(res.body, b"Not Found");
+
+    let mut res = Response::new(req.clone());
+    parse_cgi_output(b"Hello, World!\n\nBye: now\n", &mut res);
+    assert_eq!(res.code, 200);
+    assert_eq!(res.body, b"Hello, World!\n\nBye: now\n");
+}
+
+#[test_case]
+fn test_access_rules() {
+    let rules = parse_access_rules(
+        "# Path, methods, users\n\
+         /, GET, *\n\
+         /private, GET PUT DELETE, admin\n"
+    );
+    assert_eq!(rules.len(), 2);
+
+    let mut req = Request::new(IpAddress::v4(127, 0, 0, 1));
+    req.verb = "GET".to_string();
+    assert_eq!(authorize(&req, "/index.html", &rules), None);
+    assert_eq!(authorize(&req, "/privateer", &rules), None);
+    assert_eq!(authorize(&req, "/private/a.txt", &rules), Some(401));
+    let path = normalize_path("//private/a.txt").unwrap();
+    assert_eq!(path, "/private/a.txt");
+    assert_eq!(authorize(&req, &path, &rules), Some(401));
+    assert_eq!(normalize_path("/./private/a.txt"), None); // Bad Request
+    assert_eq!(normalize_path("/public/../private/a.txt"), None);
+    assert_eq!(normalize_path("/private/"), Some("/private/".into()));
+    assert_eq!(normalize_path("//"), Some("/".into()));
+    assert_eq!(normalize_path("*"), None);
+
+    req.verb = "PUT".to_string();
+    assert_eq!(authorize(&req, "/index.html", &rules), Some(403));
+
+    req.headers.insert(
+        "Authorization".to_string(),
+        "Basic dXNlcjp3cm9uZw==".to_string(), // user:wrong
+    );
+    assert_eq!(basic_auth(&req), Some(("user".into(), "wrong".into())));
+    assert_eq!(authorize(&req, "/private/a.txt", &rules), Some(401));
+}
+
+#[test_case]
+fn test_parse_range() {
+    let range = |start, end| Range::Satisfiable(start, end);
+    assert_eq!(parse_range("bytes=0-99", 1000), range(0, 99));
+    assert_eq!(parse_range("bytes=500-", 1000), range(500, 999));
+    assert_eq!(parse_range("bytes=-100", 1000), range(900, 999));
+    assert_eq!(parse_range("bytes=-2000", 1000), range(0, 999));
+    assert_eq!(parse_range("bytes=900-2000", 1000), range(900, 999));
+    assert_eq!(parse_range("bytes=1000-", 1000), Range::Unsatisfiable);
+    assert_eq!(parse_range("bytes=-0", 1000), Range::Unsatisfiable);
+    assert_eq!(parse_range("bytes=99-0", 1000), Range::Ignored);
+    assert_eq!(parse_range("bytes=0-1,5-6", 1000), Range::Ignored);
+    assert_eq!(parse_range("lines=0-1", 1000), Range::Ignored);
+}
+
+#[test_case]
+fn test_log() {
+    let mut req = Request::new(IpAddress::v4(10, 0, 2, 2));
+    req.verb = "GET".to_string();
+    req.path = "/index.html".to_string();
+    req.version = "HTTP/1.1".to_string();
+    let mut res = Response::new(req);
+    res.code = 200;
+    res.size = 42;
+    let line = res.to_log();
+    assert!(line.starts_with("10.0.2.2 - - ["));
+    assert!(line.ends_with("] \"GET /index.html HTTP/1.1\" 200 42\n"));
+
+    assert_eq!(log_path("/var/log/httpd", 0), "/var/log/httpd");
+    assert_eq!(log_path("/var/log/httpd", 2), "/var/log/httpd.2");
+}
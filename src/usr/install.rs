@@ -38,7 +38,6 @@ pub fn copy_files(verbose: bool) {
     copy_file!("/bin/halt", verbose);
     //copy_file!("/bin/hello", verbose);
     //copy_file!("/bin/mandelbrot", verbose);
-    copy_file!("/bin/ntp", verbose);
     copy_file!("/bin/pkg", verbose);
     copy_file!("/bin/print", verbose);
     copy_file!("/bin/reboot", verbose);
@@ -50,6 +49,7 @@ pub fn copy_files(verbose: bool) {
     create_dir("/dev/clk", verbose); // Clock
     create_dir("/dev/net", verbose); // Network
     create_dir("/dev/vga", verbose);
+    create_dir("/dev/vt", verbose); // Virtual consoles
 
     create_dev("/dev/ata/0/0", "ata-0-0", verbose);
     create_dev("/dev/ata/0/1", "ata-0-1", verbose);
@@ -59,6 +59,7 @@ pub fn copy_files(verbose: bool) {
     create_dev("/dev/clk/epoch", "clk-epoch", verbose);
     create_dev("/dev/clk/rtc", "clk-rtc", verbose);
     create_dev("/dev/console", "console", verbose);
+    create_dev("/dev/mouse", "mouse", verbose);
     create_dev("/dev/net/tcp", "net-tcp", verbose);
     create_dev("/dev/net/udp", "net-udp", verbose);
     create_dev("/dev/net/gw", "net-gw", verbose);
@@ -72,10 +73,18 @@ pub fn copy_files(verbose: bool) {
     create_dev("/dev/vga/font", "vga-font", verbose);
     create_dev("/dev/vga/mode", "vga-mode", verbose);
     create_dev("/dev/vga/palette", "vga-palette", verbose);
+    create_dev("/dev/vt/1", "vt-1", verbose);
+    create_dev("/dev/vt/2", "vt-2", verbose);
+    create_dev("/dev/vt/3", "vt-3", verbose);
+    create_dev("/dev/vt/4", "vt-4", verbose);
 
     copy_file!("/ini/banner.txt", verbose);
     copy_file!("/ini/boot.sh", verbose);
+    copy_file!("/ini/certs.pem", verbose);
+    copy_file!("/ini/filter", verbose);
+    copy_file!("/ini/hosts", verbose);
     copy_file!("/ini/lisp.lsp", verbose);
+    copy_file!("/ini/ntp", verbose);
     copy_file!("/ini/shell.sh", verbose);
     copy_file!("/ini/version.txt", verbose);
 
@@ -84,6 +93,11 @@ pub fn copy_files(verbose: bool) {
     copy_file!("/ini/palettes/gruvbox-dark.sh", verbose);
     copy_file!("/ini/palettes/gruvbox-light.sh", verbose);
 
+    create_dir("/ini/keyboards", verbose);
+    copy_file!("/ini/keyboards/de.map", verbose);
+    copy_file!("/ini/keyboards/es.map", verbose);
+    copy_file!("/ini/keyboards/uk.map", verbose);
+
     create_dir("/ini/fonts", verbose);
     //copy_file!("/ini/fonts/lat15-terminus-8x16.psf", verbose);
     copy_file!("/ini/fonts/zap-light-8x16.psf", verbose);
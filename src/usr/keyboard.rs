@@ -1,6 +1,10 @@
 use crate::api::console::Style;
+use crate::api::fs;
 use crate::api::process::ExitCode;
 use crate::sys;
+use crate::sys::keyboard::Keymap;
+
+use alloc::format;
 
 pub fn main(args: &[&str]) -> Result<(), ExitCode> {
     if args.len() == 1 {
@@ -17,8 +21,7 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
                 if sys::keyboard::set_keyboard(layout) {
                     Ok(())
                 } else {
-                    error!("Unknown keyboard layout");
-                    Err(ExitCode::Failure)
+                    set_keymap(layout)
                 }
             }
         }
@@ -33,6 +36,32 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
     }
 }
 
+// Load a keymap file from its path or from its name in `/ini/keyboards`
+fn set_keymap(layout: &str) -> Result<(), ExitCode> {
+    let path = if fs::exists(layout) {
+        layout.into()
+    } else {
+        format!("/ini/keyboards/{}.map", layout)
+    };
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(_) => {
+            error!("Unknown keyboard layout");
+            return Err(ExitCode::Failure);
+        }
+    };
+    match contents.parse::<Keymap>() {
+        Ok(keymap) => {
+            sys::keyboard::set_keymap(keymap);
+            Ok(())
+        }
+        Err(e) => {
+            error!("{}", e);
+            Err(ExitCode::Failure)
+        }
+    }
+}
+
 fn help() {
     let csi_option = Style::color("aqua");
     let csi_title = Style::color("yellow");
@@ -47,4 +76,11 @@ fn help() {
         "  {0}set <layout>{1}    Set keyboard layout",
         csi_option, csi_reset
     );
+    println!();
+    println!("{}Layouts:{}", csi_title, csi_reset);
+    println!(
+        "  {0}azerty{1}, {0}dvorak{1}, {0}qwerty{1}, or a keymap file from \
+        {0}/ini/keyboards{1}",
+        csi_option, csi_reset
+    );
 }
@@ -21,6 +21,7 @@ struct Game {
     quiet: bool,
     seed_interval: usize,
     seed_population: usize,
+    input: String,
 }
 
 impl Game {
@@ -34,6 +35,7 @@ impl Game {
             quiet: false,
             seed_interval: 1,
             seed_population: 30,
+            input: String::new(),
         }
     }
 
@@ -56,12 +58,11 @@ impl Game {
             if self.seed_interval > 0 && self.step % self.seed_interval == 0 {
                 self.seed();
             }
-            if console::end_of_text() || (self.is_game_over() && self.quiet) {
+            let quit = self.is_game_over() && self.quiet;
+            if quit || self.wait(1.0 / self.speed) {
                 print!("\x1b[2J\x1b[1;1H"); // Clear screen and move to top
                 return;
             }
-            print!("{}", self);
-            sys::clk::sleep(1.0 / self.speed);
             if self.is_game_over() {
                 continue; // Display the screen until ^C is received
             }
@@ -94,6 +95,45 @@ impl Game {
         }
     }
 
+    // Display the screen and wait for the next step while toggling the cells
+    // clicked with the mouse. Return true when ^C is received.
+    fn wait(&mut self, seconds: f64) -> bool {
+        print!("{}", self);
+        let started = sys::clk::boot_time();
+        while sys::clk::boot_time() - started < seconds {
+            while let Some(c) = console::try_read_char() {
+                match c {
+                    console::ETX_KEY => return true,
+                    console::ESC_KEY => {
+                        self.input.clear();
+                        self.input.push(c);
+                    }
+                    'M' | 'm' if self.input.starts_with("\x1b[") => {
+                        let params = &self.input[2..];
+                        let event = api::console::parse_mouse_event(params);
+                        if let Some((0, x, y)) = event.filter(|_| c == 'M') {
+                            if x < self.cols && y < self.rows {
+                                self.toggle((x as i64, y as i64));
+                                print!("{}", self);
+                            }
+                        }
+                        self.input.clear();
+                    }
+                    c if !self.input.is_empty() => self.input.push(c),
+                    _ => {}
+                }
+            }
+            sys::clk::sleep(0.01);
+        }
+        false
+    }
+
+    fn toggle(&mut self, cell: (i64, i64)) {
+        if !self.grid.remove(&cell) {
+            self.grid.insert(cell);
+        }
+    }
+
     pub fn is_game_over(&self) -> bool {
         self.grid.is_empty()
     }
@@ -170,6 +210,7 @@ impl fmt::Display for Game {
 }
 
 pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    api::console::update_size();
     let mut game = Game::new(api::console::cols(), api::console::rows() - 1);
     let mut i = 0;
     let n = args.len();
@@ -235,7 +276,9 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
     print!("\x1b[2J"); // Clear screen
     print!("\x1b[?25l"); // Disable cursor
     print!("\x1b[12l"); // Disable echo
+    print!("\x1b[?1000h\x1b[?1006h"); // Enable mouse reporting
     game.run();
+    print!("\x1b[?1006l\x1b[?1000l"); // Disable mouse reporting
     print!("\x1b[12h"); // Enable echo
     print!("\x1b[?25h"); // Enable cursor
     Ok(())
@@ -111,6 +111,10 @@ pub fn default_env() -> Rc<RefCell<Env>> {
         "shell".to_string(),
         Exp::Primitive(primitive::lisp_shell),
     );
+    data.insert(
+        "getenv".to_string(),
+        Exp::Primitive(primitive::lisp_getenv),
+    );
     data.insert(
         "string".to_string(),
         Exp::Primitive(primitive::lisp_string),
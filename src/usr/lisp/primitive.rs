@@ -6,8 +6,8 @@ use super::{Err, Exp, Number};
 use crate::api;
 use crate::api::regex::Regex;
 use crate::api::syscall;
+use crate::sys;
 use crate::sys::fs::OpenFlag;
-use crate::usr::host;
 use crate::usr::shell;
 use crate::{could_not, ensure_length_eq, ensure_length_gt, expected};
 
@@ -185,6 +185,15 @@ pub fn lisp_shell(args: &[Exp]) -> Result<Exp, Err> {
     }
 }
 
+pub fn lisp_getenv(args: &[Exp]) -> Result<Exp, Err> {
+    ensure_length_eq!(args, 1);
+    let key = string(&args[0])?;
+    match sys::process::env(&key) {
+        Some(val) => Ok(Exp::Str(val)),
+        None => Ok(Exp::List(vec![])),
+    }
+}
+
 pub fn lisp_string(args: &[Exp]) -> Result<Exp, Err> {
     let args: Vec<String> = args.iter().map(|arg| match arg {
         Exp::Str(s) => format!("{}", s),
@@ -657,7 +666,7 @@ pub fn lisp_put(args: &[Exp]) -> Result<Exp, Err> {
 pub fn lisp_host(args: &[Exp]) -> Result<Exp, Err> {
     ensure_length_eq!(args, 1);
     let hostname = string(&args[0])?;
-    match host::resolve(&hostname) {
+    match api::dns::resolve(&hostname) {
         Ok(addr) => Ok(Exp::Str(format!("{}", addr))),
         Err(_) => Ok(Exp::List(vec![])),
     }
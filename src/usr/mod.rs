@@ -6,6 +6,7 @@ pub mod date;
 pub mod decode;
 pub mod delete;
 pub mod dhcp;
+pub mod dhcpd;
 pub mod diff;
 pub mod disk;
 pub mod edit;
@@ -27,6 +28,7 @@ pub mod lisp;
 pub mod list;
 pub mod memory;
 pub mod net;
+pub mod ntp;
 pub mod pci;
 pub mod pi;
 pub mod pow;
@@ -34,8 +36,13 @@ pub mod r#move;
 pub mod read;
 pub mod render;
 pub mod shell;
+pub mod shelld;
+pub mod slaac;
 pub mod socket;
 pub mod tcp;
+pub mod tcpbench;
+pub mod tftp;
+pub mod tftpd;
 pub mod time;
 pub mod user;
 pub mod view;
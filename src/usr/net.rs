@@ -13,12 +13,13 @@ use alloc::borrow::ToOwned;
 use alloc::string::String;
 use alloc::string::ToString;
 use alloc::vec;
+use alloc::vec::Vec;
 use core::str::FromStr;
 use smoltcp::iface::SocketSet;
 use smoltcp::phy::Device;
 use smoltcp::socket::tcp;
 use smoltcp::time::Instant;
-use smoltcp::wire::{EthernetFrame, IpCidr, Ipv4Address, PrettyPrinter};
+use smoltcp::wire::{EthernetFrame, IpAddress, IpCidr, Ipv4Address, PrettyPrinter};
 
 pub fn main(args: &[&str]) -> Result<(), ExitCode> {
     match *args.get(1).unwrap_or(&"") {
@@ -47,6 +48,12 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
         "m" | "monitor" => {
             monitor();
         }
+        "capture" => {
+            return capture(&args[2..]);
+        }
+        "filter" => {
+            return filter(&args[2..]);
+        }
         _ => {
             error!("Invalid command");
             return Err(ExitCode::Failure);
@@ -65,7 +72,9 @@ fn help() {
     );
     println!();
     println!("{}Commands:{}", csi_title, csi_reset);
+    println!("  {}capture{}  Capture packets", csi_option, csi_reset);
     println!("  {}config{}   Configure network", csi_option, csi_reset);
+    println!("  {}filter{}   Filter packets", csi_option, csi_reset);
     println!("  {}monitor{}  Monitor network", csi_option, csi_reset);
     println!(
         "  {}stat{}     Display network status",
@@ -89,6 +98,26 @@ fn help_config() {
     println!("  {}dns{}  Domain Name Servers", csi_option, csi_reset);
 }
 
+fn help_capture() {
+    let csi_option = Style::color("aqua");
+    let csi_title = Style::color("yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} net capture {}<options>{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-o{1}, {0}--output <file>{1}    Write packets to pcap file",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-c{1}, {0}--count <n>{1}        Stop after n packets",
+        csi_option, csi_reset
+    );
+}
+
 fn print_config(attribute: &str) {
     let csi_color = Style::color("aqua");
     let csi_reset = Style::reset();
@@ -112,7 +141,7 @@ fn dns_config() -> Option<String> {
     warning!("This command is deprecated, use /dev/net/dns instead");
     if let Ok(value) = fs::read_to_string(DNS_FILE) {
         let servers = value.trim();
-        if servers.split(',').all(|s| Ipv4Address::from_str(s).is_ok()) {
+        if servers.split(',').all(|s| IpAddress::from_str(s).is_ok()) {
             Some(servers.to_string())
         } else {
             error!("Could not parse '{}'", servers);
@@ -223,7 +252,7 @@ pub fn set_config(attribute: &str, value: &str) {
         "dns" => {
             warning!("This command is deprecated, use /ini/dns instead");
             let servers = value.trim();
-            if servers.split(',').all(|s| Ipv4Address::from_str(s).is_ok()) {
+            if servers.split(',').all(|s| IpAddress::from_str(s).is_ok()) {
                 let s = format!("{}\n", servers);
                 if fs::write(DNS_FILE, s.as_bytes()).is_ok() {
                     log!("NET DNS {}", servers);
@@ -303,3 +332,233 @@ fn monitor() {
         error!("Network error");
     }
 }
+
+fn capture(args: &[&str]) -> Result<(), ExitCode> {
+    let mut path = None;
+    let mut count = None;
+    let n = args.len();
+    let mut i = 0;
+    while i < n {
+        match args[i] {
+            "-h" | "--help" => {
+                help_capture();
+                return Ok(());
+            }
+            "-o" | "--output" if i + 1 < n => {
+                i += 1;
+                path = Some(args[i]);
+            }
+            "-c" | "--count" if i + 1 < n => {
+                i += 1;
+                if let Ok(n) = args[i].parse::<usize>() {
+                    count = Some(n);
+                } else {
+                    error!("Could not parse count");
+                    return Err(ExitCode::UsageError);
+                }
+            }
+            _ => {
+                help_capture();
+                return Err(ExitCode::UsageError);
+            }
+        }
+        i += 1;
+    }
+    let path = match path {
+        Some(path) => path,
+        None => {
+            help_capture();
+            return Err(ExitCode::UsageError);
+        }
+    };
+
+    let handle = match fs::create_file(path) {
+        Some(handle) => handle,
+        None => {
+            error!("Could not write to '{}'", path);
+            return Err(ExitCode::Failure);
+        }
+    };
+    let mut buf = net::capture::pcap_header();
+    let mut packets = 0;
+    let mut res = Ok(());
+    net::capture::start();
+    while count.is_none_or(|count| packets < count) {
+        if console::end_of_text() || console::end_of_transmission() {
+            println!();
+            break;
+        }
+
+        // The lock is released between each poll to let the other sockets
+        // use the network during the capture
+        if let Some((ref mut iface, ref mut device)) = *net::NET.lock() {
+            let ms = (clock::epoch_time() * 1000000.0) as i64;
+            let time = Instant::from_micros(ms);
+            let mut sockets = net::socket::SOCKETS.lock();
+            iface.poll(time, device, &mut sockets);
+        } else {
+            error!("Network error");
+            res = Err(ExitCode::Failure);
+            break;
+        }
+
+        let mut is_idle = true;
+        while let Some(frame) = net::capture::pop() {
+            buf.extend_from_slice(&frame.to_pcap());
+            packets += 1;
+            is_idle = false;
+            if count == Some(packets) {
+                break;
+            }
+        }
+
+        // Write the packets to the file regularly to keep the memory usage
+        // bounded during long captures
+        if buf.len() > CAPTURE_BUFFER_SIZE {
+            if syscall::write(handle, &buf).is_none() {
+                error!("Could not write to '{}'", path);
+                res = Err(ExitCode::Failure);
+                break;
+            }
+            buf.clear();
+        }
+
+        if is_idle {
+            syscall::sleep(0.01);
+        }
+    }
+    net::capture::stop();
+
+    if res.is_ok() && syscall::write(handle, &buf).is_none() {
+        error!("Could not write to '{}'", path);
+        res = Err(ExitCode::Failure);
+    }
+    syscall::close(handle);
+
+    let dropped = net::capture::dropped();
+    if dropped > 0 {
+        warning!("Dropped {} packets", dropped);
+    }
+    if res.is_ok() {
+        println!("Captured {} packets", packets);
+    }
+    res
+}
+
+const CAPTURE_BUFFER_SIZE: usize = 16 << 10; // 16 KB
+
+const FILTER_FILE: &str = "/ini/filter";
+
+fn filter(args: &[&str]) -> Result<(), ExitCode> {
+    match *args.first().unwrap_or(&"list") {
+        "-h" | "--help" => {
+            help_filter();
+        }
+        "list" => {
+            let csi_color = Style::color("aqua");
+            let csi_reset = Style::reset();
+            let rules = net::filter::rules();
+            if rules.is_empty() {
+                println!("No rules");
+                return Ok(());
+            }
+            for (i, rule) in rules.iter().enumerate() {
+                println!(
+                    "{}{:>3}{} {:>8} {}", csi_color, i + 1, csi_reset,
+                    rule.hits, rule
+                );
+            }
+            println!(
+                "{}{:>3}{} {:>8} allow established",
+                csi_color, "*", csi_reset, net::filter::established()
+            );
+        }
+        "load" => {
+            let path = *args.get(1).unwrap_or(&FILTER_FILE);
+            let contents = match fs::read_to_string(path) {
+                Ok(contents) => contents,
+                Err(_) => {
+                    error!("Could not read '{}'", path);
+                    return Err(ExitCode::Failure);
+                }
+            };
+            let mut rules = Vec::new();
+            for (i, line) in contents.lines().enumerate() {
+                let line = line.split('#').next().unwrap_or("").trim();
+                if line.is_empty() {
+                    continue;
+                }
+                match line.parse() {
+                    Ok(rule) => rules.push(rule),
+                    Err(msg) => {
+                        error!("{} at line {} of '{}'", msg, i + 1, path);
+                        return Err(ExitCode::Failure);
+                    }
+                }
+            }
+            net::filter::set_rules(rules);
+        }
+        "add" if args.len() > 1 => {
+            match args[1..].join(" ").parse() {
+                Ok(rule) => net::filter::add_rule(rule),
+                Err(msg) => {
+                    error!("{}", msg);
+                    return Err(ExitCode::UsageError);
+                }
+            }
+        }
+        "delete" if args.len() == 2 => {
+            let i = args[1].parse::<usize>().unwrap_or(0);
+            if i == 0 || !net::filter::delete_rule(i - 1) {
+                error!("Could not find rule '{}'", args[1]);
+                return Err(ExitCode::Failure);
+            }
+        }
+        "clear" => {
+            net::filter::set_rules(Vec::new());
+        }
+        _ => {
+            help_filter();
+            return Err(ExitCode::UsageError);
+        }
+    }
+    Ok(())
+}
+
+fn help_filter() {
+    let csi_option = Style::color("aqua");
+    let csi_title = Style::color("yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} net filter {}<command>{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Commands:{}", csi_title, csi_reset);
+    println!(
+        "  {0}list{1}                 List rules with hit counters",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}load [<file>]{1}        Load rules from {0}{2}{1}",
+        csi_option, csi_reset, FILTER_FILE
+    );
+    println!(
+        "  {0}add <rule>{1}           Add rule",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}delete <number>{1}      Delete rule",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}clear{1}                Delete all rules",
+        csi_option, csi_reset
+    );
+    println!();
+    println!("{}Rules:{}", csi_title, csi_reset);
+    println!(
+        "  {0}<allow|deny> <tcp|udp|icmp|any> [from <cidr>] [port <ports>]{1}",
+        csi_option, csi_reset
+    );
+}
@@ -0,0 +1,237 @@
+use crate::api::clock::{self, DATE_TIME, DATE_TIME_ZONE};
+use crate::api::console::Style;
+use crate::api::dns;
+use crate::api::fs;
+use crate::api::process::ExitCode;
+use crate::api::syscall;
+use crate::api::time;
+use crate::sys::fs::OpenFlag;
+
+use alloc::string::ToString;
+use core::convert::TryInto;
+use core::str::FromStr;
+use smoltcp::wire::IpAddress;
+
+// See https://www.rfc-editor.org/rfc/rfc4330
+
+const CONFIG: &str = "/ini/ntp";
+const DEFAULT_SERVER: &str = "time.cloudflare.com";
+const NTP_PORT: u16 = 123;
+const PACKET_LEN: usize = 48;
+
+// Seconds between the NTP epoch (1900) and the Unix epoch (1970)
+const NTP_UNIX_OFFSET: f64 = 2208988800.0;
+
+const MODE_CLIENT: u8 = 3;
+const MODE_SERVER: u8 = 4;
+const VERSION: u8 = 4;
+
+#[derive(Debug, PartialEq)]
+struct Response {
+    stratum: u8,
+    originate: [u8; 8],
+    receive: f64,
+    transmit: f64,
+}
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut set = false;
+    let mut verbose = false;
+    let mut server = None;
+    for arg in &args[1..] {
+        match *arg {
+            "-h" | "--help" => {
+                help();
+                return Ok(());
+            }
+            "-s" | "--set" => {
+                set = true;
+            }
+            "-v" | "--verbose" => {
+                verbose = true;
+            }
+            _ if server.is_none() && !arg.starts_with('-') => {
+                server = Some(arg.to_string());
+            }
+            _ => {
+                help();
+                return Err(ExitCode::UsageError);
+            }
+        }
+    }
+    let server = server.unwrap_or_else(|| {
+        match fs::read_to_string(CONFIG) {
+            Ok(contents) if !contents.trim().is_empty() => {
+                contents.trim().to_string()
+            }
+            _ => DEFAULT_SERVER.to_string(),
+        }
+    });
+
+    let addr = if let Ok(addr) = IpAddress::from_str(&server) {
+        addr
+    } else {
+        match dns::resolve(&server) {
+            Ok(addr) => addr,
+            Err(e) => {
+                error!("Could not resolve host: {:?}", e);
+                return Err(ExitCode::Failure);
+            }
+        }
+    };
+    if verbose {
+        debug!("Querying {}:{}", addr, NTP_PORT);
+    }
+
+    let (offset, delay) = match query(addr) {
+        Some(res) => res,
+        None => {
+            error!("Could not get time from {}", server);
+            return Err(ExitCode::Failure);
+        }
+    };
+    if verbose {
+        debug!("Offset: {:.6} s", offset);
+        debug!("Delay:  {:.6} s", delay);
+    }
+
+    if set {
+        // Wait for the beginning of the next second because the RTC cannot
+        // be set with a better precision.
+        let now = clock::epoch_time() + offset;
+        syscall::sleep(libm::ceil(now) - now);
+        let ts = libm::round(clock::epoch_time() + offset) as i64;
+        let date = time::from_timestamp_utc(ts).format(DATE_TIME);
+        if fs::write("/dev/clk/rtc", date.as_bytes()).is_err() {
+            error!("Could not set RTC");
+            return Err(ExitCode::Failure);
+        }
+    } else {
+        let ts = (clock::epoch_time() + offset) as i64;
+        println!("{}", time::from_timestamp(ts).format(DATE_TIME_ZONE));
+    }
+    Ok(())
+}
+
+// Return the offset of the local clock and the round trip delay
+fn query(addr: IpAddress) -> Option<(f64, f64)> {
+    let flags = OpenFlag::Device as u8;
+    let handle = syscall::open("/dev/net/udp", flags)?;
+    if syscall::connect(handle, addr, NTP_PORT).is_err() {
+        syscall::close(handle);
+        return None;
+    }
+
+    let t1 = clock::epoch_time();
+    let req = request(t1);
+    let mut buf = [0; PACKET_LEN];
+    let res = if syscall::write(handle, &req).is_some() {
+        syscall::read(handle, &mut buf)
+    } else {
+        None
+    };
+    let t4 = clock::epoch_time();
+    syscall::close(handle);
+
+    let res = parse_response(&buf[0..res?])?;
+    if res.stratum == 0 || res.originate != req[40..48] {
+        return None; // Kiss-o'-Death packet or unexpected response
+    }
+    Some(offset_and_delay(t1, res.receive, res.transmit, t4))
+}
+
+fn request(time: f64) -> [u8; PACKET_LEN] {
+    let mut buf = [0; PACKET_LEN];
+    buf[0] = (VERSION << 3) | MODE_CLIENT;
+    buf[40..48].copy_from_slice(&to_timestamp(time));
+    buf
+}
+
+fn parse_response(buf: &[u8]) -> Option<Response> {
+    if buf.len() < PACKET_LEN || buf[0] & 7 != MODE_SERVER {
+        return None;
+    }
+    Some(Response {
+        stratum: buf[1],
+        originate: buf[24..32].try_into().unwrap(),
+        receive: from_timestamp(&buf[32..40]),
+        transmit: from_timestamp(&buf[40..48]),
+    })
+}
+
+fn offset_and_delay(t1: f64, t2: f64, t3: f64, t4: f64) -> (f64, f64) {
+    let offset = ((t2 - t1) + (t3 - t4)) / 2.0;
+    let delay = (t4 - t1) - (t3 - t2);
+    (offset, delay)
+}
+
+// Convert a Unix time into a 64-bit NTP timestamp
+fn to_timestamp(time: f64) -> [u8; 8] {
+    let time = time + NTP_UNIX_OFFSET;
+    let secs = time as u64 as u32; // Wrap around at the end of the era
+    let frac = ((time - libm::floor(time)) * 4294967296.0) as u32;
+    let mut buf = [0; 8];
+    buf[0..4].copy_from_slice(&secs.to_be_bytes());
+    buf[4..8].copy_from_slice(&frac.to_be_bytes());
+    buf
+}
+
+// Convert a 64-bit NTP timestamp into a Unix time
+fn from_timestamp(buf: &[u8]) -> f64 {
+    let secs = u32::from_be_bytes(buf[0..4].try_into().unwrap()) as f64;
+    let frac = u32::from_be_bytes(buf[4..8].try_into().unwrap()) as f64;
+    let mut time = secs + frac / 4294967296.0 - NTP_UNIX_OFFSET;
+    if time < 0.0 {
+        time += 4294967296.0; // Era 1 starting in 2036
+    }
+    time
+}
+
+fn help() {
+    let csi_option = Style::color("aqua");
+    let csi_title = Style::color("yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} ntp {}<options> [<server>]{1}",
+        csi_title, csi_reset, csi_option
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-s{1}, {0}--set{1}        Set the real-time clock",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-v{1}, {0}--verbose{1}    Print offset and delay",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_ntp_timestamp() {
+    let time = 1679392800.5; // 2023-03-21 10:00:00.5
+    let buf = to_timestamp(time);
+    assert_eq!(buf[0..4], 3888381600u32.to_be_bytes());
+    assert_eq!(buf[4..8], 0x80000000u32.to_be_bytes());
+    assert_eq!(from_timestamp(&buf), time);
+
+    let req = request(time);
+    assert_eq!(req[0], 0x23);
+    assert!(parse_response(&req).is_none());
+
+    let mut res = req;
+    res[0] = 0x24;
+    res[1] = 2;
+    res[24..32].copy_from_slice(&to_timestamp(time));
+    res[32..40].copy_from_slice(&to_timestamp(time + 10.1));
+    res[40..48].copy_from_slice(&to_timestamp(time + 10.2));
+    let res = parse_response(&res).unwrap();
+    assert_eq!(res.stratum, 2);
+    assert_eq!(res.originate, req[40..48]);
+
+    let (offset, delay) = offset_and_delay(
+        time, res.receive, res.transmit, time + 0.3
+    );
+    assert!(libm::fabs(offset - 10.0) < 0.001);
+    assert!(libm::fabs(delay - 0.2) < 0.001);
+}
@@ -66,6 +66,11 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
                     let path = "/".to_owned() + &parts[4..].join("/");
                     usr::http::main(&["http", host, &path])
                 }
+                "https" => {
+                    let host = format!("https://{}", parts[3]);
+                    let path = "/".to_owned() + &parts[4..].join("/");
+                    usr::http::main(&["http", &host, &path])
+                }
                 _ => {
                     error!("Unknown protocol '{}'", parts[2]);
                     Err(ExitCode::Failure)
@@ -3,14 +3,12 @@ use crate::api::fs;
 use crate::api::io;
 use crate::api::process::ExitCode;
 use crate::api::vga;
+use crate::api::vga::graphics::{Canvas, HEIGHT, WIDTH};
 
 use alloc::vec::Vec;
 use alloc::string::{String, ToString};
 use core::mem::size_of;
 
-const WIDTH: usize = 320;
-const HEIGHT: usize = 200;
-
 #[derive(Debug)]
 #[repr(C, packed)]
 struct BmpHeader {
@@ -173,10 +171,11 @@ fn render_bmp(path: &str, config: &mut Config) -> Result<Command, ExitCode> {
                 }
 
                 // Display image
-                let dev = "/dev/vga/buffer";
-                if !fs::is_device(dev) || fs::write(dev, &img).is_err() {
+                let mut canvas = Canvas::new(WIDTH, HEIGHT);
+                canvas.blit(0, 0, width, &img);
+                if !canvas.present() {
                     config.text_mode();
-                    error!("Could not write to '{}'", dev);
+                    error!("Could not write to '/dev/vga/buffer'");
                     return Err(ExitCode::Failure);
                 }
 
@@ -14,12 +14,13 @@ use alloc::vec::Vec;
 use core::sync::atomic::{fence, Ordering};
 
 // TODO: Scan /bin
-const AUTOCOMPLETE_COMMANDS: [&str; 40] = [
+const AUTOCOMPLETE_COMMANDS: [&str; 47] = [
     "2048", "calc", "chess", "copy", "date", "decode", "delete", "dhcp",
-    "diff", "disk", "edit", "elf", "encode", "env", "goto", "hash", "help",
-    "hex", "host", "http", "httpd", "install", "keyboard", "life", "lisp",
-    "list", "memory", "move", "net", "pci", "quit", "read", "render", "shell",
-    "socket", "tcp", "time", "user", "view", "write",
+    "dhcpd", "diff", "disk", "edit", "elf", "encode", "env", "goto", "hash",
+    "help", "hex", "host", "http", "httpd", "install", "keyboard", "life",
+    "lisp", "list", "memory", "move", "net", "ntp", "pci", "quit", "read",
+    "render", "shell", "shelld", "slaac", "socket", "tcp", "tcpbench", "tftp",
+    "tftpd", "time", "user", "view", "write",
 ];
 
 struct Config {
@@ -524,6 +525,7 @@ fn dispatch(args: &[&str], config: &mut Config) -> Result<(), ExitCode> {
         "decode"   => usr::decode::main(args),
         "delete"   => usr::delete::main(args),
         "dhcp"     => usr::dhcp::main(args),
+        "dhcpd"    => usr::dhcpd::main(args),
         "diff"     => usr::diff::main(args),
         "disk"     => usr::disk::main(args),
         "edit"     => usr::edit::main(args),
@@ -548,6 +550,7 @@ fn dispatch(args: &[&str], config: &mut Config) -> Result<(), ExitCode> {
         "memory"   => usr::memory::main(args),
         "move"     => usr::r#move::main(args),
         "net"      => usr::net::main(args),
+        "ntp"      => usr::ntp::main(args),
         "pci"      => usr::pci::main(args),
         "pi"       => usr::pi::main(args),
         "quit"     => Err(ExitCode::ShellExit),
@@ -555,8 +558,13 @@ fn dispatch(args: &[&str], config: &mut Config) -> Result<(), ExitCode> {
         "render"   => usr::render::main(args),
         "set"      => cmd_set(args, config),
         "shell"    => usr::shell::main(args),
+        "shelld"   => usr::shelld::main(args),
+        "slaac"    => usr::slaac::main(args),
         "socket"   => usr::socket::main(args),
         "tcp"      => usr::tcp::main(args),
+        "tcpbench" => usr::tcpbench::main(args),
+        "tftp"     => usr::tftp::main(args),
+        "tftpd"    => usr::tftpd::main(args),
         "time"     => usr::time::main(args),
         "unalias"  => cmd_unalias(args, config),
         "unset"    => cmd_unset(args, config),
@@ -627,6 +635,7 @@ fn spawn(
 fn repl(config: &mut Config) -> Result<(), ExitCode> {
     println!();
 
+    api::console::update_size();
     let mut prompt = Prompt::new();
     let history_file = "~/.shell-history";
     prompt.history.load(history_file);
@@ -0,0 +1,174 @@
+use crate::api::console::Style;
+use crate::api::fs;
+use crate::api::io;
+use crate::api::process::ExitCode;
+use crate::api::syscall;
+use crate::sys;
+use crate::sys::console;
+use crate::sys::fs::{Device, OpenFlag, Resource};
+use crate::sys::net::socket::telnet::TelnetSocket;
+use crate::usr;
+
+use alloc::format;
+use alloc::string::{String, ToString};
+
+const MAX_LOGIN_ATTEMPTS: usize = 3;
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let csi_color = Style::color("yellow");
+    let csi_reset = Style::reset();
+    let mut port = 23;
+    let mut i = 1;
+    let n = args.len();
+    while i < n {
+        match args[i] {
+            "-h" | "--help" => {
+                usage();
+                return Ok(());
+            }
+            "-p" | "--port" => {
+                if i + 1 < n {
+                    i += 1;
+                    port = args[i].parse().unwrap_or(port);
+                } else {
+                    error!("Missing port number");
+                    return Err(ExitCode::UsageError);
+                }
+            }
+            _ => {
+                usage();
+                return Err(ExitCode::UsageError);
+            }
+        }
+        i += 1;
+    }
+
+    println!(
+        "{}Shell Server listening on 0.0.0.0:{}{}",
+        csi_color, port, csi_reset
+    );
+
+    let flags = OpenFlag::Device as u8;
+    loop {
+        let handle = match syscall::open("/dev/net/tcp", flags) {
+            Some(handle) => handle,
+            None => {
+                error!("Could not open '/dev/net/tcp'");
+                return Err(ExitCode::Failure);
+            }
+        };
+        if syscall::listen(handle, port).is_err() {
+            error!("Could not listen to 0.0.0.0:{}", port);
+            syscall::close(handle);
+            return Err(ExitCode::Failure);
+        }
+
+        // Accept times out after a few seconds without a connection
+        let addr = loop {
+            if console::end_of_text() || console::end_of_transmission() {
+                println!();
+                syscall::close(handle);
+                return Ok(());
+            }
+            if let Ok(addr) = syscall::accept(handle) {
+                break addr;
+            }
+        };
+
+        println!("{}Connection from {}{}", csi_color, addr, csi_reset);
+        session(handle);
+        syscall::close(handle);
+        println!("{}Connection closed{}", csi_color, csi_reset);
+    }
+}
+
+// Bind the standard handles to the socket for the duration of the session
+fn session(handle: usize) {
+    let socket = match sys::process::handle(handle).map(|file| *file) {
+        Some(Resource::Device(Device::TcpSocket(socket))) => socket,
+        _ => return,
+    };
+    let mut telnet = TelnetSocket::new(socket);
+    if !telnet.negotiate() {
+        return;
+    }
+    for i in 0..3 {
+        let file = Resource::Device(Device::TelnetSocket(telnet.clone()));
+        sys::process::update_handle(i, file);
+    }
+
+    if let Some(username) = login() {
+        let user = sys::process::user();
+        let dir = sys::process::dir();
+        let vars = ["USER", "HOME"].map(|key| (key, sys::process::env(key)));
+
+        let home = format!("/usr/{}", username);
+        sys::process::set_user(&username);
+        sys::process::set_dir(&home);
+        sys::process::set_env("USER", &username);
+        sys::process::set_env("HOME", &home);
+
+        usr::shell::main(&["shell"]).ok();
+
+        match user {
+            Some(user) => sys::process::set_user(&user),
+            None => sys::process::delete_user(),
+        }
+        sys::process::set_dir(&dir);
+        for (key, val) in vars {
+            match val {
+                Some(val) => sys::process::set_env(key, &val),
+                None => sys::process::delete_env(key),
+            }
+        }
+    }
+
+    for i in 0..3 {
+        fs::reopen("/dev/console", i, false).ok();
+    }
+}
+
+fn login() -> Option<String> {
+    for _ in 0..MAX_LOGIN_ATTEMPTS {
+        print!("Username: ");
+        let username = read_line()?;
+        print!("Password: ");
+        print!("\x1b[12l"); // Disable echo
+        let password = read_line()?;
+        print!("\x1b[12h"); // Enable echo
+        println!();
+        if usr::user::check_password(&username, &password) {
+            return Some(username);
+        }
+        syscall::sleep(1.0);
+        println!("Login incorrect");
+        println!();
+    }
+    None
+}
+
+// Return None when the connection is closed before the end of the line
+fn read_line() -> Option<String> {
+    let line = io::stdin().read_line();
+    if line.ends_with('\n') {
+        Some(line.trim_end().to_string())
+    } else {
+        None
+    }
+}
+
+fn usage() {
+    let csi_option = Style::color("aqua");
+    let csi_title = Style::color("yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} shelld {}<options>{1}",
+        csi_title, csi_reset, csi_option
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-p{1}, {0}--port <number>{1}    Listen to a specific port",
+        csi_option, csi_reset
+    );
+}
@@ -0,0 +1,292 @@
+use crate::api::clock;
+use crate::api::console::Style;
+use crate::api::fs;
+use crate::api::process::ExitCode;
+use crate::api::syscall;
+use crate::sys::console;
+use crate::sys::net;
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use core::str::FromStr;
+use smoltcp::iface::SocketSet;
+use smoltcp::phy::ChecksumCapabilities;
+use smoltcp::socket::raw;
+use smoltcp::time::Instant;
+use smoltcp::wire::{
+    HardwareAddress, Icmpv6Packet, Icmpv6Repr, IpAddress, IpProtocol,
+    IpVersion, Ipv6Address, Ipv6Packet, Ipv6Repr, NdiscRepr,
+};
+
+// See RFC 4861 and RFC 4862 for implementation details
+
+const ALL_ROUTERS: Ipv6Address = Ipv6Address::new(0xFF02, 0, 0, 0, 0, 0, 0, 2);
+const ROUTER_ADVERT: u8 = 134;
+const OPTION_PREFIX_INFO: u8 = 3;
+const OPTION_RDNSS: u8 = 25; // See RFC 8106
+const PREFIX_FLAG_AUTONOMOUS: u8 = 0x40;
+const SOLICITATION_INTERVAL: f64 = 4.0;
+
+#[derive(Debug, PartialEq)]
+struct RouterAdvert {
+    router: Option<Ipv6Address>,
+    prefix: Option<(Ipv6Address, u8)>,
+    dns: Vec<Ipv6Address>,
+}
+
+impl RouterAdvert {
+    // Parse an ICMPv6 router advertisement message sent from `src`
+    pub fn parse(src: Ipv6Address, buf: &[u8]) -> Option<Self> {
+        if buf.len() < 16 || buf[0] != ROUTER_ADVERT {
+            return None;
+        }
+        let lifetime = u16::from_be_bytes(buf[6..8].try_into().ok()?);
+        let router = if lifetime > 0 { Some(src) } else { None };
+        let mut prefix = None;
+        let mut dns = Vec::new();
+        let mut i = 16;
+        while i + 2 <= buf.len() {
+            let len = (buf[i + 1] as usize) * 8;
+            if len == 0 || i + len > buf.len() {
+                break;
+            }
+            let opt = &buf[i..(i + len)];
+            match opt[0] {
+                OPTION_PREFIX_INFO if len == 32 => {
+                    let prefix_len = opt[2];
+                    let is_autonomous = opt[3] & PREFIX_FLAG_AUTONOMOUS != 0;
+                    if is_autonomous && prefix_len == 64 && prefix.is_none() {
+                        let octets: [u8; 16] = opt[16..32].try_into().ok()?;
+                        let addr = Ipv6Address::from_octets(octets);
+                        prefix = Some((addr, prefix_len));
+                    }
+                }
+                OPTION_RDNSS if len >= 24 => {
+                    for chunk in opt[8..].chunks_exact(16) {
+                        let octets: [u8; 16] = chunk.try_into().ok()?;
+                        dns.push(Ipv6Address::from_octets(octets));
+                    }
+                }
+                _ => {}
+            }
+            i += len;
+        }
+        Some(Self { router, prefix, dns })
+    }
+}
+
+// Combine a 64 bits prefix with the interface identifier of the MAC address
+fn global_addr(prefix: Ipv6Address, iid: [u8; 8]) -> Ipv6Address {
+    let mut octets = prefix.octets();
+    octets[8..16].copy_from_slice(&iid);
+    Ipv6Address::from_octets(octets)
+}
+
+fn router_solicitation(src: Ipv6Address, mac: HardwareAddress) -> Vec<u8> {
+    let icmp_repr = Icmpv6Repr::Ndisc(NdiscRepr::RouterSolicit {
+        lladdr: Some(mac.into()),
+    });
+    let ip_repr = Ipv6Repr {
+        src_addr: src,
+        dst_addr: ALL_ROUTERS,
+        next_header: IpProtocol::Icmpv6,
+        payload_len: icmp_repr.buffer_len(),
+        hop_limit: 255,
+    };
+    let n = ip_repr.buffer_len();
+    let mut buf = vec![0; n + icmp_repr.buffer_len()];
+    ip_repr.emit(&mut Ipv6Packet::new_unchecked(&mut buf));
+    icmp_repr.emit(
+        &src,
+        &ALL_ROUTERS,
+        &mut Icmpv6Packet::new_unchecked(&mut buf[n..]),
+        &ChecksumCapabilities::default(),
+    );
+    buf
+}
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut verbose = false;
+    let advert;
+    let iid;
+
+    for arg in args {
+        match *arg {
+            "-h" | "--help" => return help(),
+            "-v" | "--verbose" => verbose = true,
+            _ => {}
+        }
+    }
+
+    if let Some((ref mut iface, ref mut device)) = *net::NET.lock() {
+        let mac = iface.hardware_addr();
+        iid = match mac {
+            HardwareAddress::Ethernet(mac) => net::interface_id(mac),
+        };
+        let src = iface.ip_addrs().iter().find_map(|cidr| {
+            match cidr.address() {
+                IpAddress::Ipv6(addr) if addr.is_unicast_link_local() => {
+                    Some(addr)
+                }
+                _ => None,
+            }
+        });
+        let src = match src {
+            Some(addr) => addr,
+            None => {
+                error!("Could not find link-local address");
+                return Err(ExitCode::Failure);
+            }
+        };
+
+        let rx_buffer = raw::PacketBuffer::new(
+            vec![raw::PacketMetadata::EMPTY; 4], vec![0; 2048]
+        );
+        let tx_buffer = raw::PacketBuffer::new(
+            vec![raw::PacketMetadata::EMPTY; 1], vec![0; 256]
+        );
+        let raw_socket = raw::Socket::new(
+            IpVersion::Ipv6, IpProtocol::Icmpv6, rx_buffer, tx_buffer
+        );
+        let mut sockets = SocketSet::new(vec![]);
+        let raw_handle = sockets.add(raw_socket);
+
+        let timeout = 30.0;
+        let started = clock::epoch_time();
+        let mut solicited = 0.0;
+        loop {
+            if clock::epoch_time() - started > timeout {
+                error!("Timeout reached");
+                return Err(ExitCode::Failure);
+            }
+            if console::end_of_text() || console::end_of_transmission() {
+                eprintln!();
+                return Err(ExitCode::Failure);
+            }
+
+            let socket = sockets.get_mut::<raw::Socket>(raw_handle);
+            if clock::epoch_time() - solicited > SOLICITATION_INTERVAL {
+                let packet = router_solicitation(src, mac);
+                if socket.send_slice(&packet).is_ok() {
+                    if verbose {
+                        debug!("SLAAC Router Solicitation transmitted");
+                    }
+                    solicited = clock::epoch_time();
+                }
+            }
+
+            let ms = (clock::epoch_time() * 1000000.0) as i64;
+            let time = Instant::from_micros(ms);
+            iface.poll(time, device, &mut sockets);
+
+            let socket = sockets.get_mut::<raw::Socket>(raw_handle);
+            let mut found = None;
+            while let Ok(buf) = socket.recv() {
+                if let Ok(packet) = Ipv6Packet::new_checked(buf) {
+                    if packet.hop_limit() != 255 {
+                        continue; // The RA was forwarded by a router
+                    }
+                    let src = packet.src_addr();
+                    if let Some(ra) = RouterAdvert::parse(src, packet.payload()) {
+                        if ra.prefix.is_some() {
+                            found = Some(ra);
+                            break;
+                        }
+                    }
+                }
+            }
+            if let Some(ra) = found {
+                if verbose {
+                    debug!("SLAAC Router Advertisement received");
+                }
+                advert = ra;
+                break;
+            }
+
+            if let Some(delay) = iface.poll_delay(time, &sockets) {
+                let d = (delay.total_micros() as f64) / 1000000.0;
+                syscall::sleep(d.min(0.1)); // Don't sleep longer than 0.1s
+            } else {
+                syscall::sleep(0.1);
+            }
+        }
+    } else {
+        error!("Network Error");
+        return Err(ExitCode::Failure);
+    }
+
+    if let Some((prefix, prefix_len)) = advert.prefix {
+        let ip = format!("{}/{}", global_addr(prefix, iid), prefix_len);
+        fs::write("/dev/net/ip", ip.as_bytes()).ok();
+    }
+
+    if let Some(gw) = advert.router {
+        fs::write("/dev/net/gw", gw.to_string().as_bytes()).ok();
+    }
+
+    if !advert.dns.is_empty() {
+        // Keep the existing servers and add the new ones after them
+        let mut servers: Vec<String> = fs::read_to_string("/ini/dns").
+            unwrap_or_default().trim().split(',').
+            filter(|s| IpAddress::from_str(s).is_ok()).
+            map(|s| s.to_string()).collect();
+        for addr in advert.dns {
+            let addr = addr.to_string();
+            if !servers.contains(&addr) {
+                servers.push(addr);
+            }
+        }
+        let servers = servers.join(",");
+        if fs::write("/ini/dns", servers.as_bytes()).is_ok() {
+            log!("NET DNS {}", servers);
+        }
+    }
+    Ok(())
+}
+
+fn help() -> Result<(), ExitCode> {
+    let csi_option = Style::color("aqua");
+    let csi_title = Style::color("yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} slaac {}<options>{1}",
+        csi_title, csi_reset, csi_option
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-v{1}, {0}--verbose{1}              Increase verbosity",
+        csi_option, csi_reset
+    );
+    Ok(())
+}
+
+#[test_case]
+fn test_router_advert() {
+    let src = Ipv6Address::new(0xFE80, 0, 0, 0, 0, 0, 0, 2);
+    let buf = [
+        // Header
+        134, 0, 0, 0, 64, 0, 0x07, 0x08, 0, 0, 0, 0, 0, 0, 0, 0,
+        // Prefix information
+        3, 4, 64, 0xC0, 0, 0, 0x0E, 0x10, 0, 0, 0x0E, 0x10, 0, 0, 0, 0,
+        0xFE, 0xC0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        // Recursive DNS server
+        25, 3, 0, 0, 0, 0, 0x0E, 0x10,
+        0xFE, 0xC0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3,
+    ];
+    let ra = RouterAdvert::parse(src, &buf).unwrap();
+    let prefix = Ipv6Address::new(0xFEC0, 0, 0, 0, 0, 0, 0, 0);
+    let dns = Ipv6Address::new(0xFEC0, 0, 0, 0, 0, 0, 0, 3);
+    assert_eq!(ra.router, Some(src));
+    assert_eq!(ra.prefix, Some((prefix, 64)));
+    assert_eq!(ra.dns, vec![dns]);
+
+    let iid = [0x50, 0x54, 0x00, 0xFF, 0xFE, 0x12, 0x34, 0x56];
+    assert_eq!(
+        global_addr(prefix, iid),
+        Ipv6Address::new(0xFEC0, 0, 0, 0, 0x5054, 0x00FF, 0xFE12, 0x3456)
+    );
+}
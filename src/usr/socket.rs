@@ -1,4 +1,5 @@
 use crate::api::console::Style;
+use crate::api::dns;
 use crate::api::fs::IO;
 use crate::api::io;
 use crate::api::process::ExitCode;
@@ -6,7 +7,6 @@ use crate::api::syscall;
 use crate::sys::console;
 use crate::sys::fs::OpenFlag;
 use crate::sys::net::SocketStatus;
-use crate::usr;
 
 use alloc::format;
 use alloc::vec;
@@ -43,7 +43,7 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
         help();
         return Err(ExitCode::UsageError);
     }
-    let (host, port) = match args[1].split_once(':') {
+    let (host, port) = match split_host_port(args[1]) {
         Some((h, p)) => (h, p),
         None => ("0.0.0.0", args[1]),
     };
@@ -54,10 +54,10 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
             return Err(ExitCode::UsageError);
         }
     };
-    let addr = if host.ends_with(char::is_numeric) {
-        IpAddress::from_str(host).expect("invalid address format")
+    let addr = if let Ok(ip_addr) = IpAddress::from_str(host) {
+        ip_addr
     } else {
-        match usr::host::resolve(host) {
+        match dns::resolve(host) {
             Ok(ip_addr) => ip_addr,
             Err(e) => {
                 error!("Could not resolve host: {:?}", e);
@@ -146,6 +146,19 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
     }
 }
 
+// Split "<host>:<port>" with IPv6 addresses enclosed in square brackets like
+// "[::1]:80"
+pub fn split_host_port(s: &str) -> Option<(&str, &str)> {
+    let (host, port) = s.rsplit_once(':')?;
+    if let Some(host) = host.strip_prefix('[') {
+        host.strip_suffix(']').map(|host| (host, port))
+    } else if host.contains(':') {
+        None
+    } else {
+        Some((host, port))
+    }
+}
+
 fn is_closed(status: u8) -> bool {
     !status.get_bit(SocketStatus::MayRecv as usize)
 }
@@ -173,3 +186,14 @@ fn help() {
         csi_option, csi_reset
     );
 }
+
+#[test_case]
+fn test_split_host_port() {
+    assert_eq!(split_host_port("example.com:80"), Some(("example.com", "80")));
+    assert_eq!(split_host_port("10.0.2.2:80"), Some(("10.0.2.2", "80")));
+    assert_eq!(split_host_port("[::1]:80"), Some(("::1", "80")));
+    assert_eq!(split_host_port("[fe80::2]:8080"), Some(("fe80::2", "8080")));
+    assert_eq!(split_host_port("::1"), None);
+    assert_eq!(split_host_port("[::1"), None);
+    assert_eq!(split_host_port("80"), None);
+}
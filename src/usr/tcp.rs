@@ -1,4 +1,5 @@
 use crate::api::console::Style;
+use crate::api::dns;
 use crate::api::process::ExitCode;
 use crate::api::syscall;
 use crate::sys::console;
@@ -27,7 +28,7 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
         return Err(ExitCode::UsageError);
     }
 
-    let (host, port) = match args[1].split_once(':') {
+    let (host, port) = match usr::socket::split_host_port(args[1]) {
         Some((h, p)) => (h, p),
         None => {
             help();
@@ -41,10 +42,10 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
             return Err(ExitCode::UsageError);
         }
     };
-    let addr = if host.ends_with(char::is_numeric) {
-        IpAddress::from_str(host).expect("invalid address format")
+    let addr = if let Ok(ip_addr) = IpAddress::from_str(host) {
+        ip_addr
     } else {
-        match usr::host::resolve(host) {
+        match dns::resolve(host) {
             Ok(ip_addr) => ip_addr,
             Err(e) => {
                 error!("Could not resolve host {:?}", e);
@@ -0,0 +1,260 @@
+use crate::api::clock;
+use crate::api::console::Style;
+use crate::api::dns;
+use crate::api::process::ExitCode;
+use crate::api::syscall;
+use crate::sys::console;
+use crate::sys::fs::OpenFlag;
+use crate::sys::net::{SocketOption, SocketStatus};
+use crate::usr::socket::split_host_port;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use bit_field::BitField;
+use core::str::FromStr;
+use smoltcp::wire::IpAddress;
+
+const DEFAULT_PORT: u16 = 5001;
+const DEFAULT_SIZE: usize = 4 << 20;
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut listen = false;
+    let mut buffer_size = None;
+    let mut size = DEFAULT_SIZE;
+    let mut target = None;
+    let mut i = 1;
+    let n = args.len();
+    while i < n {
+        match args[i] {
+            "-h" | "--help" => {
+                help();
+                return Ok(());
+            }
+            "-l" | "--listen" => {
+                listen = true;
+            }
+            "-b" | "--buffer" | "-n" | "--bytes" if i + 1 < n => {
+                let bytes = match parse_size(args[i + 1]) {
+                    Some(bytes) => bytes,
+                    None => {
+                        error!("Could not parse size '{}'", args[i + 1]);
+                        return Err(ExitCode::UsageError);
+                    }
+                };
+                match args[i] {
+                    "-b" | "--buffer" => buffer_size = Some(bytes),
+                    _ => size = bytes,
+                }
+                i += 1;
+            }
+            arg if target.is_none() && !arg.starts_with('-') => {
+                target = Some(arg);
+            }
+            _ => {
+                help();
+                return Err(ExitCode::UsageError);
+            }
+        }
+        i += 1;
+    }
+    let target = match target {
+        Some(target) => target,
+        None if listen => "",
+        None => {
+            help();
+            return Err(ExitCode::UsageError);
+        }
+    };
+    let (host, port) = match split_host_port(target) {
+        Some((host, port)) => (host, port),
+        None if listen => ("0.0.0.0", target),
+        None => (target, ""),
+    };
+    let port = if port.is_empty() {
+        DEFAULT_PORT
+    } else if let Ok(port) = port.parse() {
+        port
+    } else {
+        error!("Could not parse port '{}'", port);
+        return Err(ExitCode::UsageError);
+    };
+
+    let flags = OpenFlag::Device as u8;
+    let handle = match syscall::open("/dev/net/tcp", flags) {
+        Some(handle) => handle,
+        None => {
+            error!("Could not open '/dev/net/tcp'");
+            return Err(ExitCode::Failure);
+        }
+    };
+    if let Some(bytes) = buffer_size {
+        syscall::ioctl(handle, SocketOption::RecvBufferSize as usize, bytes);
+        syscall::ioctl(handle, SocketOption::SendBufferSize as usize, bytes);
+    }
+    let rx = syscall::ioctl(handle, SocketOption::RecvBufferSize as usize, 0);
+    let tx = syscall::ioctl(handle, SocketOption::SendBufferSize as usize, 0);
+    debug!(
+        "Buffer sizes: {} (recv) {} (send)",
+        format_size(rx.unwrap_or(0)), format_size(tx.unwrap_or(0))
+    );
+
+    let res = if listen {
+        server(handle, port)
+    } else {
+        client(handle, host, port, size)
+    };
+    syscall::close(handle);
+    res
+}
+
+fn server(handle: usize, port: u16) -> Result<(), ExitCode> {
+    if syscall::listen(handle, port).is_err() {
+        error!("Could not listen to 0.0.0.0:{}", port);
+        return Err(ExitCode::Failure);
+    }
+    debug!("Listening to 0.0.0.0:{}", port);
+
+    // Accept times out after a few seconds without a connection
+    let addr = loop {
+        if console::end_of_text() || console::end_of_transmission() {
+            println!();
+            return Ok(());
+        }
+        if let Ok(addr) = syscall::accept(handle) {
+            break addr;
+        }
+    };
+    debug!("Connected to {}", addr);
+
+    let mut buf = vec![0; 64 << 10];
+    let mut total = 0;
+    let started = clock::boot_time();
+    loop {
+        if console::end_of_text() || console::end_of_transmission() {
+            println!();
+            break;
+        }
+        match syscall::read(handle, &mut buf) {
+            Some(0) => break,
+            Some(n) => total += n,
+            None if is_closed(handle) => break,
+            None => continue,
+        }
+    }
+    report("Received", total, clock::boot_time() - started);
+    Ok(())
+}
+
+fn client(
+    handle: usize, host: &str, port: u16, size: usize
+) -> Result<(), ExitCode> {
+    let addr = if let Ok(addr) = IpAddress::from_str(host) {
+        addr
+    } else {
+        match dns::resolve(host) {
+            Ok(addr) => addr,
+            Err(e) => {
+                error!("Could not resolve host: {:?}", e);
+                return Err(ExitCode::Failure);
+            }
+        }
+    };
+    if syscall::connect(handle, addr, port).is_err() {
+        error!("Could not connect to {}:{}", addr, port);
+        return Err(ExitCode::Failure);
+    }
+    debug!("Connected to {}:{}", addr, port);
+
+    let buf = vec![0; 64 << 10];
+    let mut total = 0;
+    let started = clock::boot_time();
+    while total < size {
+        if console::end_of_text() || console::end_of_transmission() {
+            println!();
+            break;
+        }
+        let n = (size - total).min(buf.len());
+        match syscall::write(handle, &buf[0..n]) {
+            Some(n) => total += n,
+            None => {
+                error!("Could not send data");
+                return Err(ExitCode::Failure);
+            }
+        }
+    }
+    report("Sent", total, clock::boot_time() - started);
+    Ok(())
+}
+
+fn is_closed(handle: usize) -> bool {
+    let mut buf = [0; 1]; // 1 byte status read
+    match syscall::read(handle, &mut buf) {
+        Some(1) => !buf[0].get_bit(SocketStatus::MayRecv as usize),
+        _ => true,
+    }
+}
+
+fn report(verb: &str, bytes: usize, secs: f64) {
+    let rate = if secs > 0.0 { bytes as f64 / secs } else { 0.0 };
+    println!(
+        "{} {} in {:.3} s ({}/s)",
+        verb, format_size(bytes), secs, format_size(rate as usize)
+    );
+}
+
+fn format_size(bytes: usize) -> String {
+    if bytes >= 10 << 20 {
+        format!("{} MB", bytes >> 20)
+    } else if bytes >= 10 << 10 {
+        format!("{} KB", bytes >> 10)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+// Parse a number of bytes with an optional K or M suffix
+fn parse_size(s: &str) -> Option<usize> {
+    let (num, unit) = match s.char_indices().last()? {
+        (i, 'K') | (i, 'k') => (&s[0..i], 1 << 10),
+        (i, 'M') | (i, 'm') => (&s[0..i], 1 << 20),
+        _ => (s, 1),
+    };
+    num.parse::<usize>().ok()?.checked_mul(unit)
+}
+
+fn help() {
+    let csi_option = Style::color("aqua");
+    let csi_title = Style::color("yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} tcpbench {}<options> [<host>][:<port>]{1}",
+        csi_title, csi_reset, csi_option
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-l{1}, {0}--listen{1}           Receive data on a local port",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-b{1}, {0}--buffer <size>{1}    Set socket buffer sizes",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-n{1}, {0}--bytes <size>{1}     Send {0}<size>{1} bytes",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_parse_size() {
+    assert_eq!(parse_size("1024"), Some(1024));
+    assert_eq!(parse_size("64K"), Some(64 << 10));
+    assert_eq!(parse_size("4m"), Some(4 << 20));
+    assert_eq!(parse_size("K"), None);
+    assert_eq!(parse_size("4G"), None);
+    assert_eq!(format_size(512), "512 B");
+    assert_eq!(format_size(64 << 10), "64 KB");
+    assert_eq!(format_size(4 << 20), "4096 KB");
+}
@@ -0,0 +1,472 @@
+use crate::api::console::Style;
+use crate::api::dns;
+use crate::api::fs;
+use crate::api::fs::FileIO;
+use crate::api::process::ExitCode;
+use crate::sys;
+use crate::sys::net::socket::udp::UdpSocket;
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::str::{self, FromStr};
+use smoltcp::wire::{IpAddress, IpEndpoint};
+
+// See https://www.rfc-editor.org/rfc/rfc1350
+// See https://www.rfc-editor.org/rfc/rfc2347
+// See https://www.rfc-editor.org/rfc/rfc2348
+
+pub const TFTP_PORT: u16 = 69;
+pub const DEFAULT_BLKSIZE: usize = 512;
+pub const MAX_BLKSIZE: usize = 1428; // Fits in an Ethernet frame
+pub const MIN_BLKSIZE: usize = 8;
+
+const TIMEOUT: f64 = 1.0;
+const MAX_RETRIES: usize = 5;
+
+const RRQ: u16 = 1;
+const WRQ: u16 = 2;
+const DATA: u16 = 3;
+const ACK: u16 = 4;
+const ERROR: u16 = 5;
+const OACK: u16 = 6;
+
+pub const ERR_UNDEFINED: u16 = 0;
+pub const ERR_NOT_FOUND: u16 = 1;
+pub const ERR_ACCESS: u16 = 2;
+pub const ERR_ILLEGAL: u16 = 4;
+pub const ERR_UNKNOWN_ID: u16 = 5;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Packet {
+    ReadRequest { filename: String, mode: String, blksize: Option<usize> },
+    WriteRequest { filename: String, mode: String, blksize: Option<usize> },
+    Data(u16, Vec<u8>),
+    Ack(u16),
+    Error(u16, String),
+    OptionAck(Option<usize>),
+}
+
+impl Packet {
+    pub fn parse(buf: &[u8]) -> Option<Self> {
+        if buf.len() < 2 {
+            return None;
+        }
+        let opcode = u16::from_be_bytes([buf[0], buf[1]]);
+        let mut fields = buf[2..].split(|&b| b == 0).map(str::from_utf8);
+        match opcode {
+            RRQ | WRQ => {
+                let filename = fields.next()?.ok()?.to_string();
+                let mode = fields.next()?.ok()?.to_lowercase();
+                let blksize = parse_blksize(fields);
+                if opcode == RRQ {
+                    Some(Packet::ReadRequest { filename, mode, blksize })
+                } else {
+                    Some(Packet::WriteRequest { filename, mode, blksize })
+                }
+            }
+            DATA if buf.len() >= 4 => {
+                let block = u16::from_be_bytes([buf[2], buf[3]]);
+                Some(Packet::Data(block, buf[4..].to_vec()))
+            }
+            ACK if buf.len() >= 4 => {
+                Some(Packet::Ack(u16::from_be_bytes([buf[2], buf[3]])))
+            }
+            ERROR if buf.len() >= 4 => {
+                let code = u16::from_be_bytes([buf[2], buf[3]]);
+                let msg = buf[4..].split(|&b| b == 0).next().unwrap_or(&[]);
+                Some(Packet::Error(code, String::from_utf8_lossy(msg).into()))
+            }
+            OACK => {
+                Some(Packet::OptionAck(parse_blksize(fields)))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        match self {
+            Packet::ReadRequest { filename, mode, blksize } |
+            Packet::WriteRequest { filename, mode, blksize } => {
+                let opcode = match self {
+                    Packet::ReadRequest { .. } => RRQ,
+                    _ => WRQ,
+                };
+                buf.extend_from_slice(&opcode.to_be_bytes());
+                buf.extend_from_slice(filename.as_bytes());
+                buf.push(0);
+                buf.extend_from_slice(mode.as_bytes());
+                buf.push(0);
+                push_blksize(&mut buf, *blksize);
+            }
+            Packet::Data(block, data) => {
+                buf.extend_from_slice(&DATA.to_be_bytes());
+                buf.extend_from_slice(&block.to_be_bytes());
+                buf.extend_from_slice(data);
+            }
+            Packet::Ack(block) => {
+                buf.extend_from_slice(&ACK.to_be_bytes());
+                buf.extend_from_slice(&block.to_be_bytes());
+            }
+            Packet::Error(code, msg) => {
+                buf.extend_from_slice(&ERROR.to_be_bytes());
+                buf.extend_from_slice(&code.to_be_bytes());
+                buf.extend_from_slice(msg.as_bytes());
+                buf.push(0);
+            }
+            Packet::OptionAck(blksize) => {
+                buf.extend_from_slice(&OACK.to_be_bytes());
+                push_blksize(&mut buf, *blksize);
+            }
+        }
+        buf
+    }
+}
+
+// Return the value of the blksize option ignoring the other options
+fn parse_blksize<'a, I>(mut fields: I) -> Option<usize>
+where
+    I: Iterator<Item = Result<&'a str, str::Utf8Error>>,
+{
+    let mut blksize = None;
+    while let (Some(Ok(key)), Some(Ok(val))) = (fields.next(), fields.next()) {
+        if key.eq_ignore_ascii_case("blksize") {
+            blksize = val.parse().ok();
+        }
+    }
+    blksize
+}
+
+fn push_blksize(buf: &mut Vec<u8>, blksize: Option<usize>) {
+    if let Some(blksize) = blksize {
+        buf.extend_from_slice(b"blksize\0");
+        buf.extend_from_slice(blksize.to_string().as_bytes());
+        buf.push(0);
+    }
+}
+
+// A transfer between two endpoints identified by their ports
+pub struct Connection {
+    socket: UdpSocket,
+    peer: IpEndpoint,
+    is_connected: bool,
+    pub blksize: usize,
+}
+
+impl Connection {
+    // The client sends its request to the well known port of the server
+    // and learns the port chosen by the server from the first response.
+    pub fn new(peer: IpEndpoint, is_connected: bool) -> Option<Self> {
+        let mut socket = UdpSocket::new();
+        socket.connect(peer.addr, peer.port).ok()?;
+        let blksize = DEFAULT_BLKSIZE;
+        Some(Self { socket, peer, is_connected, blksize })
+    }
+
+    pub fn send(&mut self, packet: &Packet) -> bool {
+        let buf = packet.to_bytes();
+        self.socket.send_to(&buf, self.peer).is_some()
+    }
+
+    // Return the next packet received from the peer before the timeout
+    fn recv(&mut self) -> Option<Packet> {
+        let mut buf = [0; MAX_BLKSIZE + 4];
+        let started = sys::clk::epoch_time();
+        loop {
+            let timeout = TIMEOUT - (sys::clk::epoch_time() - started);
+            if timeout < 0.0 {
+                return None;
+            }
+            let (n, endpoint) = self.socket.recv_from(&mut buf, timeout)?;
+            if endpoint != self.peer {
+                if self.is_connected || endpoint.addr != self.peer.addr {
+                    let msg = "Unknown transfer ID".into();
+                    let err = Packet::Error(ERR_UNKNOWN_ID, msg);
+                    self.socket.send_to(&err.to_bytes(), endpoint);
+                    continue;
+                }
+                self.peer = endpoint;
+                self.is_connected = true;
+            }
+            if let Some(packet) = Packet::parse(&buf[0..n]) {
+                return Some(packet);
+            }
+        }
+    }
+
+    // Send the data in blocks after an optional request, waiting for each
+    // block to be acknowledged before sending the next one.
+    pub fn send_data(
+        &mut self, data: &[u8], request: Option<Packet>
+    ) -> Result<(), String> {
+        let mut block = 0u16;
+        let mut offset = 0;
+        let mut is_done = false;
+        let mut send_next = request.is_none();
+        let mut last = request.unwrap_or(Packet::Ack(0));
+        let mut retries = 0;
+        if !send_next {
+            self.send(&last);
+        }
+        loop {
+            if send_next {
+                if is_done {
+                    return Ok(());
+                }
+                let n = self.blksize.min(data.len() - offset);
+                block = block.wrapping_add(1);
+                last = Packet::Data(block, data[offset..(offset + n)].to_vec());
+                offset += n;
+                is_done = n < self.blksize;
+                self.send(&last);
+                send_next = false;
+                retries = 0;
+            }
+            match self.recv() {
+                Some(Packet::Ack(n)) if n == block => {
+                    send_next = true;
+                }
+                Some(Packet::OptionAck(blksize)) if block == 0 => {
+                    self.blksize = blksize.unwrap_or(DEFAULT_BLKSIZE);
+                    send_next = true;
+                }
+                Some(Packet::Error(_, msg)) => {
+                    return Err(msg);
+                }
+                Some(_) => {} // Duplicated acknowledgements are ignored
+                None => {
+                    retries += 1;
+                    if retries > MAX_RETRIES {
+                        return Err("Timeout".into());
+                    }
+                    self.send(&last);
+                }
+            }
+        }
+    }
+
+    // Receive the data in blocks after sending a request or the first
+    // acknowledgement, until a block smaller than the block size.
+    pub fn recv_data(&mut self, request: Packet) -> Result<Vec<u8>, String> {
+        let mut data = Vec::new();
+        let mut block = 1u16;
+        let mut last = request;
+        let mut retries = 0;
+        self.send(&last);
+        loop {
+            match self.recv() {
+                Some(Packet::Data(n, buf)) if n == block => {
+                    data.extend_from_slice(&buf);
+                    last = Packet::Ack(n);
+                    self.send(&last);
+                    if buf.len() < self.blksize {
+                        return Ok(data);
+                    }
+                    block = block.wrapping_add(1);
+                    retries = 0;
+                }
+                Some(Packet::Data(_, _)) => {
+                    self.send(&last); // The last acknowledgement was lost
+                }
+                Some(Packet::OptionAck(blksize)) if data.is_empty() => {
+                    self.blksize = blksize.unwrap_or(DEFAULT_BLKSIZE);
+                    last = Packet::Ack(0);
+                    self.send(&last);
+                }
+                Some(Packet::Error(_, msg)) => {
+                    return Err(msg);
+                }
+                Some(_) => {}
+                None => {
+                    retries += 1;
+                    if retries > MAX_RETRIES {
+                        return Err("Timeout".into());
+                    }
+                    self.send(&last);
+                }
+            }
+        }
+    }
+
+    pub fn close(&mut self) {
+        self.socket.close();
+    }
+}
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut port = TFTP_PORT;
+    let mut blksize = MAX_BLKSIZE;
+    let mut params = Vec::new();
+    let mut i = 1;
+    let n = args.len();
+    while i < n {
+        match args[i] {
+            "-h" | "--help" => {
+                help();
+                return Ok(());
+            }
+            "-p" | "--port" if i + 1 < n => {
+                i += 1;
+                port = args[i].parse().unwrap_or(port);
+            }
+            "-b" | "--blksize" if i + 1 < n => {
+                i += 1;
+                match args[i].parse() {
+                    Ok(n) if (MIN_BLKSIZE..=MAX_BLKSIZE).contains(&n) => {
+                        blksize = n;
+                    }
+                    _ => {
+                        error!("Invalid block size");
+                        return Err(ExitCode::UsageError);
+                    }
+                }
+            }
+            arg => {
+                params.push(arg);
+            }
+        }
+        i += 1;
+    }
+    if params.len() < 3 || params.len() > 4 {
+        help();
+        return Err(ExitCode::UsageError);
+    }
+
+    let host = params[1];
+    let addr = if let Ok(addr) = IpAddress::from_str(host) {
+        addr
+    } else {
+        match dns::resolve(host) {
+            Ok(addr) => addr,
+            Err(e) => {
+                error!("Could not resolve host: {:?}", e);
+                return Err(ExitCode::Failure);
+            }
+        }
+    };
+    let mut conn = match Connection::new(IpEndpoint::new(addr, port), false) {
+        Some(conn) => conn,
+        None => {
+            error!("Could not connect to {}:{}", addr, port);
+            return Err(ExitCode::Failure);
+        }
+    };
+    let mode = "octet".to_string();
+    let blksize = if blksize == DEFAULT_BLKSIZE { None } else { Some(blksize) };
+
+    let res = match params[0] {
+        "get" => {
+            let filename = params[2].to_string();
+            let name = fs::filename(params[2]);
+            let path = params.get(3).copied().unwrap_or(name);
+            let req = Packet::ReadRequest { filename, mode, blksize };
+            match conn.recv_data(req) {
+                Ok(data) => {
+                    if fs::write(path, &data).is_ok() {
+                        println!("Received {} bytes", data.len());
+                        Ok(())
+                    } else {
+                        error!("Could not write to '{}'", path);
+                        Err(ExitCode::Failure)
+                    }
+                }
+                Err(msg) => {
+                    error!("Could not get '{}': {}", params[2], msg);
+                    Err(ExitCode::Failure)
+                }
+            }
+        }
+        "put" => {
+            let path = params[2];
+            let filename = params.get(3).copied().unwrap_or(fs::filename(path));
+            if let Ok(data) = fs::read_to_bytes(path) {
+                let filename = filename.to_string();
+                let req = Packet::WriteRequest { filename, mode, blksize };
+                match conn.send_data(&data, Some(req)) {
+                    Ok(()) => {
+                        println!("Sent {} bytes", data.len());
+                        Ok(())
+                    }
+                    Err(msg) => {
+                        error!("Could not put '{}': {}", path, msg);
+                        Err(ExitCode::Failure)
+                    }
+                }
+            } else {
+                error!("Could not read '{}'", path);
+                Err(ExitCode::Failure)
+            }
+        }
+        _ => {
+            help();
+            Err(ExitCode::UsageError)
+        }
+    };
+    conn.close();
+    res
+}
+
+fn help() {
+    let csi_option = Style::color("aqua");
+    let csi_title = Style::color("yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} tftp {}<options> <command> <host> <file> [<file>]{1}",
+        csi_title, csi_reset, csi_option
+    );
+    println!();
+    println!("{}Commands:{}", csi_title, csi_reset);
+    println!(
+        "  {}get{} <host> <remote> [<local>]    Download a file",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {}put{} <host> <local> [<remote>]    Upload a file",
+        csi_option, csi_reset
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-p{1}, {0}--port <number>{1}    Connect to a specific port",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-b{1}, {0}--blksize <size>{1}   Negotiate the block size",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_tftp_packet() {
+    let req = Packet::ReadRequest {
+        filename: "boot.img".into(),
+        mode: "octet".into(),
+        blksize: Some(1428),
+    };
+    let buf = req.to_bytes();
+    assert_eq!(buf, b"\x00\x01boot.img\x00octet\x00blksize\x001428\x00");
+    assert_eq!(Packet::parse(&buf), Some(req));
+
+    let buf = b"\x00\x02a.txt\x00OCTET\x00tsize\x000\x00";
+    let req = Packet::parse(buf).unwrap();
+    assert_eq!(req, Packet::WriteRequest {
+        filename: "a.txt".into(),
+        mode: "octet".into(),
+        blksize: None,
+    });
+
+    let packets = [
+        Packet::Data(1, b"hello".to_vec()),
+        Packet::Data(2, Vec::new()),
+        Packet::Ack(65535),
+        Packet::Error(ERR_NOT_FOUND, "File not found".into()),
+        Packet::OptionAck(Some(512)),
+        Packet::OptionAck(None),
+    ];
+    for packet in packets {
+        assert_eq!(Packet::parse(&packet.to_bytes()), Some(packet));
+    }
+    assert_eq!(Packet::parse(b"\x00\x03\x00"), None);
+    assert_eq!(Packet::parse(b"\x00\x07"), None);
+}
@@ -0,0 +1,203 @@
+use crate::api::console::Style;
+use crate::api::fs;
+use crate::api::fs::FileIO;
+use crate::api::process::ExitCode;
+use crate::sys;
+use crate::sys::console;
+use crate::sys::net::socket::udp::UdpSocket;
+use crate::usr::tftp::{Connection, Packet, MAX_BLKSIZE, MIN_BLKSIZE};
+use crate::usr::tftp::{ERR_ACCESS, ERR_ILLEGAL, ERR_NOT_FOUND, ERR_UNDEFINED};
+use crate::usr::tftp::TFTP_PORT;
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use smoltcp::wire::IpEndpoint;
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let csi_color = Style::color("yellow");
+    let csi_reset = Style::reset();
+    let mut read_only = false;
+    let mut port = TFTP_PORT;
+    let mut dir = sys::process::dir();
+    let mut i = 1;
+    let n = args.len();
+    while i < n {
+        match args[i] {
+            "-h" | "--help" => {
+                usage();
+                return Ok(());
+            }
+            "-r" | "--read-only" => {
+                read_only = true;
+            }
+            "-p" | "--port" => {
+                if i + 1 < n {
+                    i += 1;
+                    port = args[i].parse().unwrap_or(port);
+                } else {
+                    error!("Missing port number");
+                    return Err(ExitCode::UsageError);
+                }
+            }
+            "-d" | "--dir" => {
+                if i + 1 < n {
+                    i += 1;
+                    dir = args[i].to_string();
+                } else {
+                    error!("Missing directory");
+                    return Err(ExitCode::UsageError);
+                }
+            }
+            _ => {
+                usage();
+                return Err(ExitCode::UsageError);
+            }
+        }
+        i += 1;
+    }
+    let dir = format!("/{}", fs::realpath(&dir).trim_matches('/'));
+
+    let mut socket = UdpSocket::new();
+    if socket.listen(port).is_err() {
+        error!("Could not listen to 0.0.0.0:{}", port);
+        return Err(ExitCode::Failure);
+    }
+    println!(
+        "{}TFTP Server listening on 0.0.0.0:{}{}",
+        csi_color, port, csi_reset
+    );
+
+    let mut buf = [0; MAX_BLKSIZE + 4];
+    loop {
+        if console::end_of_text() || console::end_of_transmission() {
+            println!();
+            socket.close();
+            return Ok(());
+        }
+        // The transfers are made one at a time from another port
+        if let Some((n, endpoint)) = socket.recv_from(&mut buf, 0.1) {
+            if let Some(req) = Packet::parse(&buf[0..n]) {
+                serve(&dir, read_only, req, endpoint);
+            }
+        }
+    }
+}
+
+fn serve(dir: &str, read_only: bool, req: Packet, endpoint: IpEndpoint) {
+    let csi_color = Style::color("aqua");
+    let csi_reset = Style::reset();
+    let mut conn = match Connection::new(endpoint, true) {
+        Some(conn) => conn,
+        None => return,
+    };
+    let (method, res) = match req {
+        Packet::ReadRequest { filename, mode, blksize } => {
+            let res = match open(dir, &filename, &mode) {
+                Ok(path) => match fs::read_to_bytes(&path) {
+                    Ok(data) => {
+                        let oack = negotiate(&mut conn, blksize);
+                        conn.send_data(&data, oack).map(|_| data.len())
+                    }
+                    Err(_) => {
+                        let msg = "File not found".into();
+                        Err(error(&mut conn, Packet::Error(ERR_NOT_FOUND, msg)))
+                    }
+                },
+                Err(err) => Err(error(&mut conn, err)),
+            };
+            (format!("GET {}", filename), res)
+        }
+        Packet::WriteRequest { filename, mode, blksize } => {
+            let res = match open(dir, &filename, &mode) {
+                Ok(_) if read_only => {
+                    let msg = "Read-only server";
+                    Err(error(&mut conn, Packet::Error(ERR_ACCESS, msg.into())))
+                }
+                Ok(path) => {
+                    let oack = negotiate(&mut conn, blksize);
+                    let ack = oack.unwrap_or(Packet::Ack(0));
+                    match conn.recv_data(ack) {
+                        Ok(data) if fs::write(&path, &data).is_ok() => {
+                            Ok(data.len())
+                        }
+                        Ok(_) => Err("Could not write file".into()),
+                        Err(msg) => Err(msg),
+                    }
+                }
+                Err(err) => Err(error(&mut conn, err)),
+            };
+            (format!("PUT {}", filename), res)
+        }
+        _ => {
+            let msg = "Illegal operation";
+            conn.send(&Packet::Error(ERR_ILLEGAL, msg.into()));
+            (String::from("???"), Err(msg.into()))
+        }
+    };
+    conn.close();
+
+    match res {
+        Ok(n) => println!(
+            "{}{}{} {} ({} bytes)",
+            csi_color, endpoint, csi_reset, method, n
+        ),
+        Err(msg) => println!(
+            "{}{}{} {} ({})",
+            csi_color, endpoint, csi_reset, method, msg
+        ),
+    }
+}
+
+// Return the path of the file requested inside the directory of the server
+fn open(dir: &str, filename: &str, mode: &str) -> Result<String, Packet> {
+    if mode != "octet" {
+        let msg = "Only octet mode is supported";
+        return Err(Packet::Error(ERR_UNDEFINED, msg.into()));
+    }
+    if filename.split('/').any(|name| name == "..") {
+        let msg = "Access violation";
+        return Err(Packet::Error(ERR_ACCESS, msg.into()));
+    }
+    let filename = filename.trim_start_matches('/');
+    let sep = if dir == "/" { "" } else { "/" };
+    Ok(format!("{}{}{}", dir, sep, filename))
+}
+
+// Accept the block size requested by the client within the supported range
+fn negotiate(conn: &mut Connection, blksize: Option<usize>) -> Option<Packet> {
+    let blksize = blksize?.clamp(MIN_BLKSIZE, MAX_BLKSIZE);
+    conn.blksize = blksize;
+    Some(Packet::OptionAck(Some(blksize)))
+}
+
+fn error(conn: &mut Connection, err: Packet) -> String {
+    conn.send(&err);
+    match err {
+        Packet::Error(_, msg) => msg,
+        _ => String::new(),
+    }
+}
+
+fn usage() {
+    let csi_option = Style::color("aqua");
+    let csi_title = Style::color("yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} tftpd {}<options>{1}",
+        csi_title, csi_reset, csi_option
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-d{1}, {0}--dir <path>{1}       Set directory to {0}<path>{1}",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-p{1}, {0}--port <number>{1}    Listen to a specific port",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-r{1}, {0}--read-only{1}        Refuse write requests",
+        csi_option, csi_reset
+    );
+}
@@ -15,7 +15,7 @@ use core::convert::TryInto;
 use core::str;
 use sha2::Sha256;
 
-const USERS: &str = "/ini/users.csv";
+pub const USERS: &str = "/ini/users.csv";
 const DISABLE_EMPTY_PASSWORD: bool = false;
 
 pub fn main(args: &[&str]) -> Result<(), ExitCode> {
@@ -136,6 +136,14 @@ fn create(username: &str) -> Result<(), ExitCode> {
     Ok(())
 }
 
+// Check the password of a user found in the file of hashed passwords
+pub fn check_password(username: &str, password: &str) -> bool {
+    match hashed_password(username) {
+        Some(hash) => check(password, &hash).is_ok(),
+        None => false,
+    }
+}
+
 fn check(password: &str, hashed_password: &str) -> Result<(), ()> {
     let fields: Vec<_> = hashed_password.split('$').collect();
     if fields.len() != 4 || fields[0] != "1" {